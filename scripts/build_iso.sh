@@ -22,6 +22,12 @@ run() {
 cp ./grub/grub.cfg ./build/iso/boot/grub && \
 log "Successfully copied GRUB config to build/iso/boot/grub"
 
+# Boots another menu entry than the first one, like the serial console for the shell tests.
+if [ -n "$GRUB_DEFAULT" ]
+then
+    sed -i "s/^set default=.*/set default=$GRUB_DEFAULT/" ./build/iso/boot/grub/grub.cfg
+fi
+
 cp $path ./build/iso/boot/kernel.bin && \
 log "Successfully copied $path to ./build/iso/kernel.bin"
 
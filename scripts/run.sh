@@ -4,7 +4,17 @@ ISO=$1
 shift
 QEMU_ARGS="$@"
 
-qemu-system-i386 -cdrom $ISO $QEMU_ARGS
+# Raw disk image attached as the primary master, created empty on first use.
+DISK=${DISK:-build/disk.img}
+DISK_SIZE=${DISK_SIZE:-16M}
+
+if [ ! -f "$DISK" ]
+then
+    mkdir -p "$(dirname "$DISK")"
+    truncate -s $DISK_SIZE "$DISK"
+fi
+
+qemu-system-i386 -cdrom $ISO -hda "$DISK" $QEMU_ARGS
 
 if [ $? -eq 33 ]
 then
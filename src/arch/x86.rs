@@ -5,3 +5,4 @@
 pub mod gdt;
 pub mod idt;
 pub mod interrupts;
+pub mod pit;
@@ -140,7 +140,7 @@ unsafe extern "C" fn irq_handler(regs: &InterruptRegisters) {
 /// This panics if irq_line is bigger than 15
 pub fn set_mask(mut irq_line: u8) {
     assert!(irq_line < 16);
-    let mut port = Port::new(if let 0..8 = irq_line {
+    let mut port = Port::<u8>::new(if let 0..8 = irq_line {
         PIC1_DATA
     } else {
         irq_line -= 8;
@@ -160,7 +160,7 @@ pub fn set_mask(mut irq_line: u8) {
 /// This panics if irq_line is bigger than 15
 pub fn clear_mask(mut irq_line: u8) {
     assert!(irq_line < 16);
-    let mut port = Port::new(if let 0..8 = irq_line {
+    let mut port = Port::<u8>::new(if let 0..8 = irq_line {
         PIC1_DATA
     } else {
         irq_line -= 8;
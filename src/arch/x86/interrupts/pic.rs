@@ -93,7 +93,7 @@ pub fn disable() {
     // We are writing to the PIC1/PIC2 ports, which we assume to be safe.
     #[allow(clippy::multiple_unsafe_ops_per_block)]
     unsafe {
-        Port::new(PIC1_DATA as u16).write(0xff);
-        Port::new(PIC2_DATA as u16).write(0xff);
+        Port::new(PIC1_DATA as u16).write(0xff_u8);
+        Port::new(PIC2_DATA as u16).write(0xff_u8);
     };
 }
@@ -0,0 +1,59 @@
+//! Programmable Interval Timer, used as the kernel's monotonic clock.
+//!
+//! Channel 0 is programmed to fire IRQ0 every millisecond, and each interrupt
+//! increments the uptime counter.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{
+    arch::x86::{idt::InterruptRegisters, interrupts::irq},
+    port::Port,
+};
+
+const CHANNEL_0: u16 = 0x40;
+const COMMAND: u16 = 0x43;
+
+/// Frequency of the PIT's input clock.
+const BASE_FREQUENCY: u32 = 1_193_182;
+
+/// Frequency at which IRQ0 is raised.
+pub const FREQUENCY: u32 = 1000;
+
+/// Channel 0, lobyte/hibyte access, mode 3 (square wave generator), binary.
+const SQUARE_WAVE_CHANNEL_0: u8 = 0b0011_0110;
+
+/// Milliseconds since [`init`]. Wraps after ~49 days.
+static UPTIME_MS: AtomicU32 = AtomicU32::new(0);
+
+/// IRQ0
+extern "C" fn timer_interrupt_handler(_regs: &InterruptRegisters) {
+    UPTIME_MS.fetch_add(1000 / FREQUENCY, Ordering::Relaxed);
+}
+
+/// Returns the number of milliseconds elapsed since [`init`], or 0 if the timer is not
+/// initialized yet.
+#[must_use]
+pub fn uptime_ms() -> u32 {
+    UPTIME_MS.load(Ordering::Relaxed)
+}
+
+pub fn init() {
+    let divisor = (BASE_FREQUENCY / FREQUENCY) as u16;
+
+    // SAFETY:
+    // Ports 0x43 and 0x40 are the PIT's command and channel 0 data ports, programming them only
+    // affects the rate at which IRQ0 fires.
+    unsafe { Port::new(COMMAND).write(SQUARE_WAVE_CHANNEL_0) };
+
+    let mut channel_0 = Port::new(CHANNEL_0);
+    for byte in divisor.to_le_bytes() {
+        // SAFETY:
+        // The command above selected lobyte/hibyte access, so the divisor is written in two
+        // consecutive writes.
+        unsafe { channel_0.write(byte) };
+    }
+
+    irq::install_handler(0, timer_interrupt_handler);
+
+    irq::clear_mask(0);
+}
@@ -0,0 +1,411 @@
+//! ATA PIO driver.
+//!
+//! Drives are found behind the IDE controller reported by the PCI bus, on its
+//! two channels, as a master and a slave each. Transfers are polled, one sector
+//! at a time through the 16-bit data register, and the drive interrupts are
+//! kept disabled.
+//!
+//! Sectors below 2^28 are accessed with the 28-bit commands, the others with
+//! the 48-bit ones when the drive supports them.
+//!
+//! https://wiki.osdev.org/ATA_PIO_Mode
+
+use crate::{
+    block::{self, BlockDevice, BlockError, SECTOR_SIZE},
+    pci::{
+        Bar, Device,
+        class::{IDE_CONTROLLER, MASS_STORAGE},
+        driver::{self, DeviceId, Driver, ProbeError},
+    },
+    port::Port,
+    pr_info, pr_warn,
+    registry::Registry,
+};
+
+pub mod identify;
+
+use identify::{IdentifyData, Identity};
+
+const DATA: u16 = 0;
+const ERROR: u16 = 1;
+const SECTOR_COUNT: u16 = 2;
+const LBA_LOW: u16 = 3;
+const LBA_MID: u16 = 4;
+const LBA_HIGH: u16 = 5;
+const DRIVE_SELECT: u16 = 6;
+const STATUS: u16 = 7;
+const COMMAND: u16 = 7;
+
+const STATUS_ERROR: u8 = 1 << 0;
+const STATUS_DATA_REQUEST: u8 = 1 << 3;
+const STATUS_DEVICE_FAULT: u8 = 1 << 5;
+const STATUS_BUSY: u8 = 1 << 7;
+
+/// Device control register bits.
+const CONTROL_NO_INTERRUPT: u8 = 1 << 1;
+const CONTROL_RESET: u8 = 1 << 2;
+
+const SELECT_LBA: u8 = 0xe0;
+const SELECT_LBA48: u8 = 0x40;
+const SELECT_SLAVE: u8 = 1 << 4;
+
+const READ_SECTORS: u8 = 0x20;
+const READ_SECTORS_EXT: u8 = 0x24;
+const WRITE_SECTORS: u8 = 0x30;
+const WRITE_SECTORS_EXT: u8 = 0x34;
+const CACHE_FLUSH: u8 = 0xe7;
+const CACHE_FLUSH_EXT: u8 = 0xea;
+const IDENTIFY: u8 = 0xec;
+
+/// Status read when no drive drives the bus.
+const FLOATING_BUS: u8 = 0xff;
+
+/// Signature left in LBA mid and high by ATAPI drives, which do not answer IDENTIFY.
+const ATAPI_SIGNATURE: (u8, u8) = (0x14, 0xeb);
+
+/// Number of status polls before a drive is considered unresponsive.
+const TIMEOUT: usize = 1_000_000;
+
+/// Highest sector reachable with the 28-bit commands, plus one.
+const LBA28_LIMIT: u64 = 1 << 28;
+
+/// Sectors transferred by a single command.
+const SECTORS_PER_COMMAND: usize = 256;
+
+/// Legacy ports, used by controllers in compatibility mode.
+const PRIMARY: Channel = Channel::new(0x1f0, 0x3f6);
+const SECONDARY: Channel = Channel::new(0x170, 0x376);
+
+/// Set in the programming interface when a channel uses the PCI BARs instead of the legacy
+/// ports.
+const PRIMARY_NATIVE: u8 = 1 << 0;
+const SECONDARY_NATIVE: u8 = 1 << 2;
+
+const IDS: [DeviceId; 1] = [DeviceId::class(MASS_STORAGE, IDE_CONTROLLER)];
+
+/// Names of the drives, in the order they are found, for up to two controllers.
+const NAMES: [&str; 8] = ["hda", "hdb", "hdc", "hdd", "hde", "hdf", "hdg", "hdh"];
+
+pub const MAX_DRIVES: usize = NAMES.len();
+
+/// Command and control registers of a channel.
+#[derive(Clone, Copy, Debug)]
+struct Channel {
+    base: u16,
+    control: u16,
+}
+
+impl Channel {
+    const fn new(base: u16, control: u16) -> Self {
+        Self { base, control }
+    }
+
+    /// Returns the channel described by two BARs, or the legacy one if they are not I/O BARs.
+    fn from_bars(base: Option<Bar>, control: Option<Bar>, legacy: Self) -> Self {
+        match (base, control) {
+            // The device control register is the third port of the control block.
+            (Some(Bar::Io { port: base, .. }), Some(Bar::Io { port: control, .. })) => Self::new(base, control + 2),
+            _ => legacy,
+        }
+    }
+
+    fn read(&self, register: u16) -> u8 {
+        // SAFETY:
+        // The command block registers are read without side effects, except for the status
+        // register acknowledging the drive's interrupt, which is disabled.
+        unsafe { Port::new(self.base + register).read() }
+    }
+
+    fn write(&self, register: u16, value: u8) {
+        // SAFETY:
+        // The command block registers only affect the drive, which is idle when written.
+        unsafe { Port::new(self.base + register).write(value) };
+    }
+
+    fn set_control(&self, value: u8) {
+        // SAFETY:
+        // Writing the device control register only resets the channel or masks its IRQ.
+        unsafe { Port::write_only(self.control).write(value) };
+    }
+
+    /// Waits the 400ns the drive needs to update its status, by reading the alternate status
+    /// register, which has no side effect.
+    fn delay(&self) {
+        for _ in 0..4 {
+            // SAFETY:
+            // Reading the alternate status register has no side effect.
+            let _: u8 = unsafe { Port::read_only(self.control).read() };
+        }
+    }
+
+    /// Resets both drives of the channel, disables their interrupts, and waits for them to be
+    /// ready, returning `false` if no drive answers.
+    fn reset(&self) -> bool {
+        self.set_control(CONTROL_RESET | CONTROL_NO_INTERRUPT);
+        self.delay();
+        self.set_control(CONTROL_NO_INTERRUPT);
+        self.delay();
+
+        self.read(STATUS) != FLOATING_BUS && self.wait_not_busy().is_ok()
+    }
+
+    fn wait_not_busy(&self) -> Result<u8, BlockError> {
+        (0..TIMEOUT)
+            .map(|_| self.read(STATUS))
+            .find(|status| status & STATUS_BUSY == 0)
+            .ok_or(BlockError::Timeout)
+    }
+
+    /// Waits for the drive to be ready to transfer a sector.
+    fn wait_data(&self) -> Result<(), BlockError> {
+        for _ in 0..TIMEOUT {
+            let status = self.read(STATUS);
+            if status & STATUS_BUSY != 0 {
+                continue;
+            }
+            if status & (STATUS_ERROR | STATUS_DEVICE_FAULT) != 0 {
+                return Err(BlockError::Device(self.read(ERROR)));
+            }
+            if status & STATUS_DATA_REQUEST != 0 {
+                return Ok(());
+            }
+        }
+        Err(BlockError::Timeout)
+    }
+
+    fn select(&self, value: u8) {
+        self.write(DRIVE_SELECT, value);
+        self.delay();
+    }
+
+    /// Sends `command` for `count` sectors from `lba`, `count` being at most
+    /// [`SECTORS_PER_COMMAND`].
+    fn command(&self, slave: bool, lba: u64, count: usize, lba48: bool, command: u8) -> Result<(), BlockError> {
+        self.wait_not_busy()?;
+        let slave = if slave { SELECT_SLAVE } else { 0 };
+        // The 28-bit commands take a count of 0 as 256 sectors, so truncating is enough.
+        let count = (count as u16).to_le_bytes();
+        let lba = lba.to_le_bytes();
+
+        if lba48 {
+            self.select(SELECT_LBA48 | slave);
+            self.write(SECTOR_COUNT, count[1]);
+            self.write(LBA_LOW, lba[3]);
+            self.write(LBA_MID, lba[4]);
+            self.write(LBA_HIGH, lba[5]);
+        } else {
+            self.select(SELECT_LBA | slave | (lba[3] & 0x0f));
+        }
+        self.write(SECTOR_COUNT, count[0]);
+        self.write(LBA_LOW, lba[0]);
+        self.write(LBA_MID, lba[1]);
+        self.write(LBA_HIGH, lba[2]);
+        self.write(COMMAND, command);
+        self.delay();
+        Ok(())
+    }
+
+    fn read_sector(&self, buf: &mut [u8]) {
+        let mut words = [0; SECTOR_SIZE / 2];
+        // SAFETY:
+        // The drive requested the transfer of a sector, which is 256 words.
+        unsafe { Port::<u16>::new(self.base + DATA).read_string(&mut words) };
+        for (word, bytes) in words.iter().zip(buf.chunks_exact_mut(2)) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    fn write_sector(&self, buf: &[u8]) {
+        let mut words = [0; SECTOR_SIZE / 2];
+        for (word, bytes) in words.iter_mut().zip(buf.chunks_exact(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        // SAFETY:
+        // The drive requested the transfer of a sector, which is 256 words.
+        unsafe { Port::<u16>::new(self.base + DATA).write_string(&words) };
+    }
+
+    /// Identifies the master or slave drive, returning `None` if there is no ATA drive.
+    fn identify(&self, slave: bool) -> Option<Identity> {
+        self.select(SELECT_LBA | if slave { SELECT_SLAVE } else { 0 });
+        self.write(SECTOR_COUNT, 0);
+        self.write(LBA_LOW, 0);
+        self.write(LBA_MID, 0);
+        self.write(LBA_HIGH, 0);
+        self.write(COMMAND, IDENTIFY);
+        self.delay();
+
+        if let 0 | FLOATING_BUS = self.read(STATUS) {
+            return None;
+        }
+        self.wait_not_busy().ok()?;
+        if (self.read(LBA_MID), self.read(LBA_HIGH)) == ATAPI_SIGNATURE {
+            return None;
+        }
+        self.wait_data().ok()?;
+
+        let mut data: IdentifyData = [0; 256];
+        // SAFETY:
+        // The drive has the 256 words of its IDENTIFY data ready.
+        unsafe { Port::<u16>::new(self.base + DATA).read_string(&mut data) };
+        Some(Identity::parse(&data))
+    }
+}
+
+/// ATA hard drive.
+#[derive(Debug)]
+pub struct Drive {
+    name: &'static str,
+    channel: Channel,
+    slave: bool,
+    identity: Identity,
+}
+
+impl Drive {
+    #[must_use]
+    pub const fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// Returns whether the sectors before `end` need the 48-bit commands.
+    fn needs_lba48(&self, end: u64) -> Result<bool, BlockError> {
+        match (end > LBA28_LIMIT, self.identity.lba48) {
+            (false, _) => Ok(false),
+            (true, true) => Ok(true),
+            (true, false) => Err(BlockError::OutOfRange),
+        }
+    }
+}
+
+impl BlockDevice for Drive {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.identity.sectors
+    }
+
+    fn read(&self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_range(self.sector_count(), lba, buf)?;
+
+        for (i, chunk) in buf.chunks_mut(SECTORS_PER_COMMAND * SECTOR_SIZE).enumerate() {
+            let lba = lba + (i * SECTORS_PER_COMMAND) as u64;
+            let count = chunk.len() / SECTOR_SIZE;
+            let lba48 = self.needs_lba48(lba + count as u64)?;
+
+            let command = if lba48 { READ_SECTORS_EXT } else { READ_SECTORS };
+            self.channel.command(self.slave, lba, count, lba48, command)?;
+            for sector in chunk.chunks_exact_mut(SECTOR_SIZE) {
+                self.channel.wait_data()?;
+                self.channel.read_sector(sector);
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&self, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+        block::check_range(self.sector_count(), lba, buf)?;
+
+        for (i, chunk) in buf.chunks(SECTORS_PER_COMMAND * SECTOR_SIZE).enumerate() {
+            let lba = lba + (i * SECTORS_PER_COMMAND) as u64;
+            let count = chunk.len() / SECTOR_SIZE;
+            let lba48 = self.needs_lba48(lba + count as u64)?;
+
+            let command = if lba48 { WRITE_SECTORS_EXT } else { WRITE_SECTORS };
+            self.channel.command(self.slave, lba, count, lba48, command)?;
+            for sector in chunk.chunks_exact(SECTOR_SIZE) {
+                self.channel.wait_data()?;
+                self.channel.write_sector(sector);
+            }
+        }
+        self.flush()
+    }
+
+    fn flush(&self) -> Result<(), BlockError> {
+        // The drive stays busy while it commits the last sector written.
+        self.channel.wait_not_busy()?;
+        let command = if self.identity.lba48 { CACHE_FLUSH_EXT } else { CACHE_FLUSH };
+        self.channel.select(SELECT_LBA | if self.slave { SELECT_SLAVE } else { 0 });
+        self.channel.write(COMMAND, command);
+        self.channel.delay();
+
+        let status = self.channel.wait_not_busy()?;
+        if status & (STATUS_ERROR | STATUS_DEVICE_FAULT) != 0 {
+            return Err(BlockError::Device(self.channel.read(ERROR)));
+        }
+        Ok(())
+    }
+}
+
+static DRIVES: Registry<Drive, MAX_DRIVES> = Registry::new();
+
+/// Returns the drives found, in the order of their names.
+pub fn drives() -> impl Iterator<Item = &'static Drive> {
+    DRIVES.iter()
+}
+
+/// Detects the drives of the controller's channels, and registers them as block devices.
+///
+/// Channels whose drives are already known, such as the legacy ones shared by controllers in
+/// compatibility mode, are skipped.
+fn probe(device: &Device) -> Result<(), ProbeError> {
+    let primary = if device.prog_if & PRIMARY_NATIVE != 0 {
+        Channel::from_bars(device.bars[0], device.bars[1], PRIMARY)
+    } else {
+        PRIMARY
+    };
+    let secondary = if device.prog_if & SECONDARY_NATIVE != 0 {
+        Channel::from_bars(device.bars[2], device.bars[3], SECONDARY)
+    } else {
+        SECONDARY
+    };
+
+    let mut found = false;
+    'channels: for channel in [primary, secondary] {
+        if DRIVES.iter().any(|drive| drive.channel.base == channel.base) || !channel.reset() {
+            continue;
+        }
+
+        for slave in [false, true] {
+            let Some(identity) = channel.identify(slave) else {
+                continue;
+            };
+            // Drives are never removed, so the next name is the one after the last drive's.
+            let Some(&name) = NAMES.get(DRIVES.iter().count()) else {
+                pr_warn!("ata: too many drives");
+                break 'channels;
+            };
+
+            pr_info!(
+                "ata: {}: {}, {} sectors ({} MiB){}",
+                name,
+                identity.model(),
+                identity.sectors,
+                (identity.sectors * SECTOR_SIZE as u64) >> 20,
+                if identity.lba48 { ", LBA48" } else { "" }
+            );
+
+            let drive = Drive {
+                name,
+                channel,
+                slave,
+                identity,
+            };
+            let Ok(drive) = DRIVES.register(drive, |d| d.name == name) else {
+                continue;
+            };
+            if let Err(e) = block::add_disk(drive) {
+                pr_warn!("ata: {}: {:?}", name, e);
+            }
+            found = true;
+        }
+    }
+
+    if found { Ok(()) } else { Err(ProbeError::NoResponse) }
+}
+
+/// Registers the driver of the IDE controllers found on the PCI bus.
+pub fn init() {
+    let _ = driver::register(Driver { name: "ata", ids: &IDS, probe });
+}
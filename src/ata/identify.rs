@@ -0,0 +1,96 @@
+//! IDENTIFY DEVICE data.
+//!
+//! The drive answers IDENTIFY with 256 words describing it. Strings are
+//! stored with the two bytes of each word swapped.
+
+/// Words of the IDENTIFY data.
+pub type IdentifyData = [u16; 256];
+
+const SERIAL: core::ops::Range<usize> = 10..20;
+const MODEL: core::ops::Range<usize> = 27..47;
+const LBA28_SECTORS: usize = 60;
+const COMMAND_SETS: usize = 83;
+const LBA48_SECTORS: usize = 100;
+
+/// Set in [`COMMAND_SETS`] when the 48-bit commands are supported.
+const LBA48_SUPPORTED: u16 = 1 << 10;
+
+/// What the kernel needs from the IDENTIFY data.
+#[derive(Clone, Copy, Debug)]
+pub struct Identity {
+    pub model: [u8; 40],
+    pub serial: [u8; 20],
+    pub sectors: u64,
+    pub lba48: bool,
+}
+
+impl Identity {
+    #[must_use]
+    pub fn parse(data: &IdentifyData) -> Self {
+        let lba48 = data[COMMAND_SETS] & LBA48_SUPPORTED != 0;
+        let sectors = if lba48 {
+            data[LBA48_SECTORS..LBA48_SECTORS + 4]
+                .iter()
+                .rev()
+                .fold(0, |sectors, word| sectors << 16 | u64::from(*word))
+        } else {
+            u64::from(data[LBA28_SECTORS + 1]) << 16 | u64::from(data[LBA28_SECTORS])
+        };
+
+        let mut model = [0; 40];
+        string(&data[MODEL], &mut model);
+        let mut serial = [0; 20];
+        string(&data[SERIAL], &mut serial);
+
+        Self { model, serial, sectors, lba48 }
+    }
+
+    /// Returns the model name, without its padding.
+    #[must_use]
+    pub fn model(&self) -> &str {
+        trimmed(&self.model)
+    }
+
+    #[must_use]
+    pub fn serial(&self) -> &str {
+        trimmed(&self.serial)
+    }
+}
+
+/// Copies a string of swapped words into `out`.
+fn string(words: &[u16], out: &mut [u8]) {
+    for (word, bytes) in words.iter().zip(out.chunks_exact_mut(2)) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+}
+
+fn trimmed(bytes: &[u8]) -> &str {
+    core::str::from_utf8(bytes).unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn identify_data_is_parsed() -> Result<(), &'static str> {
+        let mut data = [0; 256];
+        for (word, bytes) in data[MODEL].iter_mut().zip(b"QEMU HARDDISK".chunks(2).chain(core::iter::repeat(&b"  "[..]))) {
+            *word = u16::from(bytes[0]) << 8 | u16::from(*bytes.get(1).unwrap_or(&b' '));
+        }
+        data[LBA28_SECTORS] = 0x8000;
+        data[LBA28_SECTORS + 1] = 0x0001;
+
+        let identity = Identity::parse(&data);
+        kassert_eq!(identity.model(), "QEMU HARDDISK");
+        kassert_eq!(identity.sectors, 0x1_8000);
+        kassert!(!identity.lba48);
+
+        data[COMMAND_SETS] = LBA48_SUPPORTED;
+        data[LBA48_SECTORS + 2] = 0x0001;
+        kassert_eq!(Identity::parse(&data).sectors, 1 << 32);
+
+        Ok(())
+    }
+}
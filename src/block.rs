@@ -0,0 +1,225 @@
+//! Block devices.
+//!
+//! Disk drivers implement [`BlockDevice`] and hand their disks to [`add_disk`],
+//! which registers them along with their [`partition`]s, so that filesystems
+//! can find them by name and read them without knowing what is behind.
+//! Filesystems go through the buffer [`cache`] rather than the devices
+//! themselves.
+
+use core::fmt;
+
+use crate::{
+    pr_info, pr_warn, printkln,
+    registry::{RegisterError, Registry},
+    shell::{
+        argv::Argv,
+        command::{self, Command, ExitStatus, FAILURE, Io, SUCCESS, USAGE},
+    },
+    vmm::allocators::oom,
+};
+
+pub mod cache;
+pub mod partition;
+
+/// Size of a sector, the unit of every transfer.
+pub const SECTOR_SIZE: usize = 512;
+
+/// Maximum number of block devices registered at the same time.
+pub const MAX_DEVICES: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockError {
+    /// The sectors are past the end of the device.
+    OutOfRange,
+    /// The buffer is not a whole number of sectors.
+    BufferSize,
+    /// The device did not answer in time.
+    Timeout,
+    /// The device reported an error, with the content of its error register.
+    Device(u8),
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "sector out of range"),
+            Self::BufferSize => write!(f, "buffer is not a whole number of sectors"),
+            Self::Timeout => write!(f, "device timed out"),
+            Self::Device(error) => write!(f, "device error ({error:#04x})"),
+        }
+    }
+}
+
+/// Device storing data in fixed size sectors.
+pub trait BlockDevice {
+    /// Name of the device, like `hda`.
+    fn name(&self) -> &str;
+
+    /// Number of sectors of the device.
+    fn sector_count(&self) -> u64;
+
+    /// Reads the sectors starting at `lba` into `buf`, whose length must be a multiple of
+    /// [`SECTOR_SIZE`].
+    ///
+    /// # Errors
+    /// This function returns an error if the sectors are out of range, or if the device
+    /// fails to read them.
+    fn read(&self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError>;
+
+    /// Writes `buf`, whose length must be a multiple of [`SECTOR_SIZE`], to the sectors
+    /// starting at `lba`.
+    ///
+    /// # Errors
+    /// This function returns an error if the sectors are out of range, or if the device
+    /// fails to write them.
+    fn write(&self, lba: u64, buf: &[u8]) -> Result<(), BlockError>;
+
+    /// Makes sure that the sectors written so far reached the medium.
+    ///
+    /// # Errors
+    /// This function returns an error if the device fails to flush its cache.
+    fn flush(&self) -> Result<(), BlockError> {
+        Ok(())
+    }
+
+    /// Returns the device holding the sectors of this one, and where they start on it, for
+    /// devices that are a range of another, like partitions.
+    fn parent(&self) -> Option<(&'static dyn BlockDevice, u64)> {
+        None
+    }
+}
+
+impl fmt::Debug for dyn BlockDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns the number of sectors held by `buf`, if `lba..lba + count` fits in a device of
+/// `sector_count` sectors.
+///
+/// # Errors
+/// This function returns an error if `buf` is not a whole number of sectors, or if the
+/// sectors do not fit.
+pub fn check_range(sector_count: u64, lba: u64, buf: &[u8]) -> Result<u64, BlockError> {
+    if !buf.len().is_multiple_of(SECTOR_SIZE) {
+        return Err(BlockError::BufferSize);
+    }
+    let count = (buf.len() / SECTOR_SIZE) as u64;
+    if lba.checked_add(count).is_none_or(|end| end > sector_count) {
+        return Err(BlockError::OutOfRange);
+    }
+    Ok(count)
+}
+
+static DEVICES: Registry<&'static dyn BlockDevice, MAX_DEVICES> = Registry::new();
+
+/// Makes `device` available under its name.
+///
+/// # Errors
+/// This function returns an error if a device with the same name exists, or if
+/// [`MAX_DEVICES`] devices are already registered.
+pub fn register(device: &'static dyn BlockDevice) -> Result<(), RegisterError> {
+    DEVICES.register(device, |d| d.name() == device.name())?;
+    Ok(())
+}
+
+/// Returns every registered device, in registration order.
+pub fn devices() -> impl Iterator<Item = &'static dyn BlockDevice> {
+    DEVICES.iter().copied()
+}
+
+#[must_use]
+pub fn find(name: &str) -> Option<&'static dyn BlockDevice> {
+    devices().find(|d| d.name() == name)
+}
+
+/// Registers `disk`, then the partitions found on it.
+///
+/// # Errors
+/// This function returns an error if the disk itself cannot be registered. Partition tables
+/// that cannot be read are reported, but do not prevent the disk from being used.
+pub fn add_disk(disk: &'static dyn BlockDevice) -> Result<(), RegisterError> {
+    register(disk)?;
+
+    match partition::scan(disk) {
+        Ok(0) => {}
+        Ok(count) => pr_info!("block: {}: {} partition(s)", disk.name(), count),
+        Err(e) => pr_warn!("block: {}: {}", disk.name(), e),
+    }
+    Ok(())
+}
+
+/// Lets the buffer cache give memory back, and registers the block device commands.
+pub fn init() {
+    let _ = oom::register_shrinker(cache::SHRINKER);
+
+    let _ = command::register(Command {
+        name: "lsblk",
+        usage: "",
+        help: "list the block devices",
+        func: lsblk_cmd,
+        complete: None,
+    });
+    let _ = command::register(Command {
+        name: "sync",
+        usage: "",
+        help: "write the cached sectors back to the disks",
+        func: sync_cmd,
+        complete: None,
+    });
+}
+
+fn lsblk_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    if argv.args().next().is_some() {
+        printkln!("usage: lsblk");
+        return USAGE;
+    }
+
+    printkln!("{:<8} {:>10} {:>10} {:>10}", "NAME", "START", "SECTORS", "SIZE");
+    for device in devices() {
+        let start = partition::partitions().find(|p| core::ptr::addr_eq(*p, device)).map(|p| p.start());
+        let size = device.sector_count() * SECTOR_SIZE as u64;
+        match start {
+            Some(start) => printkln!("{:<8} {:>10} {:>10} {:>7} KiB", device.name(), start, device.sector_count(), size >> 10),
+            None => printkln!("{:<8} {:>10} {:>10} {:>7} KiB", device.name(), "-", device.sector_count(), size >> 10),
+        }
+    }
+
+    let (buffers, dirty) = cache::usage();
+    printkln!("cache: {}/{} buffers, {} dirty", buffers, cache::MAX_BUFFERS, dirty);
+    SUCCESS
+}
+
+fn sync_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    if argv.args().next().is_some() {
+        printkln!("usage: sync");
+        return USAGE;
+    }
+
+    match cache::sync() {
+        Ok(()) => SUCCESS,
+        Err(e) => {
+            printkln!("sync: {}", e);
+            FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    #[test_case]
+    fn ranges_are_checked() -> Result<(), &'static str> {
+        let buf = [0; 2 * SECTOR_SIZE];
+
+        kassert_eq!(check_range(8, 6, &buf), Ok(2));
+        kassert_eq!(check_range(8, 7, &buf), Err(BlockError::OutOfRange));
+        kassert_eq!(check_range(8, u64::MAX, &buf), Err(BlockError::OutOfRange));
+        kassert_eq!(check_range(8, 0, &buf[1..]), Err(BlockError::BufferSize));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Sector buffer cache.
+//!
+//! Sectors accessed through [`read`] and [`write`] are kept in buffers taken
+//! from the 512-byte slab cache, so that a filesystem going over the same
+//! metadata again and again does not reach the disk every time.
+//!
+//! Writes only mark their buffer dirty. It is written back when it is evicted,
+//! on [`sync`], or when memory runs low and the [`SHRINKER`] gives the
+//! buffers back to the allocator. Once [`MAX_BUFFERS`] are in use, the least
+//! recently used one is evicted to make room.
+//!
+//! When no buffer can be allocated nor evicted, the sectors are transferred
+//! directly, uncached.
+//!
+//! Sectors of a partition are cached as sectors of its disk, so that reaching
+//! them through either device gives the same data.
+
+use crate::{
+    block::{self, BlockDevice, BlockError, SECTOR_SIZE},
+    vmm::allocators::{collections::KBox, oom::Shrinker},
+};
+
+/// Maximum number of sectors held by the cache.
+pub const MAX_BUFFERS: usize = 256;
+
+type Sector = [u8; SECTOR_SIZE];
+
+struct Buffer {
+    device: &'static dyn BlockDevice,
+    lba: u64,
+    data: KBox<Sector>,
+    /// Set when `data` holds changes the device has not seen yet.
+    dirty: bool,
+    /// Value of [`Cache::clock`] at the last access.
+    last_use: u64,
+}
+
+impl Buffer {
+    fn is(&self, device: &'static dyn BlockDevice, lba: u64) -> bool {
+        core::ptr::addr_eq(self.device, device) && self.lba == lba
+    }
+
+    fn write_back(&mut self) -> Result<(), BlockError> {
+        if self.dirty {
+            self.device.write(self.lba, &*self.data)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+struct Cache {
+    buffers: [Option<Buffer>; MAX_BUFFERS],
+    /// Incremented on every access, to order the buffers by last use.
+    clock: u64,
+    /// Set while the cache is in use, so that the shrinker, run by an allocation the cache
+    /// made, leaves it alone.
+    busy: bool,
+}
+
+static mut CACHE: Cache = Cache {
+    buffers: [const { None }; MAX_BUFFERS],
+    clock: 0,
+    busy: false,
+};
+
+/// Runs `f` on the cache, or returns `None` if it is already in use.
+#[allow(static_mut_refs)]
+fn with_cache<R>(f: impl FnOnce(&mut Cache) -> R) -> Option<R> {
+    // SAFETY:
+    // `CACHE` is only reachable through this function, and `busy` prevents it from being
+    // borrowed twice when a shrinker runs in the middle of an access.
+    let cache = unsafe { &mut CACHE };
+    if cache.busy {
+        return None;
+    }
+
+    cache.busy = true;
+    let res = f(cache);
+    cache.busy = false;
+    Some(res)
+}
+
+impl Cache {
+    /// Returns the index of the buffer holding `lba`, reading it from the device if `fill` is
+    /// set, or `None` if no buffer could be found for it.
+    fn get(&mut self, device: &'static dyn BlockDevice, lba: u64, fill: bool) -> Result<Option<usize>, BlockError> {
+        self.clock += 1;
+
+        if let Some(index) = self.buffers.iter().position(|b| b.as_ref().is_some_and(|b| b.is(device, lba))) {
+            if let Some(buffer) = &mut self.buffers[index] {
+                buffer.last_use = self.clock;
+            }
+            return Ok(Some(index));
+        }
+
+        let Some((index, data)) = self.allocate()? else {
+            return Ok(None);
+        };
+        let mut buffer = Buffer {
+            device,
+            lba,
+            data,
+            dirty: false,
+            last_use: self.clock,
+        };
+        if fill {
+            device.read(lba, &mut *buffer.data)?;
+        }
+        self.buffers[index] = Some(buffer);
+
+        Ok(Some(index))
+    }
+
+    /// Returns a free slot and its sector, allocating a new one or taking the one of the least
+    /// recently used buffer.
+    fn allocate(&mut self) -> Result<Option<(usize, KBox<Sector>)>, BlockError> {
+        if let Some(index) = self.buffers.iter().position(Option::is_none)
+            && let Ok(data) = KBox::try_new([0; SECTOR_SIZE])
+        {
+            return Ok(Some((index, data)));
+        }
+
+        let lru = self
+            .buffers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, b)| Some((index, b.as_ref()?.last_use)))
+            .min_by_key(|&(_, last_use)| last_use);
+        let Some((index, _)) = lru else {
+            return Ok(None);
+        };
+
+        if let Some(buffer) = &mut self.buffers[index] {
+            buffer.write_back()?;
+        }
+        Ok(self.buffers[index].take().map(|b| (index, b.data)))
+    }
+
+    /// Writes back the dirty buffers of `device`, or of every device if `None`.
+    fn write_back(&mut self, device: Option<&'static dyn BlockDevice>) -> Result<(), BlockError> {
+        self.buffers
+            .iter_mut()
+            .flatten()
+            .filter(|b| device.is_none_or(|d| core::ptr::addr_eq(b.device, d)))
+            .try_for_each(Buffer::write_back)
+    }
+}
+
+/// Returns the device at the bottom of `device`'s parents, and the sector `lba` of `device` is
+/// on it.
+fn resolve(device: &'static dyn BlockDevice, lba: u64) -> (&'static dyn BlockDevice, u64) {
+    match device.parent() {
+        Some((parent, start)) => resolve(parent, start + lba),
+        None => (device, lba),
+    }
+}
+
+/// Reads the sectors starting at `lba` into `buf`, whose length must be a multiple of
+/// [`SECTOR_SIZE`], going to `device` only for the sectors not cached yet.
+///
+/// # Errors
+/// This function returns an error if the sectors are out of range, or if the device fails to
+/// read them or to write back an evicted buffer.
+pub fn read(device: &'static dyn BlockDevice, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+    block::check_range(device.sector_count(), lba, buf)?;
+    let (device, lba) = resolve(device, lba);
+
+    for (lba, sector) in (lba..).zip(buf.chunks_exact_mut(SECTOR_SIZE)) {
+        let cached = with_cache(|cache| {
+            let Some(index) = cache.get(device, lba, true)? else {
+                return Ok(false);
+            };
+            if let Some(buffer) = &cache.buffers[index] {
+                sector.copy_from_slice(&*buffer.data);
+            }
+            Ok(true)
+        });
+
+        if !cached.unwrap_or(Ok(false))? {
+            device.read(lba, sector)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `buf`, whose length must be a multiple of [`SECTOR_SIZE`], to the cached sectors
+/// starting at `lba`. They reach `device` later, see [`sync`].
+///
+/// # Errors
+/// This function returns an error if the sectors are out of range, or if the device fails to
+/// write back an evicted buffer, or the sectors themselves when they cannot be cached.
+pub fn write(device: &'static dyn BlockDevice, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+    block::check_range(device.sector_count(), lba, buf)?;
+    let (device, lba) = resolve(device, lba);
+
+    for (lba, sector) in (lba..).zip(buf.chunks_exact(SECTOR_SIZE)) {
+        // The whole sector is overwritten, so there is no need to read it first.
+        let cached = with_cache(|cache| {
+            let Some(index) = cache.get(device, lba, false)? else {
+                return Ok(false);
+            };
+            if let Some(buffer) = &mut cache.buffers[index] {
+                buffer.data.copy_from_slice(sector);
+                buffer.dirty = true;
+            }
+            Ok(true)
+        });
+
+        if !cached.unwrap_or(Ok(false))? {
+            device.write(lba, sector)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes back the dirty buffers of `device`, and flushes it. For a partition, the buffers of
+/// the whole disk are written back.
+///
+/// # Errors
+/// This function returns an error if the device fails to write a buffer or to flush.
+pub fn sync_device(device: &'static dyn BlockDevice) -> Result<(), BlockError> {
+    let (disk, _) = resolve(device, 0);
+    with_cache(|cache| cache.write_back(Some(disk))).unwrap_or(Ok(()))?;
+    device.flush()
+}
+
+/// Writes back every dirty buffer, and flushes the registered devices.
+///
+/// # Errors
+/// This function returns the first error met, after trying every device.
+pub fn sync() -> Result<(), BlockError> {
+    let mut res = with_cache(|cache| cache.write_back(None)).unwrap_or(Ok(()));
+    for device in block::devices() {
+        res = res.and(device.flush());
+    }
+    res
+}
+
+/// Returns the number of buffers in the cache, and how many of them are dirty.
+#[must_use]
+pub fn usage() -> (usize, usize) {
+    with_cache(|cache| {
+        let buffers = cache.buffers.iter().flatten();
+        (buffers.clone().count(), buffers.filter(|b| b.dirty).count())
+    })
+    .unwrap_or_default()
+}
+
+/// Writes back and releases every buffer, returning the number of bytes freed. Buffers that
+/// fail to be written back are kept.
+fn shrink(_size: usize) -> usize {
+    with_cache(|cache| {
+        let mut freed = 0;
+        for slot in &mut cache.buffers {
+            if slot.as_mut().is_some_and(|b| b.write_back().is_ok()) {
+                *slot = None;
+                freed += SECTOR_SIZE;
+            }
+        }
+        freed
+    })
+    .unwrap_or(0)
+}
+
+pub const SHRINKER: Shrinker = Shrinker {
+    name: "block cache",
+    reclaim: shrink,
+};
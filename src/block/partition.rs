@@ -0,0 +1,223 @@
+//! Partitions.
+//!
+//! [`scan`] reads the partition table of a disk, MBR or GPT, and registers
+//! each partition as a block device of its own, named after the disk and its
+//! number: the first partition of `hda` is `hda1`. Only the primary partitions
+//! of an MBR are exposed.
+
+use core::fmt::{self, Write};
+
+use crate::{
+    block::{self, BlockDevice, BlockError, SECTOR_SIZE},
+    registry::{RegisterError, Registry},
+};
+
+pub mod gpt;
+pub mod mbr;
+
+/// Maximum number of partitions registered, over all disks.
+pub const MAX_PARTITIONS: usize = block::MAX_DEVICES;
+
+const NAME_LEN: usize = 16;
+
+#[derive(Debug)]
+pub enum PartitionError {
+    Device(BlockError),
+    /// Neither the GPT header nor its backup are valid.
+    BadGptHeader,
+    /// The GPT entries do not match their CRC, or have an unsupported size.
+    BadGptEntries,
+    Register(RegisterError),
+    /// The disk name is too long to be followed by a partition number.
+    NameTooLong,
+    /// [`MAX_PARTITIONS`] partitions are already registered.
+    TooManyPartitions,
+}
+
+impl From<BlockError> for PartitionError {
+    fn from(e: BlockError) -> Self {
+        Self::Device(e)
+    }
+}
+
+impl fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Device(e) => write!(f, "{e}"),
+            Self::BadGptHeader => write!(f, "invalid GPT header"),
+            Self::BadGptEntries => write!(f, "invalid GPT entries"),
+            Self::Register(e) => write!(f, "could not register partition: {e:?}"),
+            Self::NameTooLong => write!(f, "disk name too long"),
+            Self::TooManyPartitions => write!(f, "too many partitions"),
+        }
+    }
+}
+
+/// Name of a partition, stored inline.
+#[derive(Debug)]
+struct Name {
+    bytes: [u8; NAME_LEN],
+    len: usize,
+}
+
+impl Write for Name {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes.get_mut(self.len..end).ok_or(fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Range of sectors of a disk, seen as a device starting at sector 0.
+#[derive(Debug)]
+pub struct Partition {
+    name: Name,
+    disk: &'static dyn BlockDevice,
+    start: u64,
+    sectors: u64,
+}
+
+impl Partition {
+    #[must_use]
+    pub fn disk(&self) -> &'static dyn BlockDevice {
+        self.disk
+    }
+
+    /// First sector of the partition on its disk.
+    #[must_use]
+    pub const fn start(&self) -> u64 {
+        self.start
+    }
+}
+
+impl BlockDevice for Partition {
+    fn name(&self) -> &str {
+        core::str::from_utf8(&self.name.bytes[..self.name.len]).unwrap_or_default()
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.sectors
+    }
+
+    fn read(&self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_range(self.sectors, lba, buf)?;
+        self.disk.read(self.start + lba, buf)
+    }
+
+    fn write(&self, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+        block::check_range(self.sectors, lba, buf)?;
+        self.disk.write(self.start + lba, buf)
+    }
+
+    fn flush(&self) -> Result<(), BlockError> {
+        self.disk.flush()
+    }
+
+    fn parent(&self) -> Option<(&'static dyn BlockDevice, u64)> {
+        Some((self.disk, self.start))
+    }
+}
+
+static PARTITIONS: Registry<Partition, MAX_PARTITIONS> = Registry::new();
+
+/// Returns every registered partition.
+pub fn partitions() -> impl Iterator<Item = &'static Partition> {
+    PARTITIONS.iter()
+}
+
+/// Registers sectors `start..start + sectors` of `disk` as its partition `number`.
+fn add(disk: &'static dyn BlockDevice, number: usize, start: u64, sectors: u64) -> Result<(), PartitionError> {
+    if start.checked_add(sectors).is_none_or(|end| end > disk.sector_count()) {
+        return Err(PartitionError::Device(BlockError::OutOfRange));
+    }
+
+    let mut name = Name { bytes: [0; NAME_LEN], len: 0 };
+    write!(name, "{}{}", disk.name(), number).map_err(|_| PartitionError::NameTooLong)?;
+
+    let partition = PARTITIONS
+        .register(Partition { name, disk, start, sectors }, |_| false)
+        .map_err(|_| PartitionError::TooManyPartitions)?;
+    block::register(partition).map_err(|e| {
+        if let Some(index) = PARTITIONS.position(|p| core::ptr::eq(p, partition)) {
+            // SAFETY:
+            // The partition was not registered, so nothing else refers to it.
+            let _ = unsafe { PARTITIONS.remove(index) };
+        }
+        PartitionError::Register(e)
+    })
+}
+
+/// Registers the partitions of `disk`, and returns their number.
+///
+/// # Errors
+/// This function returns an error if the partition table cannot be read or is corrupted, or
+/// if a partition cannot be registered. The partitions registered before the error are kept.
+pub fn scan(disk: &'static dyn BlockDevice) -> Result<usize, PartitionError> {
+    let mut sector = [0; SECTOR_SIZE];
+    disk.read(0, &mut sector)?;
+    let Some(entries) = mbr::parse(&sector) else {
+        return Ok(0);
+    };
+
+    if entries.iter().flatten().any(|e| e.kind == mbr::PROTECTIVE) {
+        return scan_gpt(disk);
+    }
+
+    let mut count = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        if let Some(entry) = entry.filter(|e| !e.is_extended()) {
+            add(disk, index + 1, entry.start, entry.sectors)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Returns the GPT header, falling back to the backup in the last sector.
+fn gpt_header(disk: &dyn BlockDevice) -> Result<gpt::Header, PartitionError> {
+    let mut sector = [0; SECTOR_SIZE];
+    disk.read(1, &mut sector)?;
+    if let Ok(header) = gpt::Header::parse(&sector) {
+        return Ok(header);
+    }
+
+    disk.read(disk.sector_count().saturating_sub(1), &mut sector)?;
+    gpt::Header::parse(&sector)
+}
+
+/// Calls `f` with the number and bytes of each GPT entry, and returns the CRC of the array.
+fn gpt_entries(disk: &dyn BlockDevice, header: &gpt::Header, mut f: impl FnMut(usize, &[u8])) -> Result<u32, BlockError> {
+    let mut sector = [0; SECTOR_SIZE];
+    let mut crc = !0;
+
+    let per_sector = SECTOR_SIZE / header.entry_size;
+    for index in 0..header.entry_count as usize {
+        let offset = index % per_sector * header.entry_size;
+        if offset == 0 {
+            disk.read(header.entries_lba + (index / per_sector) as u64, &mut sector)?;
+        }
+
+        let bytes = &sector[offset..offset + header.entry_size];
+        crc = gpt::crc32(crc, bytes);
+        f(index + 1, bytes);
+    }
+    Ok(!crc)
+}
+
+fn scan_gpt(disk: &'static dyn BlockDevice) -> Result<usize, PartitionError> {
+    let header = gpt_header(disk)?;
+    if gpt_entries(disk, &header, |_, _| {})? != header.entries_crc {
+        return Err(PartitionError::BadGptEntries);
+    }
+
+    let mut count = 0;
+    let mut res = Ok(());
+    gpt_entries(disk, &header, |number, bytes| {
+        if let (Ok(()), Some(entry)) = (&res, gpt::Entry::parse(bytes)) {
+            res = add(disk, number, entry.start, entry.sectors);
+            count += usize::from(res.is_ok());
+        }
+    })?;
+    res.map(|()| count)
+}
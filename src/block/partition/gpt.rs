@@ -0,0 +1,172 @@
+//! GUID partition table.
+//!
+//! The header sits in the second sector, with a backup copy in the last one.
+//! It points to an array of entries, each describing a partition by its type
+//! GUID and its first and last sectors. Both the header and the array are
+//! protected by a CRC32.
+
+use crate::block::partition::PartitionError;
+
+const SIGNATURE: &[u8; 8] = b"EFI PART";
+
+const HEADER_SIZE: usize = 12;
+const HEADER_CRC: usize = 16;
+const ENTRIES_LBA: usize = 72;
+const ENTRY_COUNT: usize = 80;
+const ENTRY_SIZE: usize = 84;
+const ENTRIES_CRC: usize = 88;
+
+/// Size of the fields defined by the specification, the header may be larger.
+const MIN_HEADER_SIZE: usize = 92;
+
+/// Size of an entry in revision 1.0, larger entries keep the same layout.
+const MIN_ENTRY_SIZE: usize = 128;
+
+/// Number of entries read at most, well above the 128 that partitioning tools create, so that
+/// a corrupted count does not have the whole disk read.
+pub const MAX_ENTRY_COUNT: u32 = 1024;
+
+const TYPE_GUID: core::ops::Range<usize> = 0..16;
+const FIRST_LBA: usize = 32;
+const LAST_LBA: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub entries_lba: u64,
+    pub entry_count: u32,
+    pub entry_size: usize,
+    pub entries_crc: u32,
+}
+
+impl Header {
+    /// Parses and checks the header held by `sector`.
+    ///
+    /// # Errors
+    /// This function returns an error if the signature or the CRC do not match, or if the
+    /// entries are not laid out so that a whole number of them fits in a sector, or are more
+    /// than [`MAX_ENTRY_COUNT`].
+    pub fn parse(sector: &[u8]) -> Result<Self, PartitionError> {
+        if !sector.starts_with(SIGNATURE) {
+            return Err(PartitionError::BadGptHeader);
+        }
+
+        let size = u32_at(sector, HEADER_SIZE) as usize;
+        if !(MIN_HEADER_SIZE..=sector.len()).contains(&size) {
+            return Err(PartitionError::BadGptHeader);
+        }
+        // The CRC covers the header with its own field zeroed.
+        let crc = !crc32(crc32(crc32(!0, &sector[..HEADER_CRC]), &[0; 4]), &sector[HEADER_CRC + 4..size]);
+        if crc != u32_at(sector, HEADER_CRC) {
+            return Err(PartitionError::BadGptHeader);
+        }
+
+        let entry_size = u32_at(sector, ENTRY_SIZE) as usize;
+        if !entry_size.is_power_of_two() || !(MIN_ENTRY_SIZE..=sector.len()).contains(&entry_size) {
+            return Err(PartitionError::BadGptEntries);
+        }
+        let entry_count = u32_at(sector, ENTRY_COUNT);
+        if entry_count > MAX_ENTRY_COUNT {
+            return Err(PartitionError::BadGptEntries);
+        }
+
+        Ok(Self {
+            entries_lba: u64_at(sector, ENTRIES_LBA),
+            entry_count,
+            entry_size,
+            entries_crc: u32_at(sector, ENTRIES_CRC),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub start: u64,
+    pub sectors: u64,
+}
+
+impl Entry {
+    /// Parses an entry of the array, returning `None` if it is unused.
+    #[must_use]
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes[TYPE_GUID].iter().all(|b| *b == 0) {
+            return None;
+        }
+
+        let start = u64_at(bytes, FIRST_LBA);
+        // The last sector is inclusive.
+        let sectors = u64_at(bytes, LAST_LBA).checked_sub(start)?.checked_add(1)?;
+        Some(Self { start, sectors })
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from(u32_at(bytes, offset + 4)) << 32 | u64::from(u32_at(bytes, offset))
+}
+
+/// Updates a CRC32, which starts at `!0` and is complemented once all the bytes went through.
+#[must_use]
+pub fn crc32(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 0 { crc >> 1 } else { crc >> 1 ^ 0xedb8_8320 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn crc32_matches_check_value() -> Result<(), &'static str> {
+        kassert_eq!(!crc32(!0, b"123456789"), 0xcbf4_3926);
+        Ok(())
+    }
+
+    #[test_case]
+    fn header_and_entries_are_parsed() -> Result<(), &'static str> {
+        let mut sector = [0; 512];
+        sector[..8].copy_from_slice(SIGNATURE);
+        sector[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&(MIN_HEADER_SIZE as u32).to_le_bytes());
+        sector[ENTRIES_LBA..ENTRIES_LBA + 8].copy_from_slice(&2_u64.to_le_bytes());
+        sector[ENTRY_COUNT..ENTRY_COUNT + 4].copy_from_slice(&128_u32.to_le_bytes());
+        sector[ENTRY_SIZE..ENTRY_SIZE + 4].copy_from_slice(&128_u32.to_le_bytes());
+        kassert!(Header::parse(&sector).is_err(), "CRC should not match");
+
+        let crc = !crc32(!0, &sector[..MIN_HEADER_SIZE]);
+        sector[HEADER_CRC..HEADER_CRC + 4].copy_from_slice(&crc.to_le_bytes());
+        kassert_eq!(
+            Header::parse(&sector).ok(),
+            Some(Header {
+                entries_lba: 2,
+                entry_count: 128,
+                entry_size: 128,
+                entries_crc: 0,
+            })
+        );
+
+        sector[ENTRY_COUNT..ENTRY_COUNT + 4].copy_from_slice(&(MAX_ENTRY_COUNT + 1).to_le_bytes());
+        let crc = !crc32(crc32(crc32(!0, &sector[..HEADER_CRC]), &[0; 4]), &sector[HEADER_CRC + 4..MIN_HEADER_SIZE]);
+        sector[HEADER_CRC..HEADER_CRC + 4].copy_from_slice(&crc.to_le_bytes());
+        kassert!(Header::parse(&sector).is_err(), "entry count should be capped");
+
+        let mut entry = [0; 128];
+        kassert_eq!(Entry::parse(&entry), None);
+        entry[TYPE_GUID].fill(0xaa);
+        entry[FIRST_LBA..FIRST_LBA + 8].copy_from_slice(&34_u64.to_le_bytes());
+        entry[LAST_LBA..LAST_LBA + 8].copy_from_slice(&2081_u64.to_le_bytes());
+        kassert_eq!(Entry::parse(&entry), Some(Entry { start: 34, sectors: 2048 }));
+        entry[FIRST_LBA..FIRST_LBA + 8].fill(0);
+        entry[LAST_LBA..LAST_LBA + 8].fill(0xff);
+        kassert_eq!(Entry::parse(&entry), None);
+
+        Ok(())
+    }
+}
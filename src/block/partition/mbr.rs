@@ -0,0 +1,99 @@
+//! Master boot record.
+//!
+//! The first sector of the disk ends with the `0x55 0xaa` signature, preceded
+//! by four 16-byte entries describing the primary partitions.
+
+const ENTRIES: usize = 446;
+const ENTRY_SIZE: usize = 16;
+const SIGNATURE: usize = 510;
+
+const TYPE: usize = 4;
+const START: usize = 8;
+const SECTORS: usize = 12;
+
+/// Type of the single partition covering a GPT disk.
+pub const PROTECTIVE: u8 = 0xee;
+
+/// Types of the extended partitions, which hold logical ones.
+const EXTENDED: [u8; 3] = [0x05, 0x0f, 0x85];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: u8,
+    pub start: u64,
+    pub sectors: u64,
+}
+
+impl Entry {
+    #[must_use]
+    pub fn is_extended(&self) -> bool {
+        EXTENDED.contains(&self.kind)
+    }
+}
+
+/// Returns the primary partitions of the boot sector, or `None` if it has no partition table.
+#[must_use]
+pub fn parse(sector: &[u8]) -> Option<[Option<Entry>; 4]> {
+    if sector.get(SIGNATURE..SIGNATURE + 2)? != [0x55, 0xaa] {
+        return None;
+    }
+
+    let mut entries = [None; 4];
+    for (entry, bytes) in entries.iter_mut().zip(sector[ENTRIES..SIGNATURE].chunks_exact(ENTRY_SIZE)) {
+        let u32_at = |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+
+        let kind = bytes[TYPE];
+        let sectors = u64::from(u32_at(SECTORS));
+        if kind != 0 && sectors != 0 {
+            *entry = Some(Entry {
+                kind,
+                start: u64::from(u32_at(START)),
+                sectors,
+            });
+        }
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn primary_partitions_are_parsed() -> Result<(), &'static str> {
+        let mut sector = [0; 512];
+        kassert_eq!(parse(&sector), None);
+
+        sector[SIGNATURE..].copy_from_slice(&[0x55, 0xaa]);
+        let entry = &mut sector[ENTRIES + ENTRY_SIZE..ENTRIES + 2 * ENTRY_SIZE];
+        entry[TYPE] = 0x83;
+        entry[START..START + 4].copy_from_slice(&2048_u32.to_le_bytes());
+        entry[SECTORS..SECTORS + 4].copy_from_slice(&4096_u32.to_le_bytes());
+
+        let entries = parse(&sector).ok_or("No partition table")?;
+        kassert_eq!(
+            entries,
+            [
+                None,
+                Some(Entry {
+                    kind: 0x83,
+                    start: 2048,
+                    sectors: 4096,
+                }),
+                None,
+                None,
+            ]
+        );
+        kassert!(
+            Entry {
+                kind: 0x0f,
+                start: 0,
+                sectors: 1
+            }
+            .is_extended()
+        );
+
+        Ok(())
+    }
+}
@@ -95,6 +95,30 @@ pub struct MultibootInfo {
     pub color_info: [u8; 5],
 }
 
+impl MultibootInfo {
+    /// Returns the command line passed to the kernel by the bootloader, if any.
+    ///
+    /// # Safety
+    /// The command line is referenced by physical address, and is only reachable through the
+    /// boot-time mapping of the first 16MiB, which is torn down by
+    /// [`init_memory`](crate::vmm::paging::init::init_memory). This must therefore be called
+    /// before initializing paging, and the slice returned must not be used after it.
+    #[must_use]
+    pub unsafe fn cmdline(&self) -> Option<&'static [u8]> {
+        const MULTIBOOT_INFO_CMDLINE: u32 = 1 << 2;
+
+        if self.flags & MULTIBOOT_INFO_CMDLINE == 0 || self.cmdline == 0 {
+            return None;
+        }
+
+        let cmdline = (self.cmdline as usize + KERNEL_BASE) as *const core::ffi::c_char;
+        // SAFETY:
+        // The bootloader passes a NUL-terminated string, which is mapped at `KERNEL_BASE` until
+        // `init_memory` is called, as per this function's safety contract.
+        Some(unsafe { core::ffi::CStr::from_ptr(cmdline) }.to_bytes())
+    }
+}
+
 impl Display for MultibootInfo {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         writeln!(f, "MultibootInfo {{")?;
@@ -1,39 +1,36 @@
-/// Converts a slice of bytes into a `usize`, assuming hexadecimal format,
-/// skipping leading and trailing whitespaces.
+/// Converts a slice of bytes into a `usize`, assuming hexadecimal format with
+/// an optional `0x` prefix, skipping leading and trailing whitespaces.
 ///
-/// Returns `None` if `bytes` cannot be converted to a `usize`
-/// deterministically.
+/// Returns `None` if `bytes` is not a hexadecimal number, or if it does not fit
+/// in a `usize`.
 #[must_use]
-#[allow(dead_code)]
 pub fn hextou(bytes: &[u8]) -> Option<usize> {
-    let mut starting_idx = 0;
-    while b"\t \n".contains(&bytes[starting_idx]) {
-        starting_idx += 1;
+    let bytes = bytes.trim_ascii();
+    let digits = bytes.strip_prefix(b"0x").or_else(|| bytes.strip_prefix(b"0X")).unwrap_or(bytes);
+    if digits.is_empty() {
+        return None;
     }
-    let num: &[u8] = if bytes[starting_idx] == b'0' && bytes[starting_idx + 1] == b'x' {
-        &bytes[starting_idx + 2..]
-    } else {
-        &bytes[starting_idx..]
-    };
 
-    let mut result: usize = 0;
+    digits.iter().try_fold(0usize, |result, byte| {
+        let digit = char::from(*byte).to_digit(16)?;
+        result.checked_mul(16)?.checked_add(digit as usize)
+    })
+}
 
-    for byte in num {
-        let digit: u8;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
 
-        if *byte >= b'0' && *byte <= b'9' {
-            digit = *byte - b'0';
-        } else if *byte >= b'a' && *byte <= b'f' {
-            digit = *byte - b'a' + 10;
-        } else if *byte >= b'A' && *byte <= b'F' {
-            digit = *byte - b'A' + 10;
-        } else if b"\t \n\0".contains(byte) {
-            break;
-        } else {
-            return None;
-        }
+    #[test_case]
+    fn hextou_accepts_optional_prefix() -> Result<(), &'static str> {
+        kassert_eq!(hextou(b"0xb8000"), Some(0xb8000));
+        kassert_eq!(hextou(b" C0100000\n"), Some(0xc010_0000));
+        kassert_eq!(hextou(b"0x"), None);
+        kassert_eq!(hextou(b""), None);
+        kassert_eq!(hextou(b"12g"), None);
+        kassert_eq!(hextou(b"123456789"), None);
 
-        result = result * 16 + digit as usize;
+        Ok(())
     }
-    Some(result / 2)
 }
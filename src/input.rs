@@ -0,0 +1,208 @@
+//! Input core.
+//!
+//! Drivers publish the events they receive with [`publish`], usually from
+//! their IRQ handler. Every consumer gets its own queue from [`subscribe`],
+//! holding the events its filter accepts, so several consumers can observe the
+//! same input without taking events from each other.
+//!
+//! A full queue drops the new events rather than the old ones, and counts them
+//! so that the consumer can tell input was lost.
+
+use core::arch::asm;
+
+use crate::{
+    hlt,
+    ps2::{KeyEvent, MouseEvent},
+};
+
+/// Maximum number of consumers subscribed at the same time.
+pub const MAX_CONSUMERS: usize = 8;
+
+/// Number of events a queue holds before dropping the new ones.
+const QUEUE_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub enum InputEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// Byte received on COM1.
+    Serial(u8),
+}
+
+/// Selects the events a consumer receives.
+pub type Filter = fn(&InputEvent) -> bool;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// All the queues are taken.
+    TooManyConsumers,
+}
+
+struct Queue {
+    /// `None` if no consumer owns the queue.
+    filter: Option<Filter>,
+    events: [Option<InputEvent>; QUEUE_SIZE],
+    read_pos: usize,
+    write_pos: usize,
+    /// Events dropped because the queue was full.
+    dropped: usize,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Self {
+            filter: None,
+            events: [None; QUEUE_SIZE],
+            read_pos: 0,
+            write_pos: 0,
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, event: InputEvent) {
+        let next_write = (self.write_pos + 1) % QUEUE_SIZE;
+
+        if next_write == self.read_pos {
+            self.dropped += 1;
+            return;
+        }
+        self.events[self.write_pos] = Some(event);
+        self.write_pos = next_write;
+    }
+
+    fn pop(&mut self) -> Option<InputEvent> {
+        if self.read_pos == self.write_pos {
+            return None;
+        }
+        let event = self.events[self.read_pos].take();
+        self.read_pos = (self.read_pos + 1) % QUEUE_SIZE;
+
+        event
+    }
+}
+
+static mut QUEUES: [Queue; MAX_CONSUMERS] = [const { Queue::new() }; MAX_CONSUMERS];
+
+/// Runs `f` with interrupts disabled, so that it cannot race with the IRQ handlers
+/// publishing events. Interrupts are only enabled again if they were before.
+fn without_interrupts<R>(f: impl FnOnce(&mut [Queue]) -> R) -> R {
+    let flags: u32;
+    // SAFETY:
+    // Reading EFLAGS and clearing IF has no other effect.
+    unsafe { asm!("pushfd", "pop {}", "cli", out(reg) flags) };
+
+    // SAFETY:
+    // Interrupts are disabled and the kernel is single threaded, so nothing else uses
+    // `QUEUES` meanwhile.
+    #[allow(static_mut_refs)]
+    let result = f(unsafe { &mut QUEUES });
+
+    if flags & (1 << 9) != 0 {
+        crate::sti!();
+    }
+    result
+}
+
+/// Hands `event` to every consumer whose filter accepts it.
+pub fn publish(event: InputEvent) {
+    without_interrupts(|queues| {
+        for queue in queues {
+            if queue.filter.is_some_and(|filter| filter(&event)) {
+                queue.push(event);
+            }
+        }
+    });
+}
+
+/// Returns a new consumer, receiving the events published from now on that `filter`
+/// accepts.
+///
+/// # Errors
+/// This function returns an error if [`MAX_CONSUMERS`] consumers are already subscribed.
+pub fn subscribe(filter: Filter) -> Result<Consumer, InputError> {
+    without_interrupts(|queues| {
+        let (index, queue) = queues
+            .iter_mut()
+            .enumerate()
+            .find(|(_, queue)| queue.filter.is_none())
+            .ok_or(InputError::TooManyConsumers)?;
+        *queue = Queue::new();
+        queue.filter = Some(filter);
+        Ok(Consumer { index })
+    })
+}
+
+/// Queue of a consumer, released when dropped.
+#[derive(Debug)]
+pub struct Consumer {
+    index: usize,
+}
+
+impl Consumer {
+    /// Returns the oldest event that was not read yet.
+    pub fn read(&mut self) -> Option<InputEvent> {
+        without_interrupts(|queues| queues[self.index].pop())
+    }
+
+    /// Returns the oldest event that was not read yet, halting until one is published.
+    ///
+    /// Without threads to switch to, this waits for the next interrupt.
+    pub fn wait(&mut self) -> InputEvent {
+        loop {
+            if let Some(event) = self.read() {
+                return event;
+            }
+            hlt!();
+        }
+    }
+
+    /// Returns the number of events dropped because the queue was full.
+    #[must_use]
+    pub fn dropped(&self) -> usize {
+        without_interrupts(|queues| queues[self.index].dropped)
+    }
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        without_interrupts(|queues| queues[self.index].filter = None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn every_consumer_gets_the_events_it_accepts() -> Result<(), &'static str> {
+        let (Ok(mut serial), Ok(mut all)) = (subscribe(|e| matches!(e, InputEvent::Serial(_))), subscribe(|_| true)) else {
+            return Err("no queue left");
+        };
+
+        publish(InputEvent::Serial(b'a'));
+        publish(InputEvent::Mouse(MouseEvent::Wheel(1)));
+
+        kassert!(matches!(serial.read(), Some(InputEvent::Serial(b'a'))));
+        kassert!(serial.read().is_none());
+        kassert!(matches!(all.read(), Some(InputEvent::Serial(b'a'))));
+        kassert!(matches!(all.read(), Some(InputEvent::Mouse(MouseEvent::Wheel(1)))));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn full_queues_count_dropped_events() -> Result<(), &'static str> {
+        let Ok(mut consumer) = subscribe(|e| matches!(e, InputEvent::Serial(_))) else {
+            return Err("no queue left");
+        };
+
+        for byte in 0..QUEUE_SIZE + 2 {
+            publish(InputEvent::Serial(byte as u8));
+        }
+        kassert_eq!(consumer.dropped(), 3);
+        kassert!(matches!(consumer.read(), Some(InputEvent::Serial(0))));
+
+        Ok(())
+    }
+}
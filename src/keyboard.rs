@@ -1,36 +1,126 @@
 use crate::{
+    input::{self, Consumer, InputError, InputEvent},
     keyboard::layout::{Character, CharacterFull, Layout},
     ps2::{self, Key},
 };
 
 pub mod layout;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ModifierState {
     shift_pressed: bool,
     ctrl_pressed: bool,
     alt_pressed: bool,
+    /// Right Alt, which selects the third level of the layouts that have one.
+    altgr_pressed: bool,
     gui_pressed: bool,
+    /// Inverts Shift for letters.
+    caps_lock: bool,
+    /// Makes the keypad type digits instead of moving the cursor.
+    num_lock: bool,
+    scroll_lock: bool,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl ModifierState {
+    /// Modifiers of a key typed with Ctrl held, for inputs that encode Ctrl in the character
+    /// itself, like serial terminals.
+    #[must_use]
+    pub const fn ctrl() -> Self {
+        Self {
+            shift_pressed: false,
+            ctrl_pressed: true,
+            alt_pressed: false,
+            altgr_pressed: false,
+            gui_pressed: false,
+            caps_lock: false,
+            num_lock: false,
+            scroll_lock: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn shift_pressed(&self) -> bool {
+        self.shift_pressed
+    }
+
+    #[must_use]
+    pub const fn ctrl_pressed(&self) -> bool {
+        self.ctrl_pressed
+    }
+
+    #[must_use]
+    pub const fn alt_pressed(&self) -> bool {
+        self.alt_pressed
+    }
+
+    #[must_use]
+    pub const fn altgr_pressed(&self) -> bool {
+        self.altgr_pressed
+    }
+
+    #[must_use]
+    pub const fn caps_lock(&self) -> bool {
+        self.caps_lock
+    }
+
+    #[must_use]
+    pub const fn num_lock(&self) -> bool {
+        self.num_lock
+    }
+
+    #[must_use]
+    pub const fn scroll_lock(&self) -> bool {
+        self.scroll_lock
+    }
+}
+
+static mut LAYOUT: Layout = layout::QWERTY;
+
+/// Returns the layout used to map the keys of the keyboard.
+#[must_use]
+pub fn layout() -> Layout {
+    // SAFETY:
+    // The kernel is single threaded, and `Layout` is `Copy`, so no reference to `LAYOUT` is
+    // kept.
+    unsafe { LAYOUT }
+}
+
+/// Changes the layout used to map the keys of the keyboard, starting with the next key.
+pub fn set_layout(layout: Layout) {
+    // SAFETY:
+    // The kernel is single threaded, see `layout`.
+    unsafe { LAYOUT = layout };
+}
+
+#[derive(Debug)]
 pub struct Keyboard {
+    input: Consumer,
     modifier: ModifierState,
-    layout: Layout,
+    /// Dead key waiting for the character it accents.
+    dead: Option<char>,
+    /// Character typed after a dead key it could not be combined with, returned on the
+    /// next call.
+    pending: Option<CharacterFull>,
+    /// Lock keys currently held, so that the repeated presses sent while holding one do not
+    /// toggle it again.
+    locks_held: [bool; 3],
 }
 
 impl Keyboard {
-    #[must_use]
-    pub fn new(layout: Layout) -> Self {
-        Self {
-            modifier: ModifierState {
-                shift_pressed: false,
-                ctrl_pressed: false,
-                alt_pressed: false,
-                gui_pressed: false,
-            },
-            layout,
-        }
+    /// # Errors
+    /// This function returns an error if no input queue is left for the keyboard.
+    pub fn new(layout: Layout) -> Result<Self, InputError> {
+        let input = input::subscribe(|e| matches!(e, InputEvent::Key(_)))?;
+        set_layout(layout);
+        ps2::set_leds(false, false, false);
+
+        Ok(Self {
+            input,
+            modifier: ModifierState::default(),
+            dead: None,
+            pending: None,
+            locks_held: [false; 3],
+        })
     }
 
     #[allow(clippy::should_implement_trait)]
@@ -40,7 +130,14 @@ impl Keyboard {
     }
 
     pub fn next_full(&mut self) -> Option<CharacterFull> {
-        while let Some(key_event) = ps2::read_key_event() {
+        if let Some(c) = self.pending.take() {
+            return Some(c);
+        }
+
+        while let Some(event) = self.input.read() {
+            let InputEvent::Key(key_event) = event else {
+                continue;
+            };
             use ps2::Event::*;
             match key_event.key {
                 Key::LeftShift | Key::RightShift => match key_event.event {
@@ -51,17 +148,24 @@ impl Keyboard {
                     Pressed => self.modifier.ctrl_pressed = true,
                     Released => self.modifier.ctrl_pressed = false,
                 },
-                Key::LeftAlt | Key::RightAlt => match key_event.event {
+                Key::LeftAlt => match key_event.event {
                     Pressed => self.modifier.alt_pressed = true,
                     Released => self.modifier.alt_pressed = false,
                 },
+                Key::RightAlt => match key_event.event {
+                    Pressed => self.modifier.altgr_pressed = true,
+                    Released => self.modifier.altgr_pressed = false,
+                },
                 Key::LeftGui | Key::RightGui => match key_event.event {
                     Pressed => self.modifier.gui_pressed = true,
                     Released => self.modifier.gui_pressed = false,
                 },
+                Key::CapsLock | Key::NumLock | Key::ScrollLock => self.handle_lock(key_event.key, key_event.event),
                 _ => match key_event.event {
                     Pressed => {
-                        return self.layout.map(key_event.key, self.modifier);
+                        if let Some(c) = layout().map(key_event.key, self.modifier).and_then(|c| self.compose(c)) {
+                            return Some(c);
+                        }
                     }
                     Released => {}
                 },
@@ -69,4 +173,53 @@ impl Keyboard {
         }
         None
     }
+
+    /// Toggles a lock key when it is pressed, and updates the LEDs to match.
+    fn handle_lock(&mut self, key: Key, event: ps2::Event) {
+        let (index, lock) = match key {
+            Key::CapsLock => (0, &mut self.modifier.caps_lock),
+            Key::NumLock => (1, &mut self.modifier.num_lock),
+            _ => (2, &mut self.modifier.scroll_lock),
+        };
+
+        match event {
+            ps2::Event::Pressed if !self.locks_held[index] => {
+                self.locks_held[index] = true;
+                *lock = !*lock;
+                ps2::set_leds(self.modifier.scroll_lock, self.modifier.num_lock, self.modifier.caps_lock);
+            }
+            ps2::Event::Pressed => {}
+            ps2::Event::Released => self.locks_held[index] = false,
+        }
+    }
+
+    /// Combines `c` with the previous dead key, returning what was typed, if anything.
+    fn compose(&mut self, c: CharacterFull) -> Option<CharacterFull> {
+        let Character::Char(ch) = c.character else {
+            self.dead = None;
+            return Some(c);
+        };
+
+        if layout::is_dead(ch) {
+            // Pressing a dead key twice types the accent itself.
+            if self.dead.take() == Some(ch) {
+                return Some(CharacterFull::new(Character::Char(layout::spacing(ch)), c.modifiers));
+            }
+            self.dead = Some(ch);
+            return None;
+        }
+
+        let Some(accent) = self.dead.take() else {
+            return Some(c);
+        };
+        if ch == ' ' {
+            return Some(CharacterFull::new(Character::Char(layout::spacing(accent)), c.modifiers));
+        }
+        if let Some(composed) = layout::compose(accent, ch) {
+            return Some(CharacterFull::new(Character::Char(composed), c.modifiers));
+        }
+
+        self.pending = Some(c);
+        Some(CharacterFull::new(Character::Char(layout::spacing(accent)), c.modifiers))
+    }
 }
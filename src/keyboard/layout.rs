@@ -1,5 +1,13 @@
 use crate::{keyboard::ModifierState, ps2::Key};
 
+mod azerty;
+mod dvorak;
+mod qwertz;
+
+pub use azerty::map_azerty;
+pub use dvorak::map_dvorak;
+pub use qwertz::map_qwertz;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Character {
     Char(char),
@@ -11,6 +19,11 @@ pub enum Character {
     ArrowDown,
     ArrowLeft,
     ArrowRight,
+    Home,
+    End,
+    Delete,
+    PageUp,
+    PageDown,
     CapsLock,
     NumLock,
     ScrollLock,
@@ -57,15 +70,31 @@ impl CharacterFull {
     }
 }
 
+/// Maps the keys of the keyboard to characters.
+///
+/// Layouts are selected by name with [`Layout::from_name`], which is what the
+/// `loadkeys` shell command does.
 #[derive(Clone, Copy, Debug)]
 pub struct Layout {
+    name: &'static str,
     map: fn(key: Key, modifiers: ModifierState) -> Option<CharacterFull>,
 }
 
 impl Layout {
     #[must_use]
-    pub fn new(map: fn(key: Key, modifiers: ModifierState) -> Option<CharacterFull>) -> Self {
-        Self { map }
+    pub const fn new(name: &'static str, map: fn(key: Key, modifiers: ModifierState) -> Option<CharacterFull>) -> Self {
+        Self { name, map }
+    }
+
+    /// Returns the layout called `name` in [`LAYOUTS`].
+    #[must_use]
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        LAYOUTS.iter().find(|layout| layout.name.as_bytes() == name).copied()
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
     }
 
     #[must_use]
@@ -74,9 +103,66 @@ impl Layout {
     }
 }
 
-#[must_use]
-pub fn map_qwerty(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
-    let c = match key {
+pub const QWERTY: Layout = Layout::new("qwerty", map_qwerty);
+pub const AZERTY: Layout = Layout::new("azerty", map_azerty);
+pub const QWERTZ: Layout = Layout::new("qwertz", map_qwertz);
+pub const DVORAK: Layout = Layout::new("dvorak", map_dvorak);
+
+/// Every available layout.
+pub const LAYOUTS: [Layout; 4] = [QWERTY, AZERTY, QWERTZ, DVORAK];
+
+/// Characters typed by a key alone, with Shift and with AltGr, `'\0'` marking
+/// an empty level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Levels {
+    pub normal: char,
+    pub shift: char,
+    pub altgr: char,
+}
+
+impl Levels {
+    #[must_use]
+    pub const fn new(normal: char, shift: char, altgr: char) -> Self {
+        Self { normal, shift, altgr }
+    }
+
+    /// Lowercase ASCII letter, typed uppercase with Shift.
+    #[must_use]
+    pub const fn letter(c: char) -> Self {
+        Self::new(c, c.to_ascii_uppercase(), '\0')
+    }
+
+    /// Returns the character typed with `modifiers`. Keys without an AltGr level type
+    /// their usual character when AltGr is held, and Caps Lock inverts Shift for letters.
+    #[must_use]
+    pub fn pick(self, modifiers: ModifierState) -> Option<char> {
+        let is_letter = self.normal.is_lowercase() && self.shift.is_uppercase();
+
+        let c = if modifiers.altgr_pressed && self.altgr != '\0' {
+            self.altgr
+        } else if modifiers.shift_pressed != (modifiers.caps_lock && is_letter) {
+            self.shift
+        } else {
+            self.normal
+        };
+        (c != '\0').then_some(c)
+    }
+}
+
+/// Maps `key` with the keys that are the same on every layout, and `levels` for the others.
+fn map_with(key: Key, modifiers: ModifierState, levels: fn(Key) -> Option<Levels>) -> Option<CharacterFull> {
+    let c = map_common(key, modifiers).or_else(|| levels(key)?.pick(modifiers).map(Character::Char))?;
+
+    Some(CharacterFull::new(c, modifiers))
+}
+
+/// Maps the keys that do not depend on the layout, and `None` for the others.
+fn map_common(key: Key, modifiers: ModifierState) -> Option<Character> {
+    if let Some(c) = map_keypad(key, modifiers) {
+        return Some(c);
+    }
+
+    match key {
         Key::Escape => Some(Character::Escape),
         Key::Tab => Some(Character::Tab),
         Key::Enter => Some(Character::Enter),
@@ -85,6 +171,11 @@ pub fn map_qwerty(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
         Key::ArrowDown => Some(Character::ArrowDown),
         Key::ArrowLeft => Some(Character::ArrowLeft),
         Key::ArrowRight => Some(Character::ArrowRight),
+        Key::Home => Some(Character::Home),
+        Key::End => Some(Character::End),
+        Key::Delete => Some(Character::Delete),
+        Key::PageUp => Some(Character::PageUp),
+        Key::PageDown => Some(Character::PageDown),
         Key::CapsLock => Some(Character::CapsLock),
         Key::NumLock => Some(Character::NumLock),
         Key::ScrollLock => Some(Character::ScrollLock),
@@ -116,63 +207,231 @@ pub fn map_qwerty(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
         Key::KeypadN8 => Some(Character::KeypadN8),
         Key::KeypadN9 => Some(Character::KeypadN9),
         Key::KeypadComma => Some(Character::KeypadComma),
-        Key::LeftAlt => None,
-        Key::RightAlt => None,
-        Key::LeftShift => None,
-        Key::RightShift => None,
-        Key::LeftCtrl => None,
-        Key::RightCtrl => None,
-        Key::LeftGui => None,
-        Key::RightGui => None,
-        Key::A => Some(Character::Char(if modifiers.shift_pressed { 'A' } else { 'a' })),
-        Key::B => Some(Character::Char(if modifiers.shift_pressed { 'B' } else { 'b' })),
-        Key::C => Some(Character::Char(if modifiers.shift_pressed { 'C' } else { 'c' })),
-        Key::D => Some(Character::Char(if modifiers.shift_pressed { 'D' } else { 'd' })),
-        Key::E => Some(Character::Char(if modifiers.shift_pressed { 'E' } else { 'e' })),
-        Key::F => Some(Character::Char(if modifiers.shift_pressed { 'F' } else { 'f' })),
-        Key::G => Some(Character::Char(if modifiers.shift_pressed { 'G' } else { 'g' })),
-        Key::H => Some(Character::Char(if modifiers.shift_pressed { 'H' } else { 'h' })),
-        Key::I => Some(Character::Char(if modifiers.shift_pressed { 'I' } else { 'i' })),
-        Key::J => Some(Character::Char(if modifiers.shift_pressed { 'J' } else { 'j' })),
-        Key::K => Some(Character::Char(if modifiers.shift_pressed { 'K' } else { 'k' })),
-        Key::L => Some(Character::Char(if modifiers.shift_pressed { 'L' } else { 'l' })),
-        Key::M => Some(Character::Char(if modifiers.shift_pressed { 'M' } else { 'm' })),
-        Key::N => Some(Character::Char(if modifiers.shift_pressed { 'N' } else { 'n' })),
-        Key::O => Some(Character::Char(if modifiers.shift_pressed { 'O' } else { 'o' })),
-        Key::P => Some(Character::Char(if modifiers.shift_pressed { 'P' } else { 'p' })),
-        Key::Q => Some(Character::Char(if modifiers.shift_pressed { 'Q' } else { 'q' })),
-        Key::R => Some(Character::Char(if modifiers.shift_pressed { 'R' } else { 'r' })),
-        Key::S => Some(Character::Char(if modifiers.shift_pressed { 'S' } else { 's' })),
-        Key::T => Some(Character::Char(if modifiers.shift_pressed { 'T' } else { 't' })),
-        Key::U => Some(Character::Char(if modifiers.shift_pressed { 'U' } else { 'u' })),
-        Key::V => Some(Character::Char(if modifiers.shift_pressed { 'V' } else { 'v' })),
-        Key::W => Some(Character::Char(if modifiers.shift_pressed { 'W' } else { 'w' })),
-        Key::X => Some(Character::Char(if modifiers.shift_pressed { 'X' } else { 'x' })),
-        Key::Y => Some(Character::Char(if modifiers.shift_pressed { 'Y' } else { 'y' })),
-        Key::Z => Some(Character::Char(if modifiers.shift_pressed { 'Z' } else { 'z' })),
-        Key::N0 => Some(Character::Char(if modifiers.shift_pressed { ')' } else { '0' })),
-        Key::N1 => Some(Character::Char(if modifiers.shift_pressed { '!' } else { '1' })),
-        Key::N2 => Some(Character::Char(if modifiers.shift_pressed { '@' } else { '2' })),
-        Key::N3 => Some(Character::Char(if modifiers.shift_pressed { '#' } else { '3' })),
-        Key::N4 => Some(Character::Char(if modifiers.shift_pressed { '$' } else { '4' })),
-        Key::N5 => Some(Character::Char(if modifiers.shift_pressed { '%' } else { '5' })),
-        Key::N6 => Some(Character::Char(if modifiers.shift_pressed { '^' } else { '6' })),
-        Key::N7 => Some(Character::Char(if modifiers.shift_pressed { '&' } else { '7' })),
-        Key::N8 => Some(Character::Char(if modifiers.shift_pressed { '*' } else { '8' })),
-        Key::N9 => Some(Character::Char(if modifiers.shift_pressed { '(' } else { '9' })),
-        Key::Point => Some(Character::Char(if modifiers.shift_pressed { '>' } else { '.' })),
-        Key::Equal => Some(Character::Char(if modifiers.shift_pressed { '+' } else { '=' })),
-        Key::Minus => Some(Character::Char(if modifiers.shift_pressed { '_' } else { '-' })),
-        Key::Comma => Some(Character::Char(if modifiers.shift_pressed { '<' } else { ',' })),
-        Key::Backtick => Some(Character::Char(if modifiers.shift_pressed { '~' } else { '`' })),
-        Key::Semicolon => Some(Character::Char(if modifiers.shift_pressed { ':' } else { ';' })),
-        Key::Slash => Some(Character::Char(if modifiers.shift_pressed { '?' } else { '/' })),
-        Key::Backslash => Some(Character::Char(if modifiers.shift_pressed { '|' } else { '\\' })),
-        Key::SingleQuote => Some(Character::Char(if modifiers.shift_pressed { '\"' } else { '\'' })),
-        Key::SquareBracketsOpen => Some(Character::Char(if modifiers.shift_pressed { '{' } else { '}' })),
-        Key::SquareBracketsClosed => Some(Character::Char(if modifiers.shift_pressed { '}' } else { ']' })),
         Key::Space => Some(Character::Char(' ')),
-    }?;
+        _ => None,
+    }
+}
 
-    Some(CharacterFull::new(c, modifiers))
+/// Maps the keypad's digits with Num Lock on, and its navigation keys with Num Lock off.
+fn map_keypad(key: Key, modifiers: ModifierState) -> Option<Character> {
+    if modifiers.num_lock {
+        let digit = match key {
+            Key::KeypadN0 => '0',
+            Key::KeypadN1 => '1',
+            Key::KeypadN2 => '2',
+            Key::KeypadN3 => '3',
+            Key::KeypadN4 => '4',
+            Key::KeypadN5 => '5',
+            Key::KeypadN6 => '6',
+            Key::KeypadN7 => '7',
+            Key::KeypadN8 => '8',
+            Key::KeypadN9 => '9',
+            Key::KeypadComma => '.',
+            _ => return None,
+        };
+        return Some(Character::Char(digit));
+    }
+
+    match key {
+        Key::KeypadN1 => Some(Character::End),
+        Key::KeypadN2 => Some(Character::ArrowDown),
+        Key::KeypadN3 => Some(Character::PageDown),
+        Key::KeypadN4 => Some(Character::ArrowLeft),
+        Key::KeypadN6 => Some(Character::ArrowRight),
+        Key::KeypadN7 => Some(Character::Home),
+        Key::KeypadN8 => Some(Character::ArrowUp),
+        Key::KeypadN9 => Some(Character::PageUp),
+        Key::KeypadComma => Some(Character::Delete),
+        _ => None,
+    }
+}
+
+#[must_use]
+pub fn map_qwerty(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
+    map_with(key, modifiers, qwerty_levels)
+}
+
+fn qwerty_levels(key: Key) -> Option<Levels> {
+    let levels = match key {
+        Key::A => Levels::letter('a'),
+        Key::B => Levels::letter('b'),
+        Key::C => Levels::letter('c'),
+        Key::D => Levels::letter('d'),
+        Key::E => Levels::letter('e'),
+        Key::F => Levels::letter('f'),
+        Key::G => Levels::letter('g'),
+        Key::H => Levels::letter('h'),
+        Key::I => Levels::letter('i'),
+        Key::J => Levels::letter('j'),
+        Key::K => Levels::letter('k'),
+        Key::L => Levels::letter('l'),
+        Key::M => Levels::letter('m'),
+        Key::N => Levels::letter('n'),
+        Key::O => Levels::letter('o'),
+        Key::P => Levels::letter('p'),
+        Key::Q => Levels::letter('q'),
+        Key::R => Levels::letter('r'),
+        Key::S => Levels::letter('s'),
+        Key::T => Levels::letter('t'),
+        Key::U => Levels::letter('u'),
+        Key::V => Levels::letter('v'),
+        Key::W => Levels::letter('w'),
+        Key::X => Levels::letter('x'),
+        Key::Y => Levels::letter('y'),
+        Key::Z => Levels::letter('z'),
+        Key::N0 => Levels::new('0', ')', '\0'),
+        Key::N1 => Levels::new('1', '!', '\0'),
+        Key::N2 => Levels::new('2', '@', '\0'),
+        Key::N3 => Levels::new('3', '#', '\0'),
+        Key::N4 => Levels::new('4', '$', '\0'),
+        Key::N5 => Levels::new('5', '%', '\0'),
+        Key::N6 => Levels::new('6', '^', '\0'),
+        Key::N7 => Levels::new('7', '&', '\0'),
+        Key::N8 => Levels::new('8', '*', '\0'),
+        Key::N9 => Levels::new('9', '(', '\0'),
+        Key::Point => Levels::new('.', '>', '\0'),
+        Key::Equal => Levels::new('=', '+', '\0'),
+        Key::Minus => Levels::new('-', '_', '\0'),
+        Key::Comma => Levels::new(',', '<', '\0'),
+        Key::Backtick => Levels::new('`', '~', '\0'),
+        Key::Semicolon => Levels::new(';', ':', '\0'),
+        Key::Slash => Levels::new('/', '?', '\0'),
+        Key::Backslash | Key::NonUsBackslash => Levels::new('\\', '|', '\0'),
+        Key::SingleQuote => Levels::new('\'', '"', '\0'),
+        Key::SquareBracketsOpen => Levels::new('[', '{', '\0'),
+        Key::SquareBracketsClosed => Levels::new(']', '}', '\0'),
+        _ => return None,
+    };
+
+    Some(levels)
+}
+
+/// Dead keys, which accent the next character instead of being typed. They are
+/// represented by the matching Unicode combining characters.
+pub const DEAD_GRAVE: char = '\u{300}';
+pub const DEAD_ACUTE: char = '\u{301}';
+pub const DEAD_CIRCUMFLEX: char = '\u{302}';
+pub const DEAD_TILDE: char = '\u{303}';
+pub const DEAD_DIAERESIS: char = '\u{308}';
+
+/// Accented letters, by dead key and base letter.
+const COMPOSITIONS: [(char, &str, &str); 5] = [
+    (DEAD_GRAVE, "aeiou", "àèìòù"),
+    (DEAD_ACUTE, "aeiouy", "áéíóúý"),
+    (DEAD_CIRCUMFLEX, "aeiou", "âêîôû"),
+    (DEAD_TILDE, "ano", "ãñõ"),
+    (DEAD_DIAERESIS, "aeiouy", "äëïöüÿ"),
+];
+
+/// Returns whether `c` is a dead key.
+#[must_use]
+pub const fn is_dead(c: char) -> bool {
+    matches!(c, '\u{300}'..='\u{36f}')
+}
+
+/// Returns the character typed by dead key `accent` on its own, like when it is
+/// followed by a space.
+#[must_use]
+pub const fn spacing(accent: char) -> char {
+    match accent {
+        DEAD_GRAVE => '`',
+        DEAD_ACUTE => '\'',
+        DEAD_CIRCUMFLEX => '^',
+        DEAD_TILDE => '~',
+        DEAD_DIAERESIS => '"',
+        _ => accent,
+    }
+}
+
+/// Returns `base` accented by dead key `accent`, if there is such a character.
+#[must_use]
+pub fn compose(accent: char, base: char) -> Option<char> {
+    let (_, bases, composed) = COMPOSITIONS.iter().find(|(dead, _, _)| *dead == accent)?;
+    let c = composed.chars().nth(bases.chars().position(|b| b == base.to_ascii_lowercase())?)?;
+
+    if base.is_ascii_uppercase() { c.to_uppercase().next() } else { Some(c) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    fn typed(layout: Layout, key: Key, modifiers: ModifierState) -> Option<char> {
+        match layout.map(key, modifiers)?.character {
+            Character::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    #[test_case]
+    fn layouts_place_letters_differently() -> Result<(), &'static str> {
+        let none = ModifierState::default();
+
+        kassert_eq!(typed(QWERTY, Key::Q, none), Some('q'));
+        kassert_eq!(typed(AZERTY, Key::Q, none), Some('a'));
+        kassert_eq!(typed(QWERTZ, Key::Y, none), Some('z'));
+        kassert_eq!(typed(DVORAK, Key::S, none), Some('o'));
+        kassert_eq!(typed(AZERTY, Key::N2, none), Some('é'));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn altgr_types_the_third_level() -> Result<(), &'static str> {
+        let altgr = ModifierState {
+            altgr_pressed: true,
+            ..ModifierState::default()
+        };
+
+        kassert_eq!(typed(AZERTY, Key::N0, altgr), Some('@'));
+        kassert_eq!(typed(QWERTZ, Key::Q, altgr), Some('@'));
+        kassert_eq!(typed(QWERTY, Key::Q, altgr), Some('q'));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn caps_lock_only_affects_letters() -> Result<(), &'static str> {
+        let caps = ModifierState {
+            caps_lock: true,
+            ..ModifierState::default()
+        };
+        let caps_shift = ModifierState { shift_pressed: true, ..caps };
+
+        kassert_eq!(typed(QWERTY, Key::A, caps), Some('A'));
+        kassert_eq!(typed(QWERTY, Key::A, caps_shift), Some('a'));
+        kassert_eq!(typed(QWERTY, Key::N1, caps), Some('1'));
+        kassert_eq!(typed(QWERTZ, Key::Semicolon, caps), Some('Ö'));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn num_lock_switches_the_keypad() -> Result<(), &'static str> {
+        let num = ModifierState {
+            num_lock: true,
+            ..ModifierState::default()
+        };
+
+        kassert_eq!(typed(QWERTY, Key::KeypadN8, num), Some('8'));
+        kassert!(matches!(
+            QWERTY.map(Key::KeypadN8, ModifierState::default()).map(|c| c.character),
+            Some(Character::ArrowUp)
+        ));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn dead_keys_compose() -> Result<(), &'static str> {
+        kassert!(is_dead(DEAD_CIRCUMFLEX));
+        kassert_eq!(compose(DEAD_CIRCUMFLEX, 'e'), Some('ê'));
+        kassert_eq!(compose(DEAD_ACUTE, 'E'), Some('É'));
+        kassert_eq!(compose(DEAD_DIAERESIS, 'x'), None);
+        kassert_eq!(spacing(DEAD_TILDE), '~');
+        kassert!(Layout::from_name(b"qwertz").is_some_and(|l| l.name() == "qwertz"));
+
+        Ok(())
+    }
 }
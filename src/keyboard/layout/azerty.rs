@@ -0,0 +1,70 @@
+//! French AZERTY layout.
+
+use crate::{
+    keyboard::{
+        ModifierState,
+        layout::{CharacterFull, DEAD_CIRCUMFLEX, DEAD_DIAERESIS, DEAD_GRAVE, DEAD_TILDE, Levels, map_with},
+    },
+    ps2::Key,
+};
+
+#[must_use]
+pub fn map_azerty(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
+    map_with(key, modifiers, levels)
+}
+
+fn levels(key: Key) -> Option<Levels> {
+    let levels = match key {
+        Key::A => Levels::letter('q'),
+        Key::B => Levels::letter('b'),
+        Key::C => Levels::letter('c'),
+        Key::D => Levels::letter('d'),
+        Key::E => Levels::new('e', 'E', '€'),
+        Key::F => Levels::letter('f'),
+        Key::G => Levels::letter('g'),
+        Key::H => Levels::letter('h'),
+        Key::I => Levels::letter('i'),
+        Key::J => Levels::letter('j'),
+        Key::K => Levels::letter('k'),
+        Key::L => Levels::letter('l'),
+        Key::M => Levels::new(',', '?', '\0'),
+        Key::N => Levels::letter('n'),
+        Key::O => Levels::letter('o'),
+        Key::P => Levels::letter('p'),
+        Key::Q => Levels::letter('a'),
+        Key::R => Levels::letter('r'),
+        Key::S => Levels::letter('s'),
+        Key::T => Levels::letter('t'),
+        Key::U => Levels::letter('u'),
+        Key::V => Levels::letter('v'),
+        Key::W => Levels::letter('z'),
+        Key::X => Levels::letter('x'),
+        Key::Y => Levels::letter('y'),
+        Key::Z => Levels::letter('w'),
+        Key::Semicolon => Levels::letter('m'),
+        Key::Backtick => Levels::new('²', '\0', '\0'),
+        Key::N1 => Levels::new('&', '1', '\0'),
+        Key::N2 => Levels::new('é', '2', DEAD_TILDE),
+        Key::N3 => Levels::new('"', '3', '#'),
+        Key::N4 => Levels::new('\'', '4', '{'),
+        Key::N5 => Levels::new('(', '5', '['),
+        Key::N6 => Levels::new('-', '6', '|'),
+        Key::N7 => Levels::new('è', '7', DEAD_GRAVE),
+        Key::N8 => Levels::new('_', '8', '\\'),
+        Key::N9 => Levels::new('ç', '9', '^'),
+        Key::N0 => Levels::new('à', '0', '@'),
+        Key::Minus => Levels::new(')', '°', ']'),
+        Key::Equal => Levels::new('=', '+', '}'),
+        Key::SquareBracketsOpen => Levels::new(DEAD_CIRCUMFLEX, DEAD_DIAERESIS, '\0'),
+        Key::SquareBracketsClosed => Levels::new('$', '£', '¤'),
+        Key::SingleQuote => Levels::new('ù', '%', '\0'),
+        Key::Backslash => Levels::new('*', 'µ', '\0'),
+        Key::NonUsBackslash => Levels::new('<', '>', '\0'),
+        Key::Comma => Levels::new(';', '.', '\0'),
+        Key::Point => Levels::new(':', '/', '\0'),
+        Key::Slash => Levels::new('!', '§', '\0'),
+        _ => return None,
+    };
+
+    Some(levels)
+}
@@ -0,0 +1,69 @@
+//! US Dvorak layout.
+
+use crate::{
+    keyboard::{
+        ModifierState,
+        layout::{CharacterFull, Levels, map_with},
+    },
+    ps2::Key,
+};
+
+#[must_use]
+pub fn map_dvorak(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
+    map_with(key, modifiers, levels)
+}
+
+fn levels(key: Key) -> Option<Levels> {
+    let levels = match key {
+        Key::Q => Levels::new('\'', '"', '\0'),
+        Key::W => Levels::new(',', '<', '\0'),
+        Key::E => Levels::new('.', '>', '\0'),
+        Key::R => Levels::letter('p'),
+        Key::T => Levels::letter('y'),
+        Key::Y => Levels::letter('f'),
+        Key::U => Levels::letter('g'),
+        Key::I => Levels::letter('c'),
+        Key::O => Levels::letter('r'),
+        Key::P => Levels::letter('l'),
+        Key::SquareBracketsOpen => Levels::new('/', '?', '\0'),
+        Key::SquareBracketsClosed => Levels::new('=', '+', '\0'),
+        Key::A => Levels::letter('a'),
+        Key::S => Levels::letter('o'),
+        Key::D => Levels::letter('e'),
+        Key::F => Levels::letter('u'),
+        Key::G => Levels::letter('i'),
+        Key::H => Levels::letter('d'),
+        Key::J => Levels::letter('h'),
+        Key::K => Levels::letter('t'),
+        Key::L => Levels::letter('n'),
+        Key::Semicolon => Levels::letter('s'),
+        Key::SingleQuote => Levels::new('-', '_', '\0'),
+        Key::Z => Levels::new(';', ':', '\0'),
+        Key::X => Levels::letter('q'),
+        Key::C => Levels::letter('j'),
+        Key::V => Levels::letter('k'),
+        Key::B => Levels::letter('x'),
+        Key::N => Levels::letter('b'),
+        Key::M => Levels::letter('m'),
+        Key::Comma => Levels::letter('w'),
+        Key::Point => Levels::letter('v'),
+        Key::Slash => Levels::letter('z'),
+        Key::Backtick => Levels::new('`', '~', '\0'),
+        Key::N0 => Levels::new('0', ')', '\0'),
+        Key::N1 => Levels::new('1', '!', '\0'),
+        Key::N2 => Levels::new('2', '@', '\0'),
+        Key::N3 => Levels::new('3', '#', '\0'),
+        Key::N4 => Levels::new('4', '$', '\0'),
+        Key::N5 => Levels::new('5', '%', '\0'),
+        Key::N6 => Levels::new('6', '^', '\0'),
+        Key::N7 => Levels::new('7', '&', '\0'),
+        Key::N8 => Levels::new('8', '*', '\0'),
+        Key::N9 => Levels::new('9', '(', '\0'),
+        Key::Minus => Levels::new('[', '{', '\0'),
+        Key::Equal => Levels::new(']', '}', '\0'),
+        Key::Backslash | Key::NonUsBackslash => Levels::new('\\', '|', '\0'),
+        _ => return None,
+    };
+
+    Some(levels)
+}
@@ -0,0 +1,70 @@
+//! German QWERTZ layout.
+
+use crate::{
+    keyboard::{
+        ModifierState,
+        layout::{CharacterFull, DEAD_ACUTE, DEAD_CIRCUMFLEX, DEAD_GRAVE, Levels, map_with},
+    },
+    ps2::Key,
+};
+
+#[must_use]
+pub fn map_qwertz(key: Key, modifiers: ModifierState) -> Option<CharacterFull> {
+    map_with(key, modifiers, levels)
+}
+
+fn levels(key: Key) -> Option<Levels> {
+    let levels = match key {
+        Key::A => Levels::letter('a'),
+        Key::B => Levels::letter('b'),
+        Key::C => Levels::letter('c'),
+        Key::D => Levels::letter('d'),
+        Key::E => Levels::new('e', 'E', '€'),
+        Key::F => Levels::letter('f'),
+        Key::G => Levels::letter('g'),
+        Key::H => Levels::letter('h'),
+        Key::I => Levels::letter('i'),
+        Key::J => Levels::letter('j'),
+        Key::K => Levels::letter('k'),
+        Key::L => Levels::letter('l'),
+        Key::M => Levels::new('m', 'M', 'µ'),
+        Key::N => Levels::letter('n'),
+        Key::O => Levels::letter('o'),
+        Key::P => Levels::letter('p'),
+        Key::Q => Levels::new('q', 'Q', '@'),
+        Key::R => Levels::letter('r'),
+        Key::S => Levels::letter('s'),
+        Key::T => Levels::letter('t'),
+        Key::U => Levels::letter('u'),
+        Key::V => Levels::letter('v'),
+        Key::W => Levels::letter('w'),
+        Key::X => Levels::letter('x'),
+        Key::Y => Levels::letter('z'),
+        Key::Z => Levels::letter('y'),
+        Key::Backtick => Levels::new(DEAD_CIRCUMFLEX, '°', '\0'),
+        Key::N1 => Levels::new('1', '!', '\0'),
+        Key::N2 => Levels::new('2', '"', '²'),
+        Key::N3 => Levels::new('3', '§', '³'),
+        Key::N4 => Levels::new('4', '$', '\0'),
+        Key::N5 => Levels::new('5', '%', '\0'),
+        Key::N6 => Levels::new('6', '&', '\0'),
+        Key::N7 => Levels::new('7', '/', '{'),
+        Key::N8 => Levels::new('8', '(', '['),
+        Key::N9 => Levels::new('9', ')', ']'),
+        Key::N0 => Levels::new('0', '=', '}'),
+        Key::Minus => Levels::new('ß', '?', '\\'),
+        Key::Equal => Levels::new(DEAD_ACUTE, DEAD_GRAVE, '\0'),
+        Key::SquareBracketsOpen => Levels::new('ü', 'Ü', '\0'),
+        Key::SquareBracketsClosed => Levels::new('+', '*', '~'),
+        Key::Semicolon => Levels::new('ö', 'Ö', '\0'),
+        Key::SingleQuote => Levels::new('ä', 'Ä', '\0'),
+        Key::Backslash => Levels::new('#', '\'', '\0'),
+        Key::NonUsBackslash => Levels::new('<', '>', '|'),
+        Key::Comma => Levels::new(',', ';', '\0'),
+        Key::Point => Levels::new('.', ':', '\0'),
+        Key::Slash => Levels::new('-', '_', '\0'),
+        _ => return None,
+    };
+
+    Some(levels)
+}
@@ -1,6 +1,8 @@
 #![no_std]
 #![no_main]
 #![feature(const_ops)]
+#![feature(allocator_api)]
+#![feature(alloc_error_handler)]
 #![feature(const_default)]
 #![feature(const_convert)]
 #![feature(const_trait_impl)]
@@ -18,20 +20,27 @@ use core::panic::PanicInfo;
 pub extern crate alloc;
 
 pub mod arch;
+pub mod ata;
 pub mod bitmap;
+pub mod block;
 pub mod boot;
 pub mod conv;
+pub mod input;
 pub mod keyboard;
+pub mod log;
 pub mod macros;
+pub mod pci;
 pub mod port;
 pub mod printk;
 pub mod ps2;
 pub mod qemu;
+pub mod registry;
 pub mod serial;
 pub mod shell;
 pub mod stack_print_serial;
 pub mod terminal;
 pub mod tester;
+pub mod vfs;
 pub mod vmm;
 
 #[cfg(test)]
@@ -0,0 +1,333 @@
+//! Kernel log.
+//!
+//! Every message logged through [`klog!`](crate::klog) (or one of the
+//! `pr_<level>!` shorthands) is stamped with the uptime and stored in a
+//! fixed-size ring buffer, so that it can still be read with `dmesg` once it
+//! scrolled off the screen. Messages are also mirrored to the log console
+//! (Alt+F6) and to COM1 when their level is at least as severe as the
+//! corresponding threshold.
+//!
+//! ```ignore
+//! pr_info!("kmalloc: {} slab caches initialized", SLAB_CONFIGS.len());
+//! klog!(Level::Err, "ps2: controller self-test failed ({:#04x})", res);
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::{
+    arch::x86::pit,
+    serial_println,
+    terminal::console::{self, LOG_CONSOLE},
+};
+
+/// Number of records kept in the ring buffer before the oldest ones get overwritten.
+pub const LOG_CAPACITY: usize = 256;
+
+/// Maximum length of a single message, longer messages are truncated.
+pub const MESSAGE_SIZE: usize = 120;
+
+/// Severity of a message, from most to least severe.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Emerg = 0,
+    Alert = 1,
+    Crit = 2,
+    Err = 3,
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+    Debug = 7,
+}
+
+impl Level {
+    pub const ALL: [Self; 8] = [
+        Self::Emerg,
+        Self::Alert,
+        Self::Crit,
+        Self::Err,
+        Self::Warning,
+        Self::Notice,
+        Self::Info,
+        Self::Debug,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Emerg => "emerg",
+            Self::Alert => "alert",
+            Self::Crit => "crit",
+            Self::Err => "err",
+            Self::Warning => "warn",
+            Self::Notice => "notice",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+
+    /// Parses a level from its name (`emerg`, ..., `debug`) or its numeric value (`0`, ..., `7`).
+    #[must_use]
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|l| l.name().as_bytes() == name || [b'0' + *l as u8] == name)
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Emerg,
+            1 => Self::Alert,
+            2 => Self::Crit,
+            3 => Self::Err,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Info,
+            _ => Self::Debug,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Record {
+    pub level: Level,
+    /// Uptime in milliseconds at which the message was logged.
+    pub timestamp_ms: u32,
+    len: usize,
+    message: [u8; MESSAGE_SIZE],
+}
+
+impl Record {
+    const fn new(level: Level, timestamp_ms: u32) -> Self {
+        Self {
+            level,
+            timestamp_ms,
+            len: 0,
+            message: [0; MESSAGE_SIZE],
+        }
+    }
+
+    #[must_use]
+    pub fn message(&self) -> &str {
+        let message = &self.message[..self.len];
+
+        // Truncation may have cut a multi-byte character in half, only keep the valid prefix.
+        match core::str::from_utf8(message) {
+            Ok(s) => s,
+            Err(e) => core::str::from_utf8(&message[..e.valid_up_to()]).unwrap_or_default(),
+        }
+    }
+}
+
+impl Write for Record {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(MESSAGE_SIZE - self.len);
+        self.message[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>5}.{:03}] {:>6}: {}",
+            self.timestamp_ms / 1000,
+            self.timestamp_ms % 1000,
+            self.level,
+            self.message()
+        )
+    }
+}
+
+struct LogBuffer {
+    records: [Record; LOG_CAPACITY],
+    head: usize,
+    len: usize,
+}
+
+impl LogBuffer {
+    fn push(&mut self, record: Record) {
+        self.records[(self.head + self.len) % LOG_CAPACITY] = record;
+
+        if self.len == LOG_CAPACITY {
+            self.head = (self.head + 1) % LOG_CAPACITY;
+        } else {
+            self.len += 1;
+        }
+    }
+}
+
+static mut LOG: LogBuffer = LogBuffer {
+    records: [Record::new(Level::Debug, 0); LOG_CAPACITY],
+    head: 0,
+    len: 0,
+};
+
+static CONSOLE_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static SERIAL_LEVEL: AtomicU8 = AtomicU8::new(Level::Debug as u8);
+
+#[allow(static_mut_refs)]
+fn log_buffer() -> &'static mut LogBuffer {
+    // SAFETY:
+    // The log buffer is only reachable through this module.
+    unsafe { &mut LOG }
+}
+
+/// Messages at least as severe as `level` are mirrored to the log console.
+pub fn set_console_level(level: Level) {
+    CONSOLE_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn console_level() -> Level {
+    Level::from_u8(CONSOLE_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Messages at least as severe as `level` are mirrored to COM1.
+pub fn set_serial_level(level: Level) {
+    SERIAL_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn serial_level() -> Level {
+    Level::from_u8(SERIAL_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Returns the records currently held by the ring buffer, oldest first.
+pub fn records() -> impl Iterator<Item = Record> {
+    let log = log_buffer();
+    let (head, len) = (log.head, log.len);
+
+    (0..len).map(move |i| log_buffer().records[(head + i) % LOG_CAPACITY])
+}
+
+#[doc(hidden)]
+pub fn log_internal(level: Level, args: fmt::Arguments) {
+    let mut record = Record::new(level, pit::uptime_ms());
+    let _ = record.write_fmt(args);
+
+    log_buffer().push(record);
+
+    if level <= console_level() {
+        console::print(LOG_CONSOLE, format_args!("{record}\n"));
+    }
+
+    if level <= serial_level() {
+        serial_println!("{}", record);
+    }
+}
+
+#[macro_export]
+macro_rules! klog {
+    ($level:expr, $($arg:tt)*) => {{
+        $crate::log::log_internal($level, format_args!($($arg)*));
+    }};
+}
+
+#[macro_export]
+macro_rules! pr_emerg {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Emerg, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_alert {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Alert, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_crit {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Crit, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_err {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Err, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_warn {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Warning, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_notice {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Notice, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_info {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Info, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! pr_debug {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Debug, $($arg)*));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn long_messages_are_truncated() -> Result<(), &'static str> {
+        let mut record = Record::new(Level::Info, 0);
+        for _ in 0..MESSAGE_SIZE {
+            let _ = record.write_str("ab");
+        }
+
+        kassert_eq!(record.message().len(), MESSAGE_SIZE);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn truncation_keeps_valid_utf8() -> Result<(), &'static str> {
+        let mut record = Record::new(Level::Info, 0);
+        for _ in 0..MESSAGE_SIZE - 1 {
+            let _ = record.write_str("a");
+        }
+        let _ = record.write_str("é");
+
+        kassert_eq!(record.message().len(), MESSAGE_SIZE - 1);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn ring_overwrites_oldest() -> Result<(), &'static str> {
+        let mut log = LogBuffer {
+            records: [Record::new(Level::Debug, 0); LOG_CAPACITY],
+            head: 0,
+            len: 0,
+        };
+
+        for i in 0..LOG_CAPACITY + 3 {
+            log.push(Record::new(Level::Info, i as u32));
+        }
+
+        kassert_eq!(log.len, LOG_CAPACITY);
+        kassert_eq!(log.records[log.head].timestamp_ms, 3);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn levels_parse_from_names_and_numbers() -> Result<(), &'static str> {
+        kassert_eq!(Level::from_name(b"warn"), Some(Level::Warning));
+        kassert_eq!(Level::from_name(b"3"), Some(Level::Err));
+        kassert!(Level::from_name(b"loud").is_none());
+
+        Ok(())
+    }
+}
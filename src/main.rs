@@ -6,11 +6,11 @@
 
 use kfs::{
     boot::MultibootInfo,
-    keyboard::{
-        Keyboard,
-        layout::{Layout, map_qwerty},
-    },
-    shell::Shell,
+    keyboard::{Keyboard, layout},
+    pr_err, pr_info,
+    ps2::ScancodeSet,
+    serial::SerialInput,
+    shell::{self, Input},
 };
 
 mod panic;
@@ -18,7 +18,8 @@ mod panic;
 extern crate alloc;
 
 /// # Panics
-/// This function will panic if initialization of dynamic memory allocation fails.
+/// This function will panic if initialization of dynamic memory allocation fails, or if
+/// the shell cannot subscribe to input events.
 #[cfg(not(test))]
 #[unsafe(no_mangle)]
 pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
@@ -27,22 +28,54 @@ pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
         vmm::{self, paging::init::init_memory},
     };
 
+    // SAFETY:
+    // Paging is not initialized yet, and only a flag is kept from the command line.
+    let cmdline = unsafe { info.cmdline() };
+    let serial_console = cmdline.is_some_and(|cmdline| cmdline.split(|c| *c == b' ').any(|arg| arg == b"console=ttyS0"));
+
     arch::x86::gdt::init();
     arch::x86::idt::init();
+    arch::x86::pit::init();
 
     init_memory(info);
-
-    kfs::ps2::init();
+    pr_info!("paging: kernel page tables initialized");
+
+    match kfs::ps2::init(ScancodeSet::One) {
+        Ok(devices) => {
+            for (port, device) in devices.iter().enumerate() {
+                if let Some(device) = device {
+                    pr_info!("ps2: port {}: {}", port + 1, device);
+                }
+            }
+        }
+        Err(e) => pr_err!("ps2: {}", e),
+    }
 
     if vmm::allocators::kmalloc::init().is_err() {
         panic!("Failed to initialize kmalloc");
     }
+    pr_info!("kmalloc: buddy and slab allocators initialized");
+
+    kfs::pci::init();
+    pr_info!("pci: {} functions found", kfs::pci::devices().count());
+    kfs::block::init();
+    kfs::ata::init();
+    kfs::vfs::init();
 
     unsafe { core::arch::asm!("int 0x80") };
 
-    #[allow(static_mut_refs)]
-    let mut shell = Shell::default(unsafe { &mut kfs::terminal::SCREEN }, Keyboard::new(Layout::new(map_qwerty)));
-    shell.launch();
+    let input = if serial_console {
+        kfs::serial::init_rx();
+        pr_info!("serial: console on COM1");
+        SerialInput::new().map(Input::Serial)
+    } else {
+        Keyboard::new(layout::QWERTY).map(Input::Keyboard)
+    };
+    let Ok(input) = input else {
+        panic!("Failed to subscribe the shell to input events");
+    };
+
+    shell::launch(input);
 }
 
 /// # Panics
@@ -57,7 +90,7 @@ pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
 
     vmm::paging::init::init_memory(info);
 
-    kfs::ps2::init();
+    let _ = kfs::ps2::init(kfs::ps2::ScancodeSet::One);
 
     if vmm::allocators::kmalloc::init().is_err() {
         panic!("Failed to initialize kmalloc");
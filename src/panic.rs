@@ -14,6 +14,7 @@ fn panic(info: &PanicInfo) -> ! {
 
     cli!();
 
+    kfs::printk::stop_capture();
     printkln!("KERNEL PANIC: {:?}\n", info.message());
 
     serial_println!("KERNEL PANIC: {:?}", info.message());
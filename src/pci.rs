@@ -0,0 +1,364 @@
+//! PCI bus.
+//!
+//! The configuration space of every function is reached through mechanism #1:
+//! its address is written to `CONFIG_ADDRESS`, then the selected dword is read
+//! from or written to `CONFIG_DATA`.
+//!
+//! [`init`] walks the buses from the host bridge down through the PCI-to-PCI
+//! bridges, records every function found, and hands them to the drivers
+//! registered with [`driver::register`].
+//!
+//! https://wiki.osdev.org/PCI
+
+use core::fmt;
+
+use crate::{
+    port::Port,
+    printkln,
+    registry::Registry,
+    shell::{
+        argv::Argv,
+        command::{self, Command, ExitStatus, Io, SUCCESS, USAGE},
+        completion::Completions,
+    },
+};
+
+pub mod bar;
+pub mod class;
+pub mod driver;
+
+pub use bar::Bar;
+
+const CONFIG_ADDRESS: u16 = 0xcf8;
+const CONFIG_DATA: u16 = 0xcfc;
+
+/// Set in `CONFIG_ADDRESS` for the access to go to the configuration space.
+const ENABLE: u32 = 1 << 31;
+
+const VENDOR_ID: u8 = 0x00;
+const DEVICE_ID: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+const REVISION: u8 = 0x08;
+const PROG_IF: u8 = 0x09;
+const SUBCLASS: u8 = 0x0a;
+const CLASS: u8 = 0x0b;
+const HEADER_TYPE: u8 = 0x0e;
+const BAR0: u8 = 0x10;
+const SECONDARY_BUS: u8 = 0x19;
+const INTERRUPT_LINE: u8 = 0x3c;
+const INTERRUPT_PIN: u8 = 0x3d;
+
+/// Vendor ID read from an empty slot.
+const NO_DEVICE: u16 = 0xffff;
+
+const MULTI_FUNCTION: u8 = 1 << 7;
+const HEADER_TYPE_MASK: u8 = 0x7f;
+const HEADER_DEVICE: u8 = 0x00;
+const HEADER_BRIDGE: u8 = 0x01;
+
+/// Command register bits.
+pub const COMMAND_IO_SPACE: u16 = 1 << 0;
+pub const COMMAND_MEMORY_SPACE: u16 = 1 << 1;
+pub const COMMAND_BUS_MASTER: u16 = 1 << 2;
+
+pub const BUS_COUNT: usize = 256;
+pub const DEVICES_PER_BUS: u8 = 32;
+pub const FUNCTIONS_PER_DEVICE: u8 = 8;
+
+/// Maximum number of functions recorded by [`init`].
+pub const MAX_DEVICES: usize = 64;
+
+/// Location of a function in the configuration space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Address {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl Address {
+    #[must_use]
+    pub const fn new(bus: u8, device: u8, function: u8) -> Self {
+        Self { bus, device, function }
+    }
+
+    /// Returns the value of `CONFIG_ADDRESS` selecting the dword holding `offset`.
+    const fn config_address(self, offset: u8) -> u32 {
+        ENABLE | (self.bus as u32) << 16 | (self.device as u32) << 11 | (self.function as u32) << 8 | (offset & 0xfc) as u32
+    }
+
+    /// Reads the dword at `offset`, which is rounded down to a multiple of 4.
+    #[must_use]
+    pub fn read_u32(self, offset: u8) -> u32 {
+        // SAFETY:
+        // Mechanism #1 is supported by every PCI host bridge QEMU emulates, and reading the
+        // configuration space has no side effect.
+        unsafe {
+            Port::new(CONFIG_ADDRESS).write(self.config_address(offset));
+            Port::new(CONFIG_DATA).read()
+        }
+    }
+
+    #[must_use]
+    pub fn read_u16(self, offset: u8) -> u16 {
+        (self.read_u32(offset) >> ((offset & 2) * 8)) as u16
+    }
+
+    #[must_use]
+    pub fn read_u8(self, offset: u8) -> u8 {
+        (self.read_u32(offset) >> ((offset & 3) * 8)) as u8
+    }
+
+    /// Writes the dword at `offset`, which is rounded down to a multiple of 4.
+    ///
+    /// # Safety
+    /// The caller must make sure that the write does not break the device, or the memory and
+    /// ports it decodes.
+    pub unsafe fn write_u32(self, offset: u8, value: u32) {
+        // SAFETY:
+        // The caller is responsible for the effect of the write on the device.
+        unsafe {
+            Port::new(CONFIG_ADDRESS).write(self.config_address(offset));
+            Port::new(CONFIG_DATA).write(value);
+        }
+    }
+
+    /// Writes the word at `offset`, keeping the other half of its dword.
+    ///
+    /// # Safety
+    /// See [`Address::write_u32`].
+    pub unsafe fn write_u16(self, offset: u8, value: u16) {
+        let shift = (offset & 2) * 8;
+        let dword = (self.read_u32(offset) & !(0xffff << shift)) | u32::from(value) << shift;
+        // SAFETY:
+        // The caller is responsible for the effect of the write on the device.
+        unsafe { self.write_u32(offset, dword) };
+    }
+
+    fn vendor_id(self) -> u16 {
+        self.read_u16(VENDOR_ID)
+    }
+
+    fn header_type(self) -> u8 {
+        self.read_u8(HEADER_TYPE)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+    }
+}
+
+/// Function found on the bus.
+#[derive(Clone, Copy, Debug)]
+pub struct Device {
+    pub address: Address,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    /// Header type, without the multi-function bit.
+    pub header_type: u8,
+    /// Base address registers, `None` for the unused ones and the upper halves of 64-bit BARs.
+    pub bars: [Option<Bar>; 6],
+    /// IRQ the firmware routed the interrupt pin to, `0xff` if none.
+    pub interrupt_line: u8,
+    /// Interrupt pin used, from 1 for INTA# to 4 for INTD#, or 0 if none.
+    pub interrupt_pin: u8,
+    /// Name of the driver bound to the device, if any.
+    pub driver: Option<&'static str>,
+}
+
+impl Device {
+    fn read(address: Address) -> Self {
+        let header_type = address.header_type() & HEADER_TYPE_MASK;
+        let bar_count = match header_type {
+            HEADER_DEVICE => 6,
+            HEADER_BRIDGE => 2,
+            _ => 0,
+        };
+
+        let mut bars = [None; 6];
+        let mut index = 0;
+        while index < bar_count {
+            let (bar, slots) = bar::probe(address, BAR0 + index as u8 * 4);
+            bars[index] = bar;
+            index += slots;
+        }
+
+        Self {
+            address,
+            vendor_id: address.vendor_id(),
+            device_id: address.read_u16(DEVICE_ID),
+            class: address.read_u8(CLASS),
+            subclass: address.read_u8(SUBCLASS),
+            prog_if: address.read_u8(PROG_IF),
+            revision: address.read_u8(REVISION),
+            header_type,
+            bars,
+            interrupt_line: address.read_u8(INTERRUPT_LINE),
+            interrupt_pin: address.read_u8(INTERRUPT_PIN),
+            driver: None,
+        }
+    }
+
+    /// Returns the description of the class of the device.
+    #[must_use]
+    pub fn class_name(&self) -> &'static str {
+        class::name(self.class, self.subclass)
+    }
+
+    /// Sets the given bits of the command register, like bus mastering or I/O space decoding.
+    ///
+    /// # Safety
+    /// See [`Address::write_u32`].
+    pub unsafe fn enable(&self, bits: u16) {
+        let command = self.address.read_u16(COMMAND);
+        // SAFETY:
+        // The caller is responsible for what the device does once enabled.
+        unsafe { self.address.write_u16(COMMAND, command | bits) };
+    }
+}
+
+static DEVICES: Registry<Device, MAX_DEVICES> = Registry::new();
+
+/// Returns the functions found by [`init`], in bus order.
+pub fn devices() -> impl Iterator<Item = Device> {
+    DEVICES.iter().copied()
+}
+
+/// Records `device`, returning `false` if there is no room left.
+fn add(device: Device) -> bool {
+    DEVICES.register(device, |_| false).is_ok()
+}
+
+/// Scans `bus`, and the buses behind its bridges.
+fn scan_bus(bus: u8, scanned: &mut [bool; BUS_COUNT]) {
+    if core::mem::replace(&mut scanned[bus as usize], true) {
+        return;
+    }
+
+    for device in 0..DEVICES_PER_BUS {
+        let address = Address::new(bus, device, 0);
+        if address.vendor_id() == NO_DEVICE {
+            continue;
+        }
+
+        let functions = if address.header_type() & MULTI_FUNCTION != 0 {
+            FUNCTIONS_PER_DEVICE
+        } else {
+            1
+        };
+        for function in 0..functions {
+            scan_function(Address::new(bus, device, function), scanned);
+        }
+    }
+}
+
+fn scan_function(address: Address, scanned: &mut [bool; BUS_COUNT]) {
+    if address.vendor_id() == NO_DEVICE {
+        return;
+    }
+
+    let device = Device::read(address);
+    if !add(device) {
+        return;
+    }
+    if device.header_type == HEADER_BRIDGE {
+        scan_bus(address.read_u8(SECONDARY_BUS), scanned);
+    }
+}
+
+/// Enumerates the functions on the bus, and probes the drivers registered so far.
+pub fn init() {
+    let mut scanned = [false; BUS_COUNT];
+
+    // With several host bridges, function `n` of the first one handles bus `n`.
+    let host = Address::new(0, 0, 0);
+    if host.header_type() & MULTI_FUNCTION == 0 {
+        scan_bus(0, &mut scanned);
+    } else {
+        for function in 0..FUNCTIONS_PER_DEVICE {
+            if Address::new(0, 0, function).vendor_id() != NO_DEVICE {
+                scan_bus(function, &mut scanned);
+            }
+        }
+    }
+
+    driver::probe_all();
+
+    let _ = command::register(Command {
+        name: "lspci",
+        usage: "[-v]",
+        help: "list the PCI devices",
+        func: lspci_cmd,
+        complete: Some(lspci_complete),
+    });
+}
+
+fn lspci_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+    let verbose = match (args.next(), args.next()) {
+        (None, None) => false,
+        (Some(b"-v"), None) => true,
+        _ => {
+            printkln!("usage: lspci [-v]");
+            return USAGE;
+        }
+    };
+
+    for device in devices() {
+        printkln!(
+            "{} {} [{:02x}{:02x}]: {:04x}:{:04x} (rev {:02x})",
+            device.address,
+            device.class_name(),
+            device.class,
+            device.subclass,
+            device.vendor_id,
+            device.device_id,
+            device.revision
+        );
+        if !verbose {
+            continue;
+        }
+
+        if let 1..=4 = device.interrupt_pin {
+            printkln!(
+                "\tInterrupt: pin {} routed to IRQ {}",
+                (b'A' + device.interrupt_pin - 1) as char,
+                device.interrupt_line
+            );
+        }
+        for (index, bar) in device.bars.iter().enumerate() {
+            if let Some(bar) = bar {
+                printkln!("\tRegion {}: {}", index, bar);
+            }
+        }
+        if let Some(driver) = device.driver {
+            printkln!("\tKernel driver in use: {}", driver);
+        }
+    }
+    SUCCESS
+}
+
+fn lspci_complete(_before: &[u8], completions: &mut Completions) {
+    completions.add(b"-v");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    #[test_case]
+    fn config_addresses_are_encoded() -> Result<(), &'static str> {
+        kassert_eq!(Address::new(0, 0, 0).config_address(VENDOR_ID), 0x8000_0000);
+        kassert_eq!(Address::new(1, 2, 3).config_address(INTERRUPT_PIN), 0x8001_133c);
+        kassert_eq!(Address::new(0xff, 31, 7).config_address(0xff), 0x80ff_fffc);
+
+        Ok(())
+    }
+}
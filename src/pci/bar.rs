@@ -0,0 +1,170 @@
+//! Base address registers.
+//!
+//! A BAR holds the address of a memory or I/O region decoded by the device.
+//! Its size is found by writing all ones to it: the device keeps the address
+//! bits below the size at zero.
+
+use core::fmt;
+
+use crate::pci::{Address, COMMAND, COMMAND_IO_SPACE, COMMAND_MEMORY_SPACE};
+
+const IO_SPACE: u32 = 1 << 0;
+const TYPE_MASK: u32 = 0b11 << 1;
+const TYPE_64_BIT: u32 = 0b10 << 1;
+const PREFETCHABLE: u32 = 1 << 3;
+const IO_ADDRESS_MASK: u32 = !0b11;
+const MEMORY_ADDRESS_MASK: u32 = !0b1111;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bar {
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+        is_64_bit: bool,
+    },
+    Io {
+        port: u16,
+        size: u32,
+    },
+}
+
+impl Bar {
+    /// Decodes a BAR from its value and the value read back after writing all ones to it.
+    /// For 64-bit BARs, both values hold the two registers, the upper one in the high half.
+    #[must_use]
+    pub fn decode(value: u64, mask: u64) -> Option<Self> {
+        let low = value as u32;
+        if low & IO_SPACE != 0 {
+            let mask = mask as u32 & IO_ADDRESS_MASK & 0xffff;
+            if mask == 0 {
+                return None;
+            }
+            return Some(Self::Io {
+                port: (low & IO_ADDRESS_MASK) as u16,
+                size: (!mask & 0xffff) + 1,
+            });
+        }
+
+        let is_64_bit = low & TYPE_MASK == TYPE_64_BIT;
+        let address_mask = if is_64_bit { !0xf } else { u64::from(MEMORY_ADDRESS_MASK) };
+        let mask = mask & address_mask;
+        if mask == 0 {
+            return None;
+        }
+        let size = if is_64_bit { !mask } else { u64::from(!(mask as u32)) } + 1;
+
+        Some(Self::Memory {
+            address: value & address_mask,
+            size,
+            prefetchable: low & PREFETCHABLE != 0,
+            is_64_bit,
+        })
+    }
+}
+
+impl fmt::Display for Bar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory {
+                address,
+                size,
+                prefetchable,
+                is_64_bit,
+            } => {
+                write!(f, "Memory at {:#010x} ({}-bit, ", address, if *is_64_bit { 64 } else { 32 })?;
+                if !prefetchable {
+                    write!(f, "non-")?;
+                }
+                write!(f, "prefetchable) [size={}]", Size(*size))
+            }
+            Self::Io { port, size } => write!(f, "I/O ports at {:#06x} [size={}]", port, Size(u64::from(*size))),
+        }
+    }
+}
+
+/// Size of a region, in the largest unit it is a multiple of.
+struct Size(u64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (size, unit) = [(30, "G"), (20, "M"), (10, "K")]
+            .into_iter()
+            .find(|(shift, _)| self.0 >= 1 << shift && self.0.is_multiple_of(1 << shift))
+            .map_or((self.0, ""), |(shift, unit)| (self.0 >> shift, unit));
+        write!(f, "{size}{unit}")
+    }
+}
+
+/// Reads the size of the register at `offset` by writing all ones to it, and restores it.
+fn size_mask(address: Address, offset: u8) -> u32 {
+    let value = address.read_u32(offset);
+    // SAFETY:
+    // Decoding is disabled by the caller while the BAR holds all ones, and the original
+    // value is restored right after.
+    unsafe {
+        address.write_u32(offset, u32::MAX);
+        let mask = address.read_u32(offset);
+        address.write_u32(offset, value);
+        mask
+    }
+}
+
+/// Reads the BAR at `offset`, returning it along with the number of registers it takes.
+pub(super) fn probe(address: Address, offset: u8) -> (Option<Bar>, usize) {
+    let low = address.read_u32(offset);
+    let is_64_bit = low & IO_SPACE == 0 && low & TYPE_MASK == TYPE_64_BIT;
+
+    // The device must not decode the BAR while it is being sized.
+    let command = address.read_u16(COMMAND);
+    // SAFETY:
+    // Only the decoding bits are cleared, and the command register is restored below.
+    unsafe { address.write_u16(COMMAND, command & !(COMMAND_IO_SPACE | COMMAND_MEMORY_SPACE)) };
+
+    let (value, mask) = if is_64_bit {
+        let high = address.read_u32(offset + 4);
+        let value = u64::from(high) << 32 | u64::from(low);
+        let mask = u64::from(size_mask(address, offset + 4)) << 32 | u64::from(size_mask(address, offset));
+        (value, mask)
+    } else {
+        (u64::from(low), u64::from(size_mask(address, offset)))
+    };
+
+    // SAFETY:
+    // This restores the command register as it was.
+    unsafe { address.write_u16(COMMAND, command) };
+
+    (Bar::decode(value, mask), if is_64_bit { 2 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    #[test_case]
+    fn bars_are_decoded() -> Result<(), &'static str> {
+        kassert_eq!(Bar::decode(0xc001, 0xfff1), Some(Bar::Io { port: 0xc000, size: 16 }));
+        kassert_eq!(
+            Bar::decode(0xfeb0_0008, 0xfffc_0008),
+            Some(Bar::Memory {
+                address: 0xfeb0_0000,
+                size: 256 * 1024,
+                prefetchable: true,
+                is_64_bit: false,
+            })
+        );
+        kassert_eq!(
+            Bar::decode(0x0000_0001_0000_000c, 0xffff_ffff_ffff_c00c),
+            Some(Bar::Memory {
+                address: 0x1_0000_0000,
+                size: 16 * 1024,
+                prefetchable: true,
+                is_64_bit: true,
+            })
+        );
+        kassert_eq!(Bar::decode(0, 0), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! Class codes.
+//!
+//! The class and subclass of a function tell what kind of device it is,
+//! independently of its vendor.
+
+pub const MASS_STORAGE: u8 = 0x01;
+pub const BRIDGE: u8 = 0x06;
+
+pub const IDE_CONTROLLER: u8 = 0x01;
+pub const PCI_TO_PCI_BRIDGE: u8 = 0x04;
+
+/// Returns the description of a class and subclass, falling back to the class alone.
+#[must_use]
+pub const fn name(class: u8, subclass: u8) -> &'static str {
+    match (class, subclass) {
+        (0x00, 0x01) => "VGA compatible unclassified device",
+        (0x00, _) => "Unclassified device",
+        (0x01, 0x00) => "SCSI storage controller",
+        (0x01, 0x01) => "IDE interface",
+        (0x01, 0x02) => "Floppy disk controller",
+        (0x01, 0x05) => "ATA controller",
+        (0x01, 0x06) => "SATA controller",
+        (0x01, 0x08) => "Non-Volatile memory controller",
+        (0x01, _) => "Mass storage controller",
+        (0x02, 0x00) => "Ethernet controller",
+        (0x02, _) => "Network controller",
+        (0x03, 0x00) => "VGA compatible controller",
+        (0x03, _) => "Display controller",
+        (0x04, 0x01) => "Multimedia audio controller",
+        (0x04, 0x03) => "Audio device",
+        (0x04, _) => "Multimedia controller",
+        (0x05, _) => "Memory controller",
+        (0x06, 0x00) => "Host bridge",
+        (0x06, 0x01) => "ISA bridge",
+        (0x06, 0x04) => "PCI bridge",
+        (0x06, 0x80) => "Bridge",
+        (0x06, _) => "Bridge device",
+        (0x07, 0x00) => "Serial controller",
+        (0x07, _) => "Communication controller",
+        (0x08, _) => "System peripheral",
+        (0x09, _) => "Input device controller",
+        (0x0c, 0x03) => "USB controller",
+        (0x0c, 0x05) => "SMBus",
+        (0x0c, _) => "Serial bus controller",
+        (0x0d, _) => "Wireless controller",
+        _ => "Unknown device",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    #[test_case]
+    fn subclasses_fall_back_to_the_class() -> Result<(), &'static str> {
+        kassert_eq!(name(MASS_STORAGE, IDE_CONTROLLER), "IDE interface");
+        kassert_eq!(name(BRIDGE, PCI_TO_PCI_BRIDGE), "PCI bridge");
+        kassert_eq!(name(0x02, 0x42), "Network controller");
+        kassert_eq!(name(0xfe, 0x00), "Unknown device");
+
+        Ok(())
+    }
+}
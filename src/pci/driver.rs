@@ -0,0 +1,114 @@
+//! PCI drivers.
+//!
+//! A driver registers the devices it handles, either by vendor and device ID
+//! or by class, and is probed with every matching device found on the bus. The
+//! first driver whose probe succeeds is bound to the device.
+//!
+//! ```ignore
+//! fn probe(device: &Device) -> Result<(), ProbeError> { ... }
+//!
+//! driver::register(Driver {
+//!     name: "e1000",
+//!     ids: &[DeviceId::new(0x8086, 0x100e)],
+//!     probe,
+//! })?;
+//! ```
+
+use crate::{
+    pci::{DEVICES, Device, MAX_DEVICES},
+    registry::{RegisterError, Registry},
+};
+
+/// Maximum number of drivers registered at the same time.
+pub const MAX_DRIVERS: usize = 16;
+
+/// Devices handled by a driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceId {
+    /// Matches a single device of a vendor.
+    Id { vendor: u16, device: u16 },
+    /// Matches every device of a class and subclass, whatever its vendor.
+    Class { class: u8, subclass: u8 },
+}
+
+impl DeviceId {
+    #[must_use]
+    pub const fn new(vendor: u16, device: u16) -> Self {
+        Self::Id { vendor, device }
+    }
+
+    #[must_use]
+    pub const fn class(class: u8, subclass: u8) -> Self {
+        Self::Class { class, subclass }
+    }
+
+    #[must_use]
+    pub const fn matches(&self, device: &Device) -> bool {
+        match *self {
+            Self::Id { vendor, device: id } => device.vendor_id == vendor && device.device_id == id,
+            Self::Class { class, subclass } => device.class == class && device.subclass == subclass,
+        }
+    }
+}
+
+/// Why a driver did not take a device it matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProbeError {
+    /// The device is not in a mode the driver supports.
+    Unsupported,
+    /// The device did not respond as expected.
+    NoResponse,
+}
+
+#[derive(Clone, Copy)]
+pub struct Driver {
+    pub name: &'static str,
+    pub ids: &'static [DeviceId],
+    /// Initializes a matching device.
+    pub probe: fn(device: &Device) -> Result<(), ProbeError>,
+}
+
+impl Driver {
+    fn matches(&self, device: &Device) -> bool {
+        self.ids.iter().any(|id| id.matches(device))
+    }
+}
+
+static DRIVERS: Registry<Driver, MAX_DRIVERS> = Registry::new();
+
+/// Binds `driver` to the unbound devices it matches.
+fn probe(driver: &Driver) {
+    for index in 0..MAX_DEVICES {
+        // The driver may look the devices up while probing, so it is given a copy.
+        let Some(device) = DEVICES.get(index).copied() else {
+            continue;
+        };
+
+        if device.driver.is_none() && driver.matches(&device) && (driver.probe)(&device).is_ok() {
+            // SAFETY:
+            // Devices are only ever handed out as copies, and the probe returned.
+            if let Some(device) = unsafe { DEVICES.get_mut(index) } {
+                device.driver = Some(driver.name);
+            }
+        }
+    }
+}
+
+/// Makes `driver` available, and probes it with the matching devices already found.
+///
+/// # Errors
+/// This function returns an error if a driver with the same name exists, or if
+/// [`MAX_DRIVERS`] drivers are already registered.
+pub fn register(driver: Driver) -> Result<(), RegisterError> {
+    let driver = *DRIVERS.register(driver, |d| d.name == driver.name)?;
+    probe(&driver);
+
+    Ok(())
+}
+
+/// Probes every registered driver with the devices found.
+pub(super) fn probe_all() {
+    for driver in DRIVERS.iter() {
+        probe(driver);
+    }
+}
@@ -1,43 +1,199 @@
-use core::arch::asm;
+//! x86 I/O ports.
+//!
+//! A [`Port`] is typed by the width of its accesses, `u8`, `u16` or `u32`, and
+//! by the direction the device allows: writing to a [`PortReadOnly`] or reading
+//! from a [`PortWriteOnly`] does not compile.
+//!
+//! ```ignore
+//! let status = Port::<u8>::read_only(0x64);
+//! let mut data = Port::<u16>::new(0x1f0);
+//! let mut sector = [0u16; 256];
+//! unsafe { data.read_string(&mut sector) };
+//! ```
 
-pub struct Port {
+use core::{arch::asm, marker::PhantomData};
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+    impl Sealed for super::ReadOnly {}
+    impl Sealed for super::WriteOnly {}
+    impl Sealed for super::ReadWrite {}
+}
+
+/// Value transferred by a port access, one of `u8`, `u16` and `u32`.
+pub trait PortValue: sealed::Sealed + Copy {
+    /// # Safety
+    /// See [`Port::read`].
+    unsafe fn read_from(port: u16) -> Self;
+
+    /// # Safety
+    /// See [`Port::write`].
+    unsafe fn write_to(port: u16, value: Self);
+
+    /// # Safety
+    /// See [`Port::read_string`].
+    unsafe fn read_string_from(port: u16, buf: &mut [Self]);
+
+    /// # Safety
+    /// See [`Port::write_string`].
+    unsafe fn write_string_to(port: u16, buf: &[Self]);
+}
+
+macro_rules! port_value {
+    ($type: ty, $reg: tt, $ins: literal, $outs: literal) => {
+        impl PortValue for $type {
+            unsafe fn read_from(port: u16) -> Self {
+                let res: Self;
+
+                unsafe {
+                    asm!(
+                        concat!("in ", $reg, ", dx"),
+                        in("dx") port,
+                        out($reg) res,
+                        options(nomem, nostack, preserves_flags),
+                    );
+                }
+
+                res
+            }
+
+            unsafe fn write_to(port: u16, value: Self) {
+                unsafe {
+                    asm!(
+                        concat!("out dx, ", $reg),
+                        in("dx") port,
+                        in($reg) value,
+                        options(nomem, nostack, preserves_flags),
+                    );
+                }
+            }
+
+            unsafe fn read_string_from(port: u16, buf: &mut [Self]) {
+                unsafe {
+                    asm!(
+                        $ins,
+                        in("dx") port,
+                        inout("edi") buf.as_mut_ptr() => _,
+                        inout("ecx") buf.len() => _,
+                        options(nostack, preserves_flags),
+                    );
+                }
+            }
+
+            unsafe fn write_string_to(port: u16, buf: &[Self]) {
+                // `esi` is reserved by LLVM, so it is swapped with the buffer's address for
+                // the duration of the copy.
+                unsafe {
+                    asm!(
+                        "xchg esi, {buf}",
+                        $outs,
+                        "xchg esi, {buf}",
+                        buf = inout(reg) buf.as_ptr() => _,
+                        in("dx") port,
+                        inout("ecx") buf.len() => _,
+                        options(readonly, nostack, preserves_flags),
+                    );
+                }
+            }
+        }
+    };
+}
+
+port_value!(u8, "al", "rep insb", "rep outsb");
+port_value!(u16, "ax", "rep insw", "rep outsw");
+port_value!(u32, "eax", "rep insd", "rep outsd");
+
+/// Direction of the accesses a port allows.
+pub trait PortAccess: sealed::Sealed {}
+/// Accesses that read from the port.
+pub trait Readable: PortAccess {}
+/// Accesses that write to the port.
+pub trait Writable: PortAccess {}
+
+#[derive(Debug)]
+pub struct ReadOnly;
+#[derive(Debug)]
+pub struct WriteOnly;
+#[derive(Debug)]
+pub struct ReadWrite;
+
+impl PortAccess for ReadOnly {}
+impl PortAccess for WriteOnly {}
+impl PortAccess for ReadWrite {}
+impl Readable for ReadOnly {}
+impl Readable for ReadWrite {}
+impl Writable for WriteOnly {}
+impl Writable for ReadWrite {}
+
+#[derive(Debug)]
+pub struct Port<T: PortValue = u8, A: PortAccess = ReadWrite> {
     port: u16,
+    _marker: PhantomData<(T, A)>,
 }
 
-impl Port {
+pub type PortReadOnly<T = u8> = Port<T, ReadOnly>;
+pub type PortWriteOnly<T = u8> = Port<T, WriteOnly>;
+
+impl<T: PortValue> Port<T> {
+    #[must_use]
+    pub const fn new(port: u16) -> Self {
+        Self::with_access(port)
+    }
+
     #[must_use]
-    pub fn new(port: u16) -> Self {
-        Self { port }
+    pub const fn read_only(port: u16) -> PortReadOnly<T> {
+        Port::with_access(port)
     }
 
+    #[must_use]
+    pub const fn write_only(port: u16) -> PortWriteOnly<T> {
+        Port::with_access(port)
+    }
+}
+
+impl<T: PortValue, A: PortAccess> Port<T, A> {
+    const fn with_access(port: u16) -> Self {
+        Self { port, _marker: PhantomData }
+    }
+}
+
+impl<T: PortValue, A: Readable> Port<T, A> {
     /// # Safety
     /// This function interacts with hardware directly and can
     /// therefore not be checked by the compiler.
-    pub unsafe fn write(&mut self, val: u8) {
-        unsafe {
-            asm!(
-                "out dx, al",
-                in("dx") self.port,
-                in("al") val,
-            );
-        }
+    #[must_use]
+    pub unsafe fn read(&self) -> T {
+        unsafe { T::read_from(self.port) }
     }
 
+    /// Fills `buf` with as many reads from the port.
+    ///
     /// # Safety
     /// This function interacts with hardware directly and can
     /// therefore not be checked by the compiler.
-    #[must_use]
-    pub unsafe fn read(&self) -> u8 {
-        let res: u8;
-
-        unsafe {
-            asm!(
-                "in al, dx",
-                in("dx") self.port,
-                out("al") res,
-            );
-        }
+    pub unsafe fn read_string(&self, buf: &mut [T]) {
+        unsafe { T::read_string_from(self.port, buf) }
+    }
+}
+
+impl<T: PortValue, A: Writable> Port<T, A> {
+    /// # Safety
+    /// This function interacts with hardware directly and can
+    /// therefore not be checked by the compiler.
+    pub unsafe fn write(&mut self, val: T) {
+        unsafe { T::write_to(self.port, val) }
+    }
 
-        res
+    /// Writes every value of `buf` to the port, in order.
+    ///
+    /// # Safety
+    /// This function interacts with hardware directly and can
+    /// therefore not be checked by the compiler.
+    pub unsafe fn write_string(&mut self, buf: &[T]) {
+        unsafe { T::write_string_to(self.port, buf) }
     }
 }
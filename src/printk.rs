@@ -1,22 +1,29 @@
-use core::fmt;
+use core::{fmt, ptr::NonNull};
 
-use crate::terminal::{SCREEN, Screen, entry::Entry, vga::Buffer};
+use crate::{
+    terminal::{console, vga::Buffer},
+    vmm::allocators::collections::KVec,
+};
 
 const BUFFER_SIZE: usize = 1024;
 
+/// Maximum number of bytes kept by [`capture`], the following ones are dropped.
+pub const CAPTURE_LIMIT: usize = 64 * 1024;
+
+/// Writer behind `printk!`, whose output goes to the active console.
 pub struct PrintkWriter {
-    screen: &'static mut Screen,
     buffer: [u8; BUFFER_SIZE],
     position: usize,
+    /// Stream receiving the output instead of the console, see [`capture`].
+    capture: Option<NonNull<KVec<u8>>>,
 }
 
 impl PrintkWriter {
     const fn new() -> Self {
         Self {
-            #[allow(static_mut_refs)]
-            screen: unsafe { &mut SCREEN },
             buffer: [0; BUFFER_SIZE],
             position: 0,
+            capture: None,
         }
     }
 
@@ -33,11 +40,24 @@ impl PrintkWriter {
     }
 
     pub fn flush(&mut self) {
-        for byte in &self.buffer[..self.position] {
-            self.screen.push(Entry::new(*byte));
+        if let Some(mut output) = self.capture {
+            // SAFETY:
+            // `capture` only sets `self.capture` while it holds the mutable borrow of the stream.
+            let output = unsafe { output.as_mut() };
+            let len = self.position.min(CAPTURE_LIMIT.saturating_sub(output.len()));
+            let _ = output.try_extend_from_slice(&self.buffer[..len]);
+            self.position = 0;
+            return;
         }
-        let b = Buffer::from_screen(self.screen, 0);
-        b.flush();
+
+        // The output is dropped if the console is busy, which only happens when writing to it
+        // goes through `printk!` itself.
+        console::with_screen(console::active(), |screen| {
+            for byte in &self.buffer[..self.position] {
+                screen.write_byte(*byte);
+            }
+            Buffer::from_screen(screen, 0).flush();
+        });
         self.position = 0;
     }
 }
@@ -53,6 +73,50 @@ impl fmt::Write for PrintkWriter {
     }
 }
 
+#[allow(static_mut_refs)]
+fn writer() -> &'static mut PrintkWriter {
+    // SAFETY:
+    // The kernel is single threaded, and `printk!` does not call itself.
+    unsafe { &mut PRINTK_WRITER }
+}
+
+/// Writes raw bytes to the output of `printk!`, which need not be UTF-8.
+pub fn write_bytes(bytes: &[u8]) {
+    let writer = writer();
+    for byte in bytes {
+        writer.write_byte(*byte);
+    }
+}
+
+/// Writes out what `printk!` buffered so far.
+pub fn flush() {
+    writer().flush();
+}
+
+/// Runs `f` with the output of `printk!` appended to `output` instead of being
+/// written to the console, which is how the shell pipes commands together.
+///
+/// Output past [`CAPTURE_LIMIT`] bytes, or that does not fit in memory, is dropped.
+pub fn capture<R>(output: &mut KVec<u8>, f: impl FnOnce() -> R) -> R {
+    writer().flush();
+    let previous = writer().capture.replace(NonNull::from(output));
+
+    let result = f();
+
+    writer().flush();
+    writer().capture = previous;
+    result
+}
+
+/// Sends the output of `printk!` back to the console, even if it is being captured.
+///
+/// This is meant for the panic handler, whose message must not end up in a pipe.
+pub fn stop_capture() {
+    let writer = writer();
+    writer.position = 0;
+    writer.capture = None;
+}
+
 #[doc(hidden)]
 #[allow(static_mut_refs)]
 pub fn print_internal(args: ::core::fmt::Arguments) {
@@ -1,13 +1,46 @@
+mod controller;
 mod interrupt;
+mod leds;
+mod mouse;
 mod scancodes;
 
 pub const DATA_PORT: u16 = 0x60;
 pub const STATUS_PORT: u16 = 0x64;
 pub const COMMAND_PORT: u16 = 0x64;
 pub const OUTPUT_BUFFER_STATUS_BIT: u8 = 1;
+pub const INPUT_BUFFER_STATUS_BIT: u8 = 2;
 
-pub use interrupt::init;
-pub use interrupt::read_key_event;
+pub use controller::{Device, Ps2Error, devices};
+pub use leds::set_leds;
+pub use mouse::{Button, MouseEvent};
 
 pub use scancodes::Event;
 pub use scancodes::Key;
+pub use scancodes::KeyEvent;
+pub use scancodes::ScancodeSet;
+
+/// Initializes the controller and its devices, then installs the keyboard and mouse handlers,
+/// decoding the keyboard with `set`. Returns the devices found on the first and second ports.
+///
+/// # Errors
+/// This function returns an error if the controller does not work. The keyboard is then
+/// decoded with set 1, in case it still works the way the firmware left it.
+pub fn init(set: ScancodeSet) -> Result<[Option<Device>; 2], Ps2Error> {
+    use crate::arch::x86::interrupts::irq;
+
+    // The controller is polled for its responses, which the handlers would steal if IRQ1 or
+    // IRQ12 fired in the meantime. They are unmasked again once the handlers are installed.
+    irq::set_mask(interrupt::IRQ);
+    irq::set_mask(mouse::IRQ);
+
+    let devices = controller::init(set);
+
+    interrupt::init(if devices.is_ok() { set } else { ScancodeSet::One });
+    mouse::init();
+    if let Ok([_, Some(device)]) = devices
+        && device.is_mouse()
+    {
+        mouse::enable(device);
+    }
+    devices
+}
@@ -0,0 +1,392 @@
+//! 8042 PS/2 controller.
+//!
+//! [`init`] brings the controller up without relying on the state the BIOS
+//! left it in, following https://wiki.osdev.org/I8042_PS/2_Controller#Initialising_the_PS/2_Controller:
+//! the ports are disabled, the output buffer flushed, the configuration byte
+//! set, the controller and ports tested, and the devices reset and identified.
+//!
+//! Responses are polled, so everything but [`write_data`] must be used before
+//! the IRQs of the ports are enabled.
+
+use core::fmt;
+
+use crate::{
+    port::Port,
+    ps2::{COMMAND_PORT, DATA_PORT, INPUT_BUFFER_STATUS_BIT, OUTPUT_BUFFER_STATUS_BIT, STATUS_PORT, scancodes::ScancodeSet},
+};
+
+const READ_CONFIG: u8 = 0x20;
+const WRITE_CONFIG: u8 = 0x60;
+const DISABLE_SECOND_PORT: u8 = 0xa7;
+const ENABLE_SECOND_PORT: u8 = 0xa8;
+const TEST_SECOND_PORT: u8 = 0xa9;
+const SELF_TEST: u8 = 0xaa;
+const TEST_FIRST_PORT: u8 = 0xab;
+const DISABLE_FIRST_PORT: u8 = 0xad;
+const ENABLE_FIRST_PORT: u8 = 0xae;
+/// Sends the next data byte to the second port instead of the first one.
+const WRITE_SECOND_PORT: u8 = 0xd4;
+
+const SELF_TEST_PASSED: u8 = 0x55;
+const PORT_TEST_PASSED: u8 = 0x00;
+
+const FIRST_PORT_IRQ: u8 = 1 << 0;
+const SECOND_PORT_IRQ: u8 = 1 << 1;
+const FIRST_PORT_CLOCK_DISABLED: u8 = 1 << 4;
+const SECOND_PORT_CLOCK_DISABLED: u8 = 1 << 5;
+const TRANSLATION: u8 = 1 << 6;
+
+const DEVICE_SET_SCANCODE_SET: u8 = 0xf0;
+const DEVICE_IDENTIFY: u8 = 0xf2;
+const DEVICE_SET_SAMPLE_RATE: u8 = 0xf3;
+const DEVICE_ENABLE_SCANNING: u8 = 0xf4;
+const DEVICE_DISABLE_SCANNING: u8 = 0xf5;
+const DEVICE_RESET: u8 = 0xff;
+
+const ACK: u8 = 0xfa;
+const RESEND: u8 = 0xfe;
+const DEVICE_SELF_TEST_PASSED: u8 = 0xaa;
+
+/// Number of status polls before a byte is considered lost.
+const TIMEOUT: usize = 100_000;
+
+/// Number of times a byte is sent again when the device asks for it.
+const RETRIES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ps2Port {
+    First,
+    Second,
+}
+
+/// Device plugged in a port, as reported by its identify command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Device {
+    /// Does not answer the identify command.
+    AtKeyboard,
+    Mf2Keyboard,
+    Mouse,
+    /// IntelliMouse with a scroll wheel.
+    WheelMouse,
+    FiveButtonMouse,
+    Unknown(u8, u8),
+}
+
+impl Device {
+    fn from_id(id: &[u8]) -> Self {
+        match id {
+            [] => Self::AtKeyboard,
+            [0xab, 0x83 | 0x41 | 0xc1] => Self::Mf2Keyboard,
+            [0x00] => Self::Mouse,
+            [0x03] => Self::WheelMouse,
+            [0x04] => Self::FiveButtonMouse,
+            [a] => Self::Unknown(*a, 0),
+            [a, b, ..] => Self::Unknown(*a, *b),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_keyboard(&self) -> bool {
+        matches!(self, Self::AtKeyboard | Self::Mf2Keyboard)
+    }
+
+    #[must_use]
+    pub const fn is_mouse(&self) -> bool {
+        matches!(self, Self::Mouse | Self::WheelMouse | Self::FiveButtonMouse)
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AtKeyboard => write!(f, "AT keyboard"),
+            Self::Mf2Keyboard => write!(f, "MF2 keyboard"),
+            Self::Mouse => write!(f, "mouse"),
+            Self::WheelMouse => write!(f, "wheel mouse"),
+            Self::FiveButtonMouse => write!(f, "5-button mouse"),
+            Self::Unknown(a, b) => write!(f, "unknown device {a:#04x} {b:#04x}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ps2Error {
+    /// The controller did not accept or answer a byte in time.
+    Timeout,
+    /// The controller self-test returned this code instead of `0x55`.
+    SelfTestFailed(u8),
+    /// The device did not acknowledge a command.
+    NoAck(Ps2Port),
+    /// The device reset did not report a successful self-test.
+    ResetFailed(Ps2Port),
+}
+
+impl fmt::Display for Ps2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "controller timed out"),
+            Self::SelfTestFailed(code) => write!(f, "controller self-test failed ({code:#04x})"),
+            Self::NoAck(port) => write!(f, "{port:?} port device did not acknowledge a command"),
+            Self::ResetFailed(port) => write!(f, "{port:?} port device failed its self-test"),
+        }
+    }
+}
+
+/// Devices detected by [`init`], by port.
+static mut DEVICES: [Option<Device>; 2] = [None; 2];
+
+/// Returns the devices detected on the first and second ports.
+#[must_use]
+pub fn devices() -> [Option<Device>; 2] {
+    // SAFETY:
+    // `DEVICES` is only written by `init`, and copied out.
+    unsafe { DEVICES }
+}
+
+fn status() -> u8 {
+    // SAFETY:
+    // Reading the status register has no side effect.
+    unsafe { Port::read_only(STATUS_PORT).read() }
+}
+
+fn wait_input_empty() -> Result<(), Ps2Error> {
+    (0..TIMEOUT)
+        .find(|_| status() & INPUT_BUFFER_STATUS_BIT == 0)
+        .map(|_| ())
+        .ok_or(Ps2Error::Timeout)
+}
+
+fn wait_output_full() -> Result<(), Ps2Error> {
+    (0..TIMEOUT)
+        .find(|_| status() & OUTPUT_BUFFER_STATUS_BIT != 0)
+        .map(|_| ())
+        .ok_or(Ps2Error::Timeout)
+}
+
+fn command(command: u8) -> Result<(), Ps2Error> {
+    wait_input_empty()?;
+    // SAFETY:
+    // The controller is ready to accept a command.
+    unsafe { Port::write_only(COMMAND_PORT).write(command) };
+    Ok(())
+}
+
+/// Writes `byte` to the data port, which sends it to the first port's device unless a
+/// command expecting a data byte was just sent.
+///
+/// # Errors
+/// This function returns an error if the controller does not accept the byte in time.
+pub fn write_data(byte: u8) -> Result<(), Ps2Error> {
+    wait_input_empty()?;
+    // SAFETY:
+    // The controller is ready to accept a data byte.
+    unsafe { Port::new(DATA_PORT).write(byte) };
+    Ok(())
+}
+
+fn read_data() -> Result<u8, Ps2Error> {
+    wait_output_full()?;
+    // SAFETY:
+    // The output buffer is full, reading it pops the byte.
+    Ok(unsafe { Port::new(DATA_PORT).read() })
+}
+
+/// Discards the bytes left in the output buffer.
+fn flush() {
+    while status() & OUTPUT_BUFFER_STATUS_BIT != 0 {
+        // SAFETY:
+        // The output buffer is full, reading it pops the byte.
+        let _: u8 = unsafe { Port::new(DATA_PORT).read() };
+    }
+}
+
+fn read_config() -> Result<u8, Ps2Error> {
+    command(READ_CONFIG)?;
+    read_data()
+}
+
+fn write_config(config: u8) -> Result<(), Ps2Error> {
+    command(WRITE_CONFIG)?;
+    write_data(config)
+}
+
+/// Sends `byte` to the device on `port`, and waits for its acknowledgement.
+///
+/// # Errors
+/// This function returns an error if the device does not acknowledge the byte.
+pub fn send(port: Ps2Port, byte: u8) -> Result<(), Ps2Error> {
+    for _ in 0..RETRIES {
+        if port == Ps2Port::Second {
+            command(WRITE_SECOND_PORT)?;
+        }
+        write_data(byte)?;
+
+        match read_data() {
+            Ok(ACK) => return Ok(()),
+            Ok(RESEND) => {}
+            _ => return Err(Ps2Error::NoAck(port)),
+        }
+    }
+
+    Err(Ps2Error::NoAck(port))
+}
+
+fn reset(port: Ps2Port) -> Result<(), Ps2Error> {
+    send(port, DEVICE_RESET)?;
+    if read_data()? != DEVICE_SELF_TEST_PASSED {
+        return Err(Ps2Error::ResetFailed(port));
+    }
+    // Mice follow the self-test result with their ID, which may take a while to arrive and
+    // would otherwise be mistaken for the answer to the next command.
+    let _ = read_data();
+    Ok(())
+}
+
+fn identify(port: Ps2Port) -> Result<Device, Ps2Error> {
+    send(port, DEVICE_DISABLE_SCANNING)?;
+    send(port, DEVICE_IDENTIFY)?;
+
+    let mut id = [0; 2];
+    let mut len = 0;
+    while len < id.len()
+        && let Ok(byte) = read_data()
+    {
+        id[len] = byte;
+        len += 1;
+    }
+
+    send(port, DEVICE_ENABLE_SCANNING)?;
+    Ok(Device::from_id(&id[..len]))
+}
+
+/// Sets the sample rates of `knock` in a row, which is how mice are asked to unlock their
+/// extensions, and identifies the mouse again to see if it did.
+fn unlock(port: Ps2Port, knock: [u8; 3]) -> Result<Device, Ps2Error> {
+    for rate in knock {
+        send(port, DEVICE_SET_SAMPLE_RATE)?;
+        send(port, rate)?;
+    }
+    identify(port)
+}
+
+/// Resets and identifies the device on `port`, returning `None` if there is none or if
+/// it does not work.
+///
+/// Mice are switched to the wheel and 5-button modes when they support them.
+fn detect(port: Ps2Port) -> Option<Device> {
+    let device = reset(port).and_then(|()| identify(port)).ok()?;
+    if device != Device::Mouse {
+        return Some(device);
+    }
+
+    let Ok(Device::WheelMouse) = unlock(port, [200, 100, 80]) else {
+        return Some(device);
+    };
+    Some(unlock(port, [200, 200, 80]).unwrap_or(Device::WheelMouse))
+}
+
+/// Initializes the controller and the devices plugged in it, and returns them by port.
+///
+/// The keyboard is switched to scancode set 2, and the controller translates it to
+/// set 1 if `set` asks for it. The IRQs of the ports that work are enabled, their
+/// handlers must be installed before they are unmasked.
+///
+/// # Errors
+/// This function returns an error if the controller does not answer, or if its
+/// self-test fails. The first port is then enabled again with translation, so that
+/// the keyboard can still be decoded with set 1.
+pub fn init(set: ScancodeSet) -> Result<[Option<Device>; 2], Ps2Error> {
+    let res = command(DISABLE_FIRST_PORT)
+        .and_then(|()| command(DISABLE_SECOND_PORT))
+        .and_then(|()| {
+            flush();
+            read_config()
+        })
+        .and_then(|original| {
+            configure(set, original).inspect_err(|_| {
+                // The keyboard may have been reset to set 2 already.
+                let fallback = (original | FIRST_PORT_IRQ | TRANSLATION) & !FIRST_PORT_CLOCK_DISABLED;
+                flush();
+                let _ = write_config(fallback);
+            })
+        });
+    if res.is_err() {
+        let _ = command(ENABLE_FIRST_PORT);
+    }
+    res
+}
+
+/// Runs the part of [`init`] after which the configuration byte `original` must be restored
+/// on errors.
+fn configure(set: ScancodeSet, original: u8) -> Result<[Option<Device>; 2], Ps2Error> {
+    let mut config = original & !(FIRST_PORT_IRQ | SECOND_PORT_IRQ | TRANSLATION);
+    write_config(config)?;
+
+    command(SELF_TEST)?;
+    let result = read_data()?;
+    if result != SELF_TEST_PASSED {
+        return Err(Ps2Error::SelfTestFailed(result));
+    }
+    // Some controllers reset themselves during the self-test.
+    write_config(config)?;
+
+    command(ENABLE_SECOND_PORT)?;
+    let dual_port = read_config()? & SECOND_PORT_CLOCK_DISABLED == 0;
+    command(DISABLE_SECOND_PORT)?;
+
+    command(TEST_FIRST_PORT)?;
+    let first_works = read_data()? == PORT_TEST_PASSED;
+    let second_works = dual_port && {
+        command(TEST_SECOND_PORT)?;
+        read_data()? == PORT_TEST_PASSED
+    };
+
+    let mut devices = [None; 2];
+    if first_works {
+        command(ENABLE_FIRST_PORT)?;
+        devices[0] = detect(Ps2Port::First);
+    }
+    if second_works {
+        command(ENABLE_SECOND_PORT)?;
+        devices[1] = detect(Ps2Port::Second);
+    }
+
+    if devices[0].is_some_and(|d| d.is_keyboard()) {
+        send(Ps2Port::First, DEVICE_SET_SCANCODE_SET)?;
+        send(Ps2Port::First, 2)?;
+    }
+
+    if set == ScancodeSet::One {
+        config |= TRANSLATION;
+    }
+    if first_works {
+        config = (config | FIRST_PORT_IRQ) & !FIRST_PORT_CLOCK_DISABLED;
+    }
+    if second_works {
+        config = (config | SECOND_PORT_IRQ) & !SECOND_PORT_CLOCK_DISABLED;
+    }
+    flush();
+    write_config(config)?;
+
+    // SAFETY:
+    // The IRQs reading the devices do not touch `DEVICES`.
+    unsafe { DEVICES = devices };
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn devices_are_identified() -> Result<(), &'static str> {
+        kassert_eq!(Device::from_id(&[]), Device::AtKeyboard);
+        kassert_eq!(Device::from_id(&[0xab, 0x83]), Device::Mf2Keyboard);
+        kassert_eq!(Device::from_id(&[0x03]), Device::WheelMouse);
+        kassert!(Device::from_id(&[0x04]).is_mouse());
+        kassert!(!Device::from_id(&[0x12, 0x34]).is_keyboard());
+
+        Ok(())
+    }
+}
@@ -1,97 +1,46 @@
 use crate::{
     arch::x86::idt::InterruptRegisters,
+    input::{self, InputEvent},
     port::Port,
     ps2::{
-        DATA_PORT,
-        scancodes::{KeyEvent, SCANCODE_SET_1_TO_KEY, SCANCODE_SET_1_TO_KEY_EXTENDED},
+        DATA_PORT, leds,
+        scancodes::{Decoder, ScancodeSet},
     },
 };
 
-const BUFFER_SIZE: usize = 256;
+pub(super) const IRQ: u8 = 1;
 
-static mut BUFFER: Buffer = Buffer::new();
-static mut EXTENDED_BYTE_SENT: bool = false;
-
-struct Buffer {
-    buffer: [Option<KeyEvent>; BUFFER_SIZE],
-    read_pos: usize,
-    write_pos: usize,
-}
-
-impl Buffer {
-    const fn new() -> Self {
-        Self {
-            buffer: [None; BUFFER_SIZE],
-            read_pos: 0,
-            write_pos: 0,
-        }
-    }
-
-    fn push(&mut self, key_event: KeyEvent) {
-        let next_write = (self.write_pos + 1) % BUFFER_SIZE;
-
-        if next_write == self.read_pos {
-            self.read_pos = (self.read_pos + 1) % BUFFER_SIZE;
-        }
-        self.buffer[self.write_pos] = Some(key_event);
-        self.write_pos = next_write;
-    }
-
-    fn pop(&mut self) -> Option<KeyEvent> {
-        if self.read_pos == self.write_pos {
-            return None;
-        }
-        let key = self.buffer[self.read_pos];
-        self.read_pos = (self.read_pos + 1) % BUFFER_SIZE;
-
-        key
-    }
-}
+static mut DECODER: Decoder = Decoder::new(ScancodeSet::One);
 
 /// IRQ1
 extern "C" fn keyboard_interrupt_handler(_regs: &InterruptRegisters) {
     let data_port = Port::new(DATA_PORT);
     let scancode = unsafe { data_port.read() };
 
-    // SAFETY
-    // This is a global variable which will be available throughout
-    // the whole runtime of the program
-    let key = if unsafe { EXTENDED_BYTE_SENT } {
-        unsafe {
-            EXTENDED_BYTE_SENT = false;
-        }
-        SCANCODE_SET_1_TO_KEY_EXTENDED[scancode as usize]
-    } else {
-        if scancode == 0xE0 {
-            unsafe {
-                EXTENDED_BYTE_SENT = true;
-            }
-            None
-        } else {
-            SCANCODE_SET_1_TO_KEY[scancode as usize]
-        }
-    };
-
-    if let Some(key) = key {
-        unsafe {
-            #[allow(static_mut_refs)]
-            BUFFER.push(key);
-        }
+    if leds::acknowledge(scancode) {
+        return;
     }
-}
 
-#[must_use]
-pub fn read_key_event() -> Option<KeyEvent> {
+    // SAFETY:
+    // `DECODER` is only written by `init`, while IRQ1 is masked.
     #[allow(static_mut_refs)]
-    unsafe {
-        BUFFER.pop()
+    let key = unsafe { DECODER.advance(scancode) };
+
+    if let Some(key) = key {
+        input::publish(InputEvent::Key(key));
     }
 }
 
-pub fn init() {
-    use crate::arch::x86::interrupts::irq;
+/// Installs the keyboard handler, decoding the bytes received with `set`.
+pub fn init(set: ScancodeSet) {
+    use crate::arch::x86::interrupts::{irq, lock::IRQLock};
 
-    irq::install_handler(1, keyboard_interrupt_handler);
+    let lock = IRQLock::lock(IRQ);
+    // SAFETY:
+    // IRQ1 is masked, so the handler cannot use `DECODER` meanwhile.
+    unsafe { DECODER = Decoder::new(set) };
+    lock.unlock();
+    irq::install_handler(u32::from(IRQ), keyboard_interrupt_handler);
 
-    irq::clear_mask(1);
+    irq::clear_mask(IRQ);
 }
@@ -0,0 +1,65 @@
+//! Keyboard LEDs.
+//!
+//! Setting the LEDs takes two bytes: the 0xED command, then the LED state once
+//! the keyboard acknowledged the command. The acknowledgement arrives through
+//! IRQ1, whose handler passes the keyboard's responses to [`acknowledge`].
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::ps2::controller;
+
+const SET_LEDS: u8 = 0xed;
+const ACK: u8 = 0xfa;
+const RESEND: u8 = 0xfe;
+
+const SCROLL_LOCK: u8 = 1 << 0;
+const NUM_LOCK: u8 = 1 << 1;
+const CAPS_LOCK: u8 = 1 << 2;
+
+/// Marks that no LED state is waiting to be sent.
+const NONE: u8 = 0xff;
+
+/// LED state sent once the keyboard acknowledges [`SET_LEDS`].
+static PENDING: AtomicU8 = AtomicU8::new(NONE);
+
+/// Writes `byte` to the keyboard. A byte the controller does not accept in time is dropped,
+/// the LEDs are only a hint.
+fn send(byte: u8) {
+    let _ = controller::write_data(byte);
+}
+
+/// Lights the LEDs of the lock keys that are on.
+pub fn set_leds(scroll_lock: bool, num_lock: bool, caps_lock: bool) {
+    let mut leds = 0;
+    if scroll_lock {
+        leds |= SCROLL_LOCK;
+    }
+    if num_lock {
+        leds |= NUM_LOCK;
+    }
+    if caps_lock {
+        leds |= CAPS_LOCK;
+    }
+
+    PENDING.store(leds, Ordering::Relaxed);
+    send(SET_LEDS);
+}
+
+/// Handles a byte received from the keyboard, returning `true` if it was a response to a LED
+/// command rather than a scancode.
+pub(super) fn acknowledge(byte: u8) -> bool {
+    match byte {
+        ACK => {
+            let leds = PENDING.swap(NONE, Ordering::Relaxed);
+            if leds != NONE {
+                send(leds);
+            }
+            true
+        }
+        RESEND if PENDING.load(Ordering::Relaxed) != NONE => {
+            send(SET_LEDS);
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,243 @@
+//! PS/2 mouse, on the second port.
+//!
+//! The mouse sends a packet of 3 bytes whenever it moves or a button changes,
+//! or 4 bytes once it was switched to the wheel or 5-button mode:
+//!
+//! | Byte | Bits                                                                |
+//! |------|---------------------------------------------------------------------|
+//! | 0    | Y overflow, X overflow, Y sign, X sign, 1, middle, right, left      |
+//! | 1    | X movement                                                          |
+//! | 2    | Y movement                                                          |
+//! | 3    | Wheel movement, or buttons 5 and 4 and a 4-bit wheel movement       |
+//!
+//! https://wiki.osdev.org/PS/2_Mouse
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{
+    arch::x86::{
+        idt::InterruptRegisters,
+        interrupts::{irq, lock::IRQLock},
+    },
+    input::{self, InputEvent},
+    port::Port,
+    ps2::{DATA_PORT, Device, Event},
+};
+
+pub(super) const IRQ: u8 = 12;
+
+const LEFT_BUTTON: u8 = 1 << 0;
+const RIGHT_BUTTON: u8 = 1 << 1;
+const MIDDLE_BUTTON: u8 = 1 << 2;
+/// Always set in the first byte, which is how packets are kept in sync.
+const ALWAYS_ONE: u8 = 1 << 3;
+const X_SIGN: u8 = 1 << 4;
+const Y_SIGN: u8 = 1 << 5;
+const X_OVERFLOW: u8 = 1 << 6;
+const Y_OVERFLOW: u8 = 1 << 7;
+const FOURTH_BUTTON: u8 = 1 << 4;
+const FIFTH_BUTTON: u8 = 1 << 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+    Fourth,
+    Fifth,
+}
+
+impl Button {
+    const ALL: [(Self, u8); 5] = [
+        (Self::Left, LEFT_BUTTON),
+        (Self::Right, RIGHT_BUTTON),
+        (Self::Middle, MIDDLE_BUTTON),
+        (Self::Fourth, FOURTH_BUTTON >> 1),
+        (Self::Fifth, FIFTH_BUTTON >> 1),
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseEvent {
+    /// Relative movement, with `dy` positive upwards.
+    Move {
+        dx: i16,
+        dy: i16,
+    },
+    Button {
+        button: Button,
+        event: Event,
+    },
+    /// Wheel movement, positive when scrolled towards the user.
+    Wheel(i8),
+}
+
+/// Packet being received.
+struct Packet {
+    bytes: [u8; 4],
+    len: usize,
+    /// Number of bytes of a packet, 3 or 4.
+    size: usize,
+    five_buttons: bool,
+    /// Buttons held, as of the last packet. The 4th and 5th buttons are bits 3 and 4.
+    buttons: u8,
+}
+
+impl Packet {
+    const fn new(device: Device) -> Self {
+        Self {
+            bytes: [0; 4],
+            len: 0,
+            size: match device {
+                Device::WheelMouse | Device::FiveButtonMouse => 4,
+                _ => 3,
+            },
+            five_buttons: matches!(device, Device::FiveButtonMouse),
+            buttons: 0,
+        }
+    }
+
+    /// Feeds a byte received from the mouse, and calls `push` with the events of the packet
+    /// it completes, if any.
+    fn advance(&mut self, byte: u8, mut push: impl FnMut(MouseEvent)) {
+        // A first byte without this bit means a byte was lost, skip until the next packet.
+        if self.len == 0 && byte & ALWAYS_ONE == 0 {
+            return;
+        }
+        self.bytes[self.len] = byte;
+        self.len += 1;
+        if self.len < self.size {
+            return;
+        }
+        self.len = 0;
+
+        let [flags, x, y, extra] = self.bytes;
+        let mut buttons = flags & (LEFT_BUTTON | RIGHT_BUTTON | MIDDLE_BUTTON);
+        let dz = match (self.size, self.five_buttons) {
+            (3, _) => 0,
+            (_, false) => extra.cast_signed(),
+            (_, true) => {
+                buttons |= (extra & (FOURTH_BUTTON | FIFTH_BUTTON)) >> 1;
+                // Sign-extends the low nibble.
+                (extra << 4).cast_signed() >> 4
+            }
+        };
+
+        for (button, mask) in Button::ALL {
+            if (buttons ^ self.buttons) & mask != 0 {
+                let event = if buttons & mask != 0 { Event::Pressed } else { Event::Released };
+                push(MouseEvent::Button { button, event });
+            }
+        }
+        self.buttons = buttons;
+
+        // The movement of an overflowing packet is garbage.
+        if flags & (X_OVERFLOW | Y_OVERFLOW) == 0 {
+            let dx = i16::from(x) - if flags & X_SIGN != 0 { 0x100 } else { 0 };
+            let dy = i16::from(y) - if flags & Y_SIGN != 0 { 0x100 } else { 0 };
+            if dx != 0 || dy != 0 {
+                push(MouseEvent::Move { dx, dy });
+            }
+        }
+        if dz != 0 {
+            push(MouseEvent::Wheel(dz));
+        }
+    }
+}
+
+static mut PACKET: Packet = Packet::new(Device::Mouse);
+
+/// Whether a mouse was found, before which the bytes received are dropped.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// IRQ12
+extern "C" fn mouse_interrupt_handler(_regs: &InterruptRegisters) {
+    // SAFETY:
+    // Reading the data port pops the byte that raised the IRQ.
+    let byte = unsafe { Port::new(DATA_PORT).read() };
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // SAFETY:
+    // `PACKET` is only written by `enable`, while IRQ12 is masked.
+    #[allow(static_mut_refs)]
+    unsafe {
+        PACKET.advance(byte, |event| input::publish(InputEvent::Mouse(event)));
+    }
+}
+
+/// Installs the mouse handler, which drops the bytes received until [`enable`] is called.
+pub(super) fn init() {
+    irq::install_handler(u32::from(IRQ), mouse_interrupt_handler);
+    irq::clear_mask(IRQ);
+}
+
+/// Starts parsing the packets of `device`.
+pub(super) fn enable(device: Device) {
+    let lock = IRQLock::lock(IRQ);
+    // SAFETY:
+    // IRQ12 is masked, so the handler cannot use `PACKET` meanwhile.
+    unsafe { PACKET = Packet::new(device) };
+    ENABLED.store(true, Ordering::Relaxed);
+    lock.unlock();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    fn parse(device: Device, bytes: &[u8]) -> ([Option<MouseEvent>; 4], usize) {
+        let mut packet = Packet::new(device);
+        let mut events = [None; 4];
+        let mut len = 0;
+        for byte in bytes {
+            packet.advance(*byte, |event| {
+                events[len] = Some(event);
+                len += 1;
+            });
+        }
+        (events, len)
+    }
+
+    #[test_case]
+    fn packets_are_parsed() -> Result<(), &'static str> {
+        let (events, len) = parse(Device::Mouse, &[0x09 | X_SIGN, 0xfe, 0x03]);
+        kassert_eq!(len, 2);
+        kassert_eq!(
+            events[0],
+            Some(MouseEvent::Button {
+                button: Button::Left,
+                event: Event::Pressed
+            })
+        );
+        kassert_eq!(events[1], Some(MouseEvent::Move { dx: -2, dy: 3 }));
+
+        let (events, len) = parse(Device::WheelMouse, &[0x08, 0x00, 0x00, 0xff]);
+        kassert_eq!(len, 1);
+        kassert_eq!(events[0], Some(MouseEvent::Wheel(-1)));
+
+        let (events, len) = parse(Device::FiveButtonMouse, &[0x08, 0x00, 0x00, 0x1f]);
+        kassert_eq!(len, 2);
+        kassert_eq!(
+            events[0],
+            Some(MouseEvent::Button {
+                button: Button::Fourth,
+                event: Event::Pressed
+            })
+        );
+        kassert_eq!(events[1], Some(MouseEvent::Wheel(-1)));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn lost_bytes_are_resynchronized() -> Result<(), &'static str> {
+        let (_, len) = parse(Device::Mouse, &[0x00, 0x05, 0x08, 0x01, 0x00]);
+        kassert_eq!(len, 1);
+        kassert!(parse(Device::Mouse, &[0x08 | X_OVERFLOW, 0xff, 0x00]).1 == 0);
+
+        Ok(())
+    }
+}
@@ -8,6 +8,11 @@ pub enum Key {
     ArrowDown,
     ArrowLeft,
     ArrowRight,
+    Home,
+    End,
+    Delete,
+    PageUp,
+    PageDown,
     LeftAlt,
     RightAlt,
     LeftShift,
@@ -76,6 +81,8 @@ pub enum Key {
     Semicolon,
     Slash,
     Backslash,
+    /// Key between left Shift and Z on ISO keyboards, `<>` on most European layouts.
+    NonUsBackslash,
     SingleQuote,
     SquareBracketsOpen,
     SquareBracketsClosed,
@@ -97,7 +104,7 @@ pub enum Key {
     KeypadComma,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Event {
     Pressed,
     Released,
@@ -111,10 +118,73 @@ pub struct KeyEvent {
 }
 
 impl KeyEvent {
+    #[must_use]
     pub const fn new(scancode: u8, event: Event, key: Key) -> Self {
         Self { key, event, scancode }
     }
 }
+
+/// Scancode set the keyboard's bytes are decoded with.
+///
+/// PS/2 keyboards send set 2. With [`ScancodeSet::One`], the controller
+/// translates it to set 1 on the fly, and with [`ScancodeSet::Two`] the bytes
+/// are decoded as sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScancodeSet {
+    One,
+    Two,
+}
+
+/// Turns the bytes sent by the keyboard into key events, keeping track of the
+/// prefixes of multi-byte scancodes.
+#[derive(Clone, Copy, Debug)]
+pub struct Decoder {
+    set: ScancodeSet,
+    /// A `0xe0` prefix was received.
+    extended: bool,
+    /// A `0xf0` prefix was received (set 2 only).
+    released: bool,
+}
+
+impl Decoder {
+    #[must_use]
+    pub const fn new(set: ScancodeSet) -> Self {
+        Self {
+            set,
+            extended: false,
+            released: false,
+        }
+    }
+
+    /// Feeds a byte received from the keyboard, returning the key event it completes, if any.
+    pub fn advance(&mut self, byte: u8) -> Option<KeyEvent> {
+        match (self.set, byte) {
+            (_, 0xe0) => {
+                self.extended = true;
+                return None;
+            }
+            (ScancodeSet::Two, 0xf0) => {
+                self.released = true;
+                return None;
+            }
+            _ => {}
+        }
+
+        let extended = core::mem::take(&mut self.extended);
+        let released = core::mem::take(&mut self.released);
+
+        match (self.set, extended) {
+            (ScancodeSet::One, false) => SCANCODE_SET_1_TO_KEY[byte as usize],
+            (ScancodeSet::One, true) => SCANCODE_SET_1_TO_KEY_EXTENDED[byte as usize],
+            (ScancodeSet::Two, _) => {
+                let table = if extended { &SCANCODE_SET_2_TO_KEY_EXTENDED } else { &SCANCODE_SET_2_TO_KEY };
+                let event = if released { Event::Released } else { Event::Pressed };
+                table[byte as usize].map(|key| KeyEvent::new(byte, event, key))
+            }
+        }
+    }
+}
+
 use Event::*;
 use Key::*;
 
@@ -209,7 +279,7 @@ pub const SCANCODE_SET_1_TO_KEY: [Option<KeyEvent>; 256] = [
     /* 0x53 */ Some(KeyEvent::new(0x53, Pressed, KeypadComma)),
     /* 0x54 */ None,
     /* 0x55 */ None,
-    /* 0x56 */ None,
+    /* 0x56 */ Some(KeyEvent::new(0x56, Pressed, NonUsBackslash)),
     /* 0x57 */ Some(KeyEvent::new(0x57, Pressed, F11)),
     /* 0x58 */ Some(KeyEvent::new(0x58, Pressed, F12)),
     /* 0x59 */ None,
@@ -337,7 +407,7 @@ pub const SCANCODE_SET_1_TO_KEY: [Option<KeyEvent>; 256] = [
     /* 0xd3 */ Some(KeyEvent::new(0xd3, Released, KeypadComma)),
     /* 0xd4 */ None,
     /* 0xd5 */ None,
-    /* 0xd6 */ None,
+    /* 0xd6 */ Some(KeyEvent::new(0xd6, Released, NonUsBackslash)),
     /* 0xd7 */ Some(KeyEvent::new(0xd7, Released, F11)),
     /* 0xd8 */ Some(KeyEvent::new(0xd8, Released, F12)),
     /* 0xd9 */ None,
@@ -438,7 +508,7 @@ pub const SCANCODE_SET_1_TO_KEY_EXTENDED: [Option<KeyEvent>; 256] = [
     /* 0x35 */ None,
     /* 0x36 */ None,
     /* 0x37 */ None,
-    /* 0x38 */ None,
+    /* 0x38 */ Some(KeyEvent::new(0x38, Pressed, RightAlt)),
     /* 0x39 */ None,
     /* 0x3a */ None,
     /* 0x3b */ None,
@@ -453,19 +523,19 @@ pub const SCANCODE_SET_1_TO_KEY_EXTENDED: [Option<KeyEvent>; 256] = [
     /* 0x44 */ None,
     /* 0x45 */ None,
     /* 0x46 */ None,
-    /* 0x47 */ None,
+    /* 0x47 */ Some(KeyEvent::new(0x47, Pressed, Home)),
     /* 0x48 */ Some(KeyEvent::new(0x48, Pressed, ArrowUp)),
-    /* 0x49 */ None,
+    /* 0x49 */ Some(KeyEvent::new(0x49, Pressed, PageUp)),
     /* 0x4a */ None,
     /* 0x4b */ Some(KeyEvent::new(0x4b, Pressed, ArrowLeft)),
     /* 0x4c */ None,
     /* 0x4d */ Some(KeyEvent::new(0x4d, Pressed, ArrowRight)),
     /* 0x4e */ None,
-    /* 0x4f */ None,
+    /* 0x4f */ Some(KeyEvent::new(0x4f, Pressed, End)),
     /* 0x50 */ Some(KeyEvent::new(0x50, Pressed, ArrowDown)),
-    /* 0x51 */ None,
+    /* 0x51 */ Some(KeyEvent::new(0x51, Pressed, PageDown)),
     /* 0x52 */ None,
-    /* 0x53 */ None,
+    /* 0x53 */ Some(KeyEvent::new(0x53, Pressed, Delete)),
     /* 0x54 */ None,
     /* 0x55 */ None,
     /* 0x56 */ None,
@@ -566,7 +636,7 @@ pub const SCANCODE_SET_1_TO_KEY_EXTENDED: [Option<KeyEvent>; 256] = [
     /* 0xb5 */ None,
     /* 0xb6 */ None,
     /* 0xb7 */ None,
-    /* 0xb8 */ None,
+    /* 0xb8 */ Some(KeyEvent::new(0xb8, Released, RightAlt)),
     /* 0xb9 */ None,
     /* 0xba */ None,
     /* 0xbb */ None,
@@ -581,19 +651,19 @@ pub const SCANCODE_SET_1_TO_KEY_EXTENDED: [Option<KeyEvent>; 256] = [
     /* 0xc4 */ None,
     /* 0xc5 */ None,
     /* 0xc6 */ None,
-    /* 0xc7 */ None,
+    /* 0xc7 */ Some(KeyEvent::new(0xc7, Released, Home)),
     /* 0xc8 */ Some(KeyEvent::new(0xc8, Released, ArrowUp)),
-    /* 0xc9 */ None,
+    /* 0xc9 */ Some(KeyEvent::new(0xc9, Released, PageUp)),
     /* 0xca */ None,
     /* 0xcb */ Some(KeyEvent::new(0xcb, Released, ArrowLeft)),
     /* 0xcc */ None,
     /* 0xcd */ Some(KeyEvent::new(0xcd, Released, ArrowRight)),
     /* 0xce */ None,
-    /* 0xcf */ None,
+    /* 0xcf */ Some(KeyEvent::new(0xcf, Released, End)),
     /* 0xd0 */ Some(KeyEvent::new(0xd0, Released, ArrowDown)),
-    /* 0xd1 */ None,
+    /* 0xd1 */ Some(KeyEvent::new(0xd1, Released, PageDown)),
     /* 0xd2 */ None,
-    /* 0xd3 */ None,
+    /* 0xd3 */ Some(KeyEvent::new(0xd3, Released, Delete)),
     /* 0xd4 */ None,
     /* 0xd5 */ None,
     /* 0xd6 */ None,
@@ -639,3 +709,573 @@ pub const SCANCODE_SET_1_TO_KEY_EXTENDED: [Option<KeyEvent>; 256] = [
     /* 0xfe */ None,
     /* 0xff */ None,
 ];
+
+/// Make codes of scancode set 2, the native set of PS/2 keyboards. Break codes
+/// are the same bytes, preceded by `0xf0`.
+///
+/// https://wiki.osdev.org/PS/2_Keyboard#Scan_Code_Set_2
+pub const SCANCODE_SET_2_TO_KEY: [Option<Key>; 256] = [
+    /* 0x00 */ None,
+    /* 0x01 */ Some(F9),
+    /* 0x02 */ None,
+    /* 0x03 */ Some(F5),
+    /* 0x04 */ Some(F3),
+    /* 0x05 */ Some(F1),
+    /* 0x06 */ Some(F2),
+    /* 0x07 */ Some(F12),
+    /* 0x08 */ None,
+    /* 0x09 */ Some(F10),
+    /* 0x0a */ Some(F8),
+    /* 0x0b */ Some(F6),
+    /* 0x0c */ Some(F4),
+    /* 0x0d */ Some(Tab),
+    /* 0x0e */ Some(Backtick),
+    /* 0x0f */ None,
+    /* 0x10 */ None,
+    /* 0x11 */ Some(LeftAlt),
+    /* 0x12 */ Some(LeftShift),
+    /* 0x13 */ None,
+    /* 0x14 */ Some(LeftCtrl),
+    /* 0x15 */ Some(Q),
+    /* 0x16 */ Some(N1),
+    /* 0x17 */ None,
+    /* 0x18 */ None,
+    /* 0x19 */ None,
+    /* 0x1a */ Some(Z),
+    /* 0x1b */ Some(S),
+    /* 0x1c */ Some(A),
+    /* 0x1d */ Some(W),
+    /* 0x1e */ Some(N2),
+    /* 0x1f */ None,
+    /* 0x20 */ None,
+    /* 0x21 */ Some(C),
+    /* 0x22 */ Some(X),
+    /* 0x23 */ Some(D),
+    /* 0x24 */ Some(E),
+    /* 0x25 */ Some(N4),
+    /* 0x26 */ Some(N3),
+    /* 0x27 */ None,
+    /* 0x28 */ None,
+    /* 0x29 */ Some(Space),
+    /* 0x2a */ Some(V),
+    /* 0x2b */ Some(F),
+    /* 0x2c */ Some(T),
+    /* 0x2d */ Some(R),
+    /* 0x2e */ Some(N5),
+    /* 0x2f */ None,
+    /* 0x30 */ None,
+    /* 0x31 */ Some(N),
+    /* 0x32 */ Some(B),
+    /* 0x33 */ Some(H),
+    /* 0x34 */ Some(G),
+    /* 0x35 */ Some(Y),
+    /* 0x36 */ Some(N6),
+    /* 0x37 */ None,
+    /* 0x38 */ None,
+    /* 0x39 */ None,
+    /* 0x3a */ Some(M),
+    /* 0x3b */ Some(J),
+    /* 0x3c */ Some(U),
+    /* 0x3d */ Some(N7),
+    /* 0x3e */ Some(N8),
+    /* 0x3f */ None,
+    /* 0x40 */ None,
+    /* 0x41 */ Some(Comma),
+    /* 0x42 */ Some(K),
+    /* 0x43 */ Some(I),
+    /* 0x44 */ Some(O),
+    /* 0x45 */ Some(N0),
+    /* 0x46 */ Some(N9),
+    /* 0x47 */ None,
+    /* 0x48 */ None,
+    /* 0x49 */ Some(Point),
+    /* 0x4a */ Some(Slash),
+    /* 0x4b */ Some(L),
+    /* 0x4c */ Some(Semicolon),
+    /* 0x4d */ Some(P),
+    /* 0x4e */ Some(Minus),
+    /* 0x4f */ None,
+    /* 0x50 */ None,
+    /* 0x51 */ None,
+    /* 0x52 */ Some(SingleQuote),
+    /* 0x53 */ None,
+    /* 0x54 */ Some(SquareBracketsOpen),
+    /* 0x55 */ Some(Equal),
+    /* 0x56 */ None,
+    /* 0x57 */ None,
+    /* 0x58 */ Some(CapsLock),
+    /* 0x59 */ Some(RightShift),
+    /* 0x5a */ Some(Enter),
+    /* 0x5b */ Some(SquareBracketsClosed),
+    /* 0x5c */ None,
+    /* 0x5d */ Some(Backslash),
+    /* 0x5e */ None,
+    /* 0x5f */ None,
+    /* 0x60 */ None,
+    /* 0x61 */ Some(NonUsBackslash),
+    /* 0x62 */ None,
+    /* 0x63 */ None,
+    /* 0x64 */ None,
+    /* 0x65 */ None,
+    /* 0x66 */ Some(Backspace),
+    /* 0x67 */ None,
+    /* 0x68 */ None,
+    /* 0x69 */ Some(KeypadN1),
+    /* 0x6a */ None,
+    /* 0x6b */ Some(KeypadN4),
+    /* 0x6c */ Some(KeypadN7),
+    /* 0x6d */ None,
+    /* 0x6e */ None,
+    /* 0x6f */ None,
+    /* 0x70 */ Some(KeypadN0),
+    /* 0x71 */ Some(KeypadComma),
+    /* 0x72 */ Some(KeypadN2),
+    /* 0x73 */ Some(KeypadN5),
+    /* 0x74 */ Some(KeypadN6),
+    /* 0x75 */ Some(KeypadN8),
+    /* 0x76 */ Some(Escape),
+    /* 0x77 */ Some(NumLock),
+    /* 0x78 */ Some(F11),
+    /* 0x79 */ Some(KeypadAdd),
+    /* 0x7a */ Some(KeypadN3),
+    /* 0x7b */ Some(KeypadSub),
+    /* 0x7c */ Some(KeypadMul),
+    /* 0x7d */ Some(KeypadN9),
+    /* 0x7e */ Some(ScrollLock),
+    /* 0x7f */ None,
+    /* 0x80 */ None,
+    /* 0x81 */ None,
+    /* 0x82 */ None,
+    /* 0x83 */ Some(F7),
+    /* 0x84 */ None,
+    /* 0x85 */ None,
+    /* 0x86 */ None,
+    /* 0x87 */ None,
+    /* 0x88 */ None,
+    /* 0x89 */ None,
+    /* 0x8a */ None,
+    /* 0x8b */ None,
+    /* 0x8c */ None,
+    /* 0x8d */ None,
+    /* 0x8e */ None,
+    /* 0x8f */ None,
+    /* 0x90 */ None,
+    /* 0x91 */ None,
+    /* 0x92 */ None,
+    /* 0x93 */ None,
+    /* 0x94 */ None,
+    /* 0x95 */ None,
+    /* 0x96 */ None,
+    /* 0x97 */ None,
+    /* 0x98 */ None,
+    /* 0x99 */ None,
+    /* 0x9a */ None,
+    /* 0x9b */ None,
+    /* 0x9c */ None,
+    /* 0x9d */ None,
+    /* 0x9e */ None,
+    /* 0x9f */ None,
+    /* 0xa0 */ None,
+    /* 0xa1 */ None,
+    /* 0xa2 */ None,
+    /* 0xa3 */ None,
+    /* 0xa4 */ None,
+    /* 0xa5 */ None,
+    /* 0xa6 */ None,
+    /* 0xa7 */ None,
+    /* 0xa8 */ None,
+    /* 0xa9 */ None,
+    /* 0xaa */ None,
+    /* 0xab */ None,
+    /* 0xac */ None,
+    /* 0xad */ None,
+    /* 0xae */ None,
+    /* 0xaf */ None,
+    /* 0xb0 */ None,
+    /* 0xb1 */ None,
+    /* 0xb2 */ None,
+    /* 0xb3 */ None,
+    /* 0xb4 */ None,
+    /* 0xb5 */ None,
+    /* 0xb6 */ None,
+    /* 0xb7 */ None,
+    /* 0xb8 */ None,
+    /* 0xb9 */ None,
+    /* 0xba */ None,
+    /* 0xbb */ None,
+    /* 0xbc */ None,
+    /* 0xbd */ None,
+    /* 0xbe */ None,
+    /* 0xbf */ None,
+    /* 0xc0 */ None,
+    /* 0xc1 */ None,
+    /* 0xc2 */ None,
+    /* 0xc3 */ None,
+    /* 0xc4 */ None,
+    /* 0xc5 */ None,
+    /* 0xc6 */ None,
+    /* 0xc7 */ None,
+    /* 0xc8 */ None,
+    /* 0xc9 */ None,
+    /* 0xca */ None,
+    /* 0xcb */ None,
+    /* 0xcc */ None,
+    /* 0xcd */ None,
+    /* 0xce */ None,
+    /* 0xcf */ None,
+    /* 0xd0 */ None,
+    /* 0xd1 */ None,
+    /* 0xd2 */ None,
+    /* 0xd3 */ None,
+    /* 0xd4 */ None,
+    /* 0xd5 */ None,
+    /* 0xd6 */ None,
+    /* 0xd7 */ None,
+    /* 0xd8 */ None,
+    /* 0xd9 */ None,
+    /* 0xda */ None,
+    /* 0xdb */ None,
+    /* 0xdc */ None,
+    /* 0xdd */ None,
+    /* 0xde */ None,
+    /* 0xdf */ None,
+    /* 0xe0 */ None,
+    /* 0xe1 */ None,
+    /* 0xe2 */ None,
+    /* 0xe3 */ None,
+    /* 0xe4 */ None,
+    /* 0xe5 */ None,
+    /* 0xe6 */ None,
+    /* 0xe7 */ None,
+    /* 0xe8 */ None,
+    /* 0xe9 */ None,
+    /* 0xea */ None,
+    /* 0xeb */ None,
+    /* 0xec */ None,
+    /* 0xed */ None,
+    /* 0xee */ None,
+    /* 0xef */ None,
+    /* 0xf0 */ None,
+    /* 0xf1 */ None,
+    /* 0xf2 */ None,
+    /* 0xf3 */ None,
+    /* 0xf4 */ None,
+    /* 0xf5 */ None,
+    /* 0xf6 */ None,
+    /* 0xf7 */ None,
+    /* 0xf8 */ None,
+    /* 0xf9 */ None,
+    /* 0xfa */ None,
+    /* 0xfb */ None,
+    /* 0xfc */ None,
+    /* 0xfd */ None,
+    /* 0xfe */ None,
+    /* 0xff */ None,
+];
+
+/// Make codes of scancode set 2 preceded by `0xe0`.
+pub const SCANCODE_SET_2_TO_KEY_EXTENDED: [Option<Key>; 256] = [
+    /* 0x00 */ None,
+    /* 0x01 */ None,
+    /* 0x02 */ None,
+    /* 0x03 */ None,
+    /* 0x04 */ None,
+    /* 0x05 */ None,
+    /* 0x06 */ None,
+    /* 0x07 */ None,
+    /* 0x08 */ None,
+    /* 0x09 */ None,
+    /* 0x0a */ None,
+    /* 0x0b */ None,
+    /* 0x0c */ None,
+    /* 0x0d */ None,
+    /* 0x0e */ None,
+    /* 0x0f */ None,
+    /* 0x10 */ None,
+    /* 0x11 */ Some(RightAlt),
+    /* 0x12 */ None,
+    /* 0x13 */ None,
+    /* 0x14 */ Some(RightCtrl),
+    /* 0x15 */ None,
+    /* 0x16 */ None,
+    /* 0x17 */ None,
+    /* 0x18 */ None,
+    /* 0x19 */ None,
+    /* 0x1a */ None,
+    /* 0x1b */ None,
+    /* 0x1c */ None,
+    /* 0x1d */ None,
+    /* 0x1e */ None,
+    /* 0x1f */ Some(LeftGui),
+    /* 0x20 */ None,
+    /* 0x21 */ None,
+    /* 0x22 */ None,
+    /* 0x23 */ None,
+    /* 0x24 */ None,
+    /* 0x25 */ None,
+    /* 0x26 */ None,
+    /* 0x27 */ Some(RightGui),
+    /* 0x28 */ None,
+    /* 0x29 */ None,
+    /* 0x2a */ None,
+    /* 0x2b */ None,
+    /* 0x2c */ None,
+    /* 0x2d */ None,
+    /* 0x2e */ None,
+    /* 0x2f */ None,
+    /* 0x30 */ None,
+    /* 0x31 */ None,
+    /* 0x32 */ None,
+    /* 0x33 */ None,
+    /* 0x34 */ None,
+    /* 0x35 */ None,
+    /* 0x36 */ None,
+    /* 0x37 */ None,
+    /* 0x38 */ None,
+    /* 0x39 */ None,
+    /* 0x3a */ None,
+    /* 0x3b */ None,
+    /* 0x3c */ None,
+    /* 0x3d */ None,
+    /* 0x3e */ None,
+    /* 0x3f */ None,
+    /* 0x40 */ None,
+    /* 0x41 */ None,
+    /* 0x42 */ None,
+    /* 0x43 */ None,
+    /* 0x44 */ None,
+    /* 0x45 */ None,
+    /* 0x46 */ None,
+    /* 0x47 */ None,
+    /* 0x48 */ None,
+    /* 0x49 */ None,
+    /* 0x4a */ Some(KeypadDiv),
+    /* 0x4b */ None,
+    /* 0x4c */ None,
+    /* 0x4d */ None,
+    /* 0x4e */ None,
+    /* 0x4f */ None,
+    /* 0x50 */ None,
+    /* 0x51 */ None,
+    /* 0x52 */ None,
+    /* 0x53 */ None,
+    /* 0x54 */ None,
+    /* 0x55 */ None,
+    /* 0x56 */ None,
+    /* 0x57 */ None,
+    /* 0x58 */ None,
+    /* 0x59 */ None,
+    /* 0x5a */ Some(KeypadEnter),
+    /* 0x5b */ None,
+    /* 0x5c */ None,
+    /* 0x5d */ None,
+    /* 0x5e */ None,
+    /* 0x5f */ None,
+    /* 0x60 */ None,
+    /* 0x61 */ None,
+    /* 0x62 */ None,
+    /* 0x63 */ None,
+    /* 0x64 */ None,
+    /* 0x65 */ None,
+    /* 0x66 */ None,
+    /* 0x67 */ None,
+    /* 0x68 */ None,
+    /* 0x69 */ Some(End),
+    /* 0x6a */ None,
+    /* 0x6b */ Some(ArrowLeft),
+    /* 0x6c */ Some(Home),
+    /* 0x6d */ None,
+    /* 0x6e */ None,
+    /* 0x6f */ None,
+    /* 0x70 */ None,
+    /* 0x71 */ Some(Delete),
+    /* 0x72 */ Some(ArrowDown),
+    /* 0x73 */ None,
+    /* 0x74 */ Some(ArrowRight),
+    /* 0x75 */ Some(ArrowUp),
+    /* 0x76 */ None,
+    /* 0x77 */ None,
+    /* 0x78 */ None,
+    /* 0x79 */ None,
+    /* 0x7a */ Some(PageDown),
+    /* 0x7b */ None,
+    /* 0x7c */ None,
+    /* 0x7d */ Some(PageUp),
+    /* 0x7e */ None,
+    /* 0x7f */ None,
+    /* 0x80 */ None,
+    /* 0x81 */ None,
+    /* 0x82 */ None,
+    /* 0x83 */ None,
+    /* 0x84 */ None,
+    /* 0x85 */ None,
+    /* 0x86 */ None,
+    /* 0x87 */ None,
+    /* 0x88 */ None,
+    /* 0x89 */ None,
+    /* 0x8a */ None,
+    /* 0x8b */ None,
+    /* 0x8c */ None,
+    /* 0x8d */ None,
+    /* 0x8e */ None,
+    /* 0x8f */ None,
+    /* 0x90 */ None,
+    /* 0x91 */ None,
+    /* 0x92 */ None,
+    /* 0x93 */ None,
+    /* 0x94 */ None,
+    /* 0x95 */ None,
+    /* 0x96 */ None,
+    /* 0x97 */ None,
+    /* 0x98 */ None,
+    /* 0x99 */ None,
+    /* 0x9a */ None,
+    /* 0x9b */ None,
+    /* 0x9c */ None,
+    /* 0x9d */ None,
+    /* 0x9e */ None,
+    /* 0x9f */ None,
+    /* 0xa0 */ None,
+    /* 0xa1 */ None,
+    /* 0xa2 */ None,
+    /* 0xa3 */ None,
+    /* 0xa4 */ None,
+    /* 0xa5 */ None,
+    /* 0xa6 */ None,
+    /* 0xa7 */ None,
+    /* 0xa8 */ None,
+    /* 0xa9 */ None,
+    /* 0xaa */ None,
+    /* 0xab */ None,
+    /* 0xac */ None,
+    /* 0xad */ None,
+    /* 0xae */ None,
+    /* 0xaf */ None,
+    /* 0xb0 */ None,
+    /* 0xb1 */ None,
+    /* 0xb2 */ None,
+    /* 0xb3 */ None,
+    /* 0xb4 */ None,
+    /* 0xb5 */ None,
+    /* 0xb6 */ None,
+    /* 0xb7 */ None,
+    /* 0xb8 */ None,
+    /* 0xb9 */ None,
+    /* 0xba */ None,
+    /* 0xbb */ None,
+    /* 0xbc */ None,
+    /* 0xbd */ None,
+    /* 0xbe */ None,
+    /* 0xbf */ None,
+    /* 0xc0 */ None,
+    /* 0xc1 */ None,
+    /* 0xc2 */ None,
+    /* 0xc3 */ None,
+    /* 0xc4 */ None,
+    /* 0xc5 */ None,
+    /* 0xc6 */ None,
+    /* 0xc7 */ None,
+    /* 0xc8 */ None,
+    /* 0xc9 */ None,
+    /* 0xca */ None,
+    /* 0xcb */ None,
+    /* 0xcc */ None,
+    /* 0xcd */ None,
+    /* 0xce */ None,
+    /* 0xcf */ None,
+    /* 0xd0 */ None,
+    /* 0xd1 */ None,
+    /* 0xd2 */ None,
+    /* 0xd3 */ None,
+    /* 0xd4 */ None,
+    /* 0xd5 */ None,
+    /* 0xd6 */ None,
+    /* 0xd7 */ None,
+    /* 0xd8 */ None,
+    /* 0xd9 */ None,
+    /* 0xda */ None,
+    /* 0xdb */ None,
+    /* 0xdc */ None,
+    /* 0xdd */ None,
+    /* 0xde */ None,
+    /* 0xdf */ None,
+    /* 0xe0 */ None,
+    /* 0xe1 */ None,
+    /* 0xe2 */ None,
+    /* 0xe3 */ None,
+    /* 0xe4 */ None,
+    /* 0xe5 */ None,
+    /* 0xe6 */ None,
+    /* 0xe7 */ None,
+    /* 0xe8 */ None,
+    /* 0xe9 */ None,
+    /* 0xea */ None,
+    /* 0xeb */ None,
+    /* 0xec */ None,
+    /* 0xed */ None,
+    /* 0xee */ None,
+    /* 0xef */ None,
+    /* 0xf0 */ None,
+    /* 0xf1 */ None,
+    /* 0xf2 */ None,
+    /* 0xf3 */ None,
+    /* 0xf4 */ None,
+    /* 0xf5 */ None,
+    /* 0xf6 */ None,
+    /* 0xf7 */ None,
+    /* 0xf8 */ None,
+    /* 0xf9 */ None,
+    /* 0xfa */ None,
+    /* 0xfb */ None,
+    /* 0xfc */ None,
+    /* 0xfd */ None,
+    /* 0xfe */ None,
+    /* 0xff */ None,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert;
+
+    fn decode(set: ScancodeSet, bytes: &[u8]) -> Option<KeyEvent> {
+        let mut decoder = Decoder::new(set);
+        bytes.iter().filter_map(|b| decoder.advance(*b)).last()
+    }
+
+    #[test_case]
+    fn both_sets_decode_the_same_keys() -> Result<(), &'static str> {
+        kassert!(matches!(decode(ScancodeSet::One, &[0x1e]), Some(KeyEvent { key: A, event: Pressed, .. })));
+        kassert!(matches!(decode(ScancodeSet::Two, &[0x1c]), Some(KeyEvent { key: A, event: Pressed, .. })));
+        kassert!(matches!(decode(ScancodeSet::One, &[0x9e]), Some(KeyEvent { key: A, event: Released, .. })));
+        kassert!(matches!(
+            decode(ScancodeSet::Two, &[0xf0, 0x1c]),
+            Some(KeyEvent { key: A, event: Released, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn extended_prefix_applies_to_the_next_byte() -> Result<(), &'static str> {
+        kassert!(matches!(decode(ScancodeSet::One, &[0xe0, 0x48]), Some(KeyEvent { key: ArrowUp, .. })));
+        kassert!(matches!(
+            decode(ScancodeSet::Two, &[0xe0, 0xf0, 0x75]),
+            Some(KeyEvent {
+                key: ArrowUp,
+                event: Released,
+                ..
+            })
+        ));
+        kassert!(matches!(
+            decode(ScancodeSet::Two, &[0xe0, 0xf0, 0x75, 0x75]),
+            Some(KeyEvent {
+                key: KeypadN8,
+                event: Pressed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}
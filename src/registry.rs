@@ -0,0 +1,155 @@
+//! Fixed-capacity registries.
+//!
+//! Subsystems keep what is registered with them (commands, drivers, devices,
+//! ...) in a [`Registry`], which lives in a plain `static` so that registering
+//! never allocates. Entries are never moved, so the references handed out stay
+//! valid until the entry is [removed](Registry::remove).
+
+use core::{cell::UnsafeCell, fmt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterError {
+    /// The registry is full.
+    Full,
+    /// An entry with the same name already exists.
+    AlreadyRegistered,
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "too many entries registered"),
+            Self::AlreadyRegistered => write!(f, "already registered"),
+        }
+    }
+}
+
+pub struct Registry<T, const N: usize> {
+    slots: UnsafeCell<[Option<T>; N]>,
+}
+
+// SAFETY:
+// The kernel is single threaded, and registries are not used from interrupt handlers.
+unsafe impl<T, const N: usize> Sync for Registry<T, N> {}
+
+impl<T, const N: usize> Default for Registry<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Registry<T, N> {
+    const EMPTY: Option<T> = None;
+
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            slots: UnsafeCell::new([Self::EMPTY; N]),
+        }
+    }
+
+    fn slot(&self, index: usize) -> *mut Option<T> {
+        assert!(index < N, "registry index out of bounds");
+        self.slots.get().cast::<Option<T>>().wrapping_add(index)
+    }
+
+    /// Returns the entry at `index`.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= N {
+            return None;
+        }
+        // SAFETY:
+        // Slots are only written through `register`, which fills empty ones, and through the
+        // unsafe `get_mut` and `remove`, whose callers guarantee no reference to them is alive.
+        unsafe { (*self.slot(index)).as_ref() }
+    }
+
+    /// Returns the entry at `index`, to be modified in place.
+    ///
+    /// # Safety
+    /// No other reference to the entry at `index` may be alive while the one returned is.
+    #[must_use]
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn get_mut(&self, index: usize) -> Option<&mut T> {
+        if index >= N {
+            return None;
+        }
+        // SAFETY:
+        // The caller guarantees the entry is not borrowed elsewhere.
+        unsafe { (*self.slot(index)).as_mut() }
+    }
+
+    /// Stores `value` in the first free slot, unless `is_duplicate` returns `true` for one of
+    /// the entries, and returns it.
+    ///
+    /// # Errors
+    /// This function returns an error if `is_duplicate` matched an entry, or if the registry is
+    /// full.
+    pub fn register(&self, value: T, is_duplicate: impl Fn(&T) -> bool) -> Result<&T, RegisterError> {
+        if self.iter().any(is_duplicate) {
+            return Err(RegisterError::AlreadyRegistered);
+        }
+
+        let index = (0..N).find(|&index| self.get(index).is_none()).ok_or(RegisterError::Full)?;
+        // SAFETY:
+        // The slot is empty, so nothing borrows it.
+        Ok(unsafe { (*self.slot(index)).insert(value) })
+    }
+
+    /// Removes the entry at `index`, and returns it.
+    ///
+    /// # Safety
+    /// No reference to the entry at `index` may be alive.
+    pub unsafe fn remove(&self, index: usize) -> Option<T> {
+        if index >= N {
+            return None;
+        }
+        // SAFETY:
+        // The caller guarantees the entry is not borrowed.
+        unsafe { (*self.slot(index)).take() }
+    }
+
+    /// Returns the entries along with their index, in slot order.
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &T)> {
+        (0..N).filter_map(|index| Some((index, self.get(index)?)))
+    }
+
+    /// Returns the entries, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries().map(|(_, entry)| entry)
+    }
+
+    #[must_use]
+    pub fn find(&self, predicate: impl Fn(&T) -> bool) -> Option<&T> {
+        self.iter().find(|entry| predicate(entry))
+    }
+
+    #[must_use]
+    pub fn position(&self, predicate: impl Fn(&T) -> bool) -> Option<usize> {
+        self.entries().find(|(_, entry)| predicate(entry)).map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn entries_are_registered_once() -> Result<(), &'static str> {
+        let registry = Registry::<u32, 2>::new();
+
+        kassert_eq!(registry.register(1, |e| *e == 1).copied(), Ok(1));
+        kassert_eq!(registry.register(1, |e| *e == 1).copied(), Err(RegisterError::AlreadyRegistered));
+        kassert_eq!(registry.register(2, |e| *e == 2).copied(), Ok(2));
+        kassert_eq!(registry.register(3, |e| *e == 3).copied(), Err(RegisterError::Full));
+
+        kassert_eq!(unsafe { registry.remove(0) }, Some(1));
+        kassert_eq!(registry.register(3, |e| *e == 3).copied(), Ok(3));
+        kassert!(registry.iter().copied().eq([3, 2]));
+        kassert_eq!(registry.position(|e| *e == 2), Some(1));
+
+        Ok(())
+    }
+}
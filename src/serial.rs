@@ -1,5 +1,14 @@
 use core::{fmt, sync::atomic::AtomicBool};
 
+use crate::{
+    arch::x86::{idt::InterruptRegisters, interrupts::irq},
+    input::{self, Consumer, InputError, InputEvent},
+    keyboard::{
+        ModifierState,
+        layout::{Character, CharacterFull},
+    },
+};
+
 enum SendError {
     WouldBlock,
 }
@@ -50,6 +59,15 @@ impl SerialPort {
         unsafe { Port::new(self.port_line_status()).read() }
     }
 
+    fn data_ready(&self) -> bool {
+        self.line_status() & 1 == 1
+    }
+
+    fn receive_raw(&mut self) -> u8 {
+        use crate::port::Port;
+        unsafe { Port::new(self.port_data()).read() }
+    }
+
     pub fn send(&mut self, data: u8) {
         match data {
             8 | 0x7f => {
@@ -107,14 +125,189 @@ impl fmt::Write for SerialPort {
 
 pub static mut SERIAL1: SerialPort = unsafe { SerialPort::new(0x3f8) };
 
+/// IRQ line of COM1.
+const SERIAL1_IRQ: u8 = 4;
+
+#[allow(static_mut_refs)]
+fn ensure_initialized() {
+    if !SERIAL_INITIALIZED.swap(true, core::sync::atomic::Ordering::Relaxed) {
+        unsafe { SERIAL1.init() };
+    }
+}
+
+/// IRQ4
+#[allow(static_mut_refs)]
+extern "C" fn serial_interrupt_handler(_regs: &InterruptRegisters) {
+    // SAFETY
+    // This is a global variable which will be available throughout
+    // the whole runtime of the program.
+    unsafe {
+        while SERIAL1.data_ready() {
+            input::publish(InputEvent::Serial(SERIAL1.receive_raw()));
+        }
+    }
+}
+
+/// Starts publishing the bytes received on COM1 as [`InputEvent::Serial`] events.
+pub fn init_rx() {
+    ensure_initialized();
+
+    irq::install_handler(u32::from(SERIAL1_IRQ), serial_interrupt_handler);
+
+    irq::clear_mask(SERIAL1_IRQ);
+}
+
+#[derive(Clone, Copy, Debug)]
+enum EscapeState {
+    Ground,
+    /// Received `ESC`.
+    Escape,
+    /// Received `ESC [`, waiting for the final byte.
+    Csi,
+}
+
+/// Reads the bytes received on COM1 and decodes them into [`Character`]s, the
+/// same way [`Keyboard`](crate::keyboard::Keyboard) does for PS/2 key events.
+#[derive(Debug)]
+pub struct SerialInput {
+    input: Consumer,
+    decoder: Decoder,
+}
+
+impl SerialInput {
+    /// # Errors
+    /// This function returns an error if no input queue is left for the serial port.
+    pub fn new() -> Result<Self, InputError> {
+        Ok(Self {
+            input: input::subscribe(|e| matches!(e, InputEvent::Serial(_)))?,
+            decoder: Decoder::new(),
+        })
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Character> {
+        let c = self.next_full()?;
+        Some(c.character)
+    }
+
+    pub fn next_full(&mut self) -> Option<CharacterFull> {
+        while let Some(byte) = self.decoder.pending.take().or_else(|| self.read_byte()) {
+            if let Some(c) = self.decoder.decode(byte) {
+                return Some(c);
+            }
+        }
+        None
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        while let Some(event) = self.input.read() {
+            if let InputEvent::Serial(byte) = event {
+                return Some(byte);
+            }
+        }
+        None
+    }
+}
+
+/// Decodes the bytes received on COM1.
+///
+/// Terminals send the arrow keys as `ESC [ A` through `ESC [ D`, Home and End
+/// as `ESC [ H`/`ESC [ F` or `ESC [ 1 ~`/`ESC [ 4 ~`, and Delete as
+/// `ESC [ 3 ~`, and PageUp and PageDown as `ESC [ 5 ~`/`ESC [ 6 ~`. Other
+/// escape sequences are dropped. Control bytes `0x01` to
+/// `0x1a` are decoded as Ctrl and the matching letter.
+#[derive(Clone, Copy, Debug)]
+struct Decoder {
+    state: EscapeState,
+    /// Numeric parameter of the escape sequence being decoded.
+    param: u8,
+    /// Byte to process before reading a new one, used when a lone `ESC` is followed by a
+    /// regular character.
+    pending: Option<u8>,
+    last_was_cr: bool,
+}
+
+impl Decoder {
+    const fn new() -> Self {
+        Self {
+            state: EscapeState::Ground,
+            param: 0,
+            pending: None,
+            last_was_cr: false,
+        }
+    }
+
+    fn decode(&mut self, byte: u8) -> Option<CharacterFull> {
+        let last_was_cr = core::mem::replace(&mut self.last_was_cr, byte == b'\r');
+
+        let character = match self.state {
+            EscapeState::Ground => match byte {
+                0x1b => {
+                    self.state = EscapeState::Escape;
+                    None
+                }
+                b'\n' if last_was_cr => None,
+                b'\r' | b'\n' => Some(Character::Enter),
+                0x08 | 0x7f => Some(Character::Backspace),
+                b'\t' => Some(Character::Tab),
+                0x01..=0x1a => {
+                    return Some(CharacterFull::new(Character::Char((b'a' + byte - 1) as char), ModifierState::ctrl()));
+                }
+                0x20..0x7f => Some(Character::Char(byte as char)),
+                _ => None,
+            },
+            EscapeState::Escape => {
+                if byte == b'[' {
+                    self.state = EscapeState::Csi;
+                    self.param = 0;
+                    None
+                } else {
+                    self.state = EscapeState::Ground;
+                    self.pending = Some(byte);
+                    Some(Character::Escape)
+                }
+            }
+            EscapeState::Csi => match byte {
+                b'0'..=b'9' => {
+                    self.param = self.param.saturating_mul(10).saturating_add(byte - b'0');
+                    None
+                }
+                0x40..=0x7e => {
+                    self.state = EscapeState::Ground;
+                    match (byte, self.param) {
+                        (b'A', _) => Some(Character::ArrowUp),
+                        (b'B', _) => Some(Character::ArrowDown),
+                        (b'C', _) => Some(Character::ArrowRight),
+                        (b'D', _) => Some(Character::ArrowLeft),
+                        (b'H', _) | (b'~', 1 | 7) => Some(Character::Home),
+                        (b'F', _) | (b'~', 4 | 8) => Some(Character::End),
+                        (b'~', 3) => Some(Character::Delete),
+                        (b'~', 5) => Some(Character::PageUp),
+                        (b'~', 6) => Some(Character::PageDown),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+        };
+
+        character.map(|c| CharacterFull::new(c, ModifierState::default()))
+    }
+}
+
+/// Sends a single byte on COM1.
+#[allow(static_mut_refs)]
+pub fn write_byte(byte: u8) {
+    ensure_initialized();
+    unsafe { SERIAL1.send(byte) };
+}
+
 #[doc(hidden)]
 #[allow(static_mut_refs)]
 pub fn print_internal(args: ::core::fmt::Arguments) {
     use core::fmt::Write;
+    ensure_initialized();
     unsafe {
-        if !SERIAL_INITIALIZED.swap(true, core::sync::atomic::Ordering::Relaxed) {
-            SERIAL1.init();
-        }
         let _ = SERIAL1.write_fmt(args);
     }
 }
@@ -132,3 +325,77 @@ macro_rules! serial_println {
     ($fmt:expr) => ($crate::serial_print!(concat!($fmt, "\n")));
     ($fmt:expr, $($arg:tt)*) => ($crate::serial_print!(concat!($fmt, "\n"), $($arg)*));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert;
+
+    fn decode_all(input: &mut Decoder, bytes: &[u8]) -> [Option<Character>; 4] {
+        let mut out = [None; 4];
+        let mut i = 0;
+        for byte in bytes {
+            let mut next = Some(*byte);
+            while let Some(b) = next.take() {
+                if let Some(c) = input.decode(b) {
+                    out[i] = Some(c.character);
+                    i += 1;
+                }
+                next = input.pending.take();
+            }
+        }
+        out
+    }
+
+    #[test_case]
+    fn arrows_are_decoded() -> Result<(), &'static str> {
+        let out = decode_all(&mut Decoder::new(), b"\x1b[A\x1b[D");
+
+        kassert!(matches!(out, [Some(Character::ArrowUp), Some(Character::ArrowLeft), None, None]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn control_bytes_are_ctrl_letters() -> Result<(), &'static str> {
+        let mut input = Decoder::new();
+
+        let c = input.decode(0x01).ok_or("Expected a character")?;
+        kassert!(matches!(c.character, Character::Char('a')));
+        kassert!(c.modifiers.ctrl_pressed());
+
+        let c = input.decode(b'a').ok_or("Expected a character")?;
+        kassert!(!c.modifiers.ctrl_pressed());
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn crlf_is_a_single_enter() -> Result<(), &'static str> {
+        let out = decode_all(&mut Decoder::new(), b"a\r\nb");
+
+        kassert!(matches!(
+            out,
+            [Some(Character::Char('a')), Some(Character::Enter), Some(Character::Char('b')), None]
+        ));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn lone_escape_keeps_next_byte() -> Result<(), &'static str> {
+        let out = decode_all(&mut Decoder::new(), b"\x1bx\x1b[3~\x7f");
+
+        kassert!(matches!(
+            out,
+            [
+                Some(Character::Escape),
+                Some(Character::Char('x')),
+                Some(Character::Delete),
+                Some(Character::Backspace)
+            ]
+        ));
+
+        Ok(())
+    }
+}
@@ -1,104 +1,510 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::missing_panics_doc)]
 
+use core::{cmp::Ordering, fmt::Write};
+
 use crate::{
     boot::{STACK, STACK_SIZE},
     hlt,
-    keyboard::{Keyboard, layout::Character as Char},
+    input::{self, Consumer, InputEvent},
+    keyboard::{
+        self, Keyboard,
+        layout::{Character as Char, CharacterFull, LAYOUTS, Layout},
+    },
+    log::{self, Level},
+    port::Port,
     printk, printkln,
+    ps2::{self, MouseEvent},
     qemu::{ExitCode, exit},
+    serial::SerialInput,
     serial_println,
+    shell::{
+        argv::Argv,
+        command::{Command, ExitStatus, Io, SUCCESS, USAGE},
+        completion::Completions,
+        filters::print_bytes,
+        history::{LINE_SIZE, Line, history},
+    },
     terminal::{
         self, Screen,
+        console::{self, CONSOLE_COUNT, LOG_CONSOLE},
         cursor::Cursor,
         entry::Entry,
         vga::{self, BUFFER_HEIGHT, Buffer},
     },
 };
 
+pub mod argv;
+pub mod command;
+pub mod completion;
+pub mod filters;
+pub mod history;
+pub mod memory;
+pub mod script;
+
 type Character = u8;
 
-pub struct Shell<'a> {
-    screen: &'a mut Screen,
+/// Where the shell reads its input from.
+pub enum Input {
+    Keyboard(Keyboard),
+    /// Reads from COM1, and mirrors the screen's output to it.
+    Serial(SerialInput),
+}
+
+impl Input {
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<CharacterFull> {
+        match self {
+            Self::Keyboard(keyboard) => keyboard.next_full(),
+            Self::Serial(serial) => serial.next_full(),
+        }
+    }
+}
+
+/// Runs a shell on every console but the log one, and feeds the input to the
+/// shell of the active console. Alt+F1 to Alt+F6 switch between consoles, and
+/// the mouse wheel scrolls the active one.
+pub fn launch(mut input: Input) -> ! {
+    let serial_echo = matches!(input, Input::Serial(_));
+    let mut shells: [Shell; LOG_CONSOLE] = core::array::from_fn(|i| Shell::default(i, serial_echo));
+
+    for shell in &shells {
+        shell.screen(|screen| screen.write("sh> "));
+    }
+    shells[console::active()].flush();
+
+    // Scrolling with the wheel is a convenience, the shell works without it.
+    let mut mouse = input::subscribe(|e| matches!(e, InputEvent::Mouse(MouseEvent::Wheel(_)))).ok();
+
+    loop {
+        // Halt CPU until next interrupt to prevent busy waiting.
+        hlt!();
+
+        while let Some(key) = input.next() {
+            if let Some(index) = console_switch(key)
+                && index != console::active()
+            {
+                let _ = console::switch(index);
+                if let Some(shell) = shells.get_mut(index) {
+                    shell.flush();
+                }
+                continue;
+            }
+
+            if let Some(shell) = shells.get_mut(console::active()) {
+                shell.handle(key);
+            }
+        }
+
+        while let Some(event) = mouse.as_mut().and_then(Consumer::read) {
+            if let InputEvent::Mouse(MouseEvent::Wheel(dz)) = event
+                && let Some(shell) = shells.get_mut(console::active())
+            {
+                shell.wheel(isize::from(dz) * WHEEL_STEP);
+            }
+        }
+    }
+}
+
+/// Returns the console selected by an Alt+Fn combination.
+fn console_switch(key: CharacterFull) -> Option<usize> {
+    if !key.modifiers.alt_pressed() {
+        return None;
+    }
+
+    let index = match key.character {
+        Char::F1 => 0,
+        Char::F2 => 1,
+        Char::F3 => 2,
+        Char::F4 => 3,
+        Char::F5 => 4,
+        Char::F6 => 5,
+        _ => return None,
+    };
+
+    (index < CONSOLE_COUNT).then_some(index)
+}
+
+/// Number of rows scrolled by Shift+PageUp and Shift+PageDown.
+const SCROLL_STEP: usize = BUFFER_HEIGHT / 2;
+
+/// Number of rows scrolled by a notch of the mouse wheel.
+const WHEEL_STEP: isize = 3;
+
+/// State of an incremental reverse search, started with Ctrl+R.
+struct Search {
+    query: Line,
+    /// Age of the most recent command matching `query`.
+    found: Option<usize>,
+    /// Prompt restored if the search is cancelled.
+    original: Line,
+}
+
+pub struct Shell {
+    /// Console the shell runs on, whose screen is only borrowed while it is being drawn to.
+    console: usize,
     prompt: Prompt,
     rows_scrolled_up: usize,
-    keyboard: Keyboard,
+    /// Position of the screen's cursor, relative to the start of the input after `sh> `.
+    screen_cursor: usize,
+    /// Age of the command recalled with Up/Down, if any.
+    history_age: Option<usize>,
+    /// Prompt being typed before recalling commands, restored when going back down.
+    draft: Line,
+    search: Option<Search>,
+    /// Exit status of the last command.
+    status: ExitStatus,
 }
 
-impl<'a> Shell<'a> {
-    pub fn default(screen: &'a mut Screen, keyboard: Keyboard) -> Self {
+impl Shell {
+    #[must_use]
+    pub fn default(console: usize, serial_echo: bool) -> Self {
+        console::with_screen(console, |screen| screen.serial_echo = serial_echo);
+
         Self {
-            screen,
+            console,
             prompt: Prompt::default(),
             rows_scrolled_up: 0,
-            keyboard,
+            screen_cursor: 0,
+            history_age: None,
+            draft: Line::new(),
+            search: None,
+            status: SUCCESS,
         }
     }
-    pub fn launch(&mut self) {
-        loop {
-            self.screen.write("sh> ");
+
+    /// Handles a key typed on this shell's console.
+    pub fn handle(&mut self, key: CharacterFull) {
+        let (ctrl, shift) = (key.modifiers.ctrl_pressed(), key.modifiers.shift_pressed());
+
+        match key.character {
+            Char::PageUp | Char::PageDown if shift => {}
+            _ => self.rows_scrolled_up = 0,
+        }
+
+        if self.search.is_some() {
+            self.handle_search(key);
             self.flush();
+            return;
+        }
+
+        let (before, len_before) = (self.prompt.cursor, self.prompt.len);
+        let mut replaced = false;
+        match key.character {
+            Char::Enter => {
+                self.execute();
+                self.flush();
+                return;
+            }
+            Char::Char(c) if ctrl => match c.to_ascii_lowercase() {
+                'a' => self.prompt.home(),
+                'e' => self.prompt.end(),
+                'k' => self.prompt.kill_to_end(),
+                'u' => self.prompt.kill_to_start(),
+                'w' => self.prompt.delete_word(),
+                'r' => self.start_search(),
+                'l' => {
+                    self.screen(|screen| screen.write("\x1b[H\x1b[2Jsh> "));
+                    self.screen_cursor = 0;
+                    replaced = true;
+                }
+                _ => {}
+            },
+            Char::Char(c) => {
+                let _ = self.prompt.insert_char(c);
+            }
+            Char::Backspace => self.prompt.backspace(),
+            Char::Delete => self.prompt.delete(),
+            Char::ArrowLeft => self.prompt.left(),
+            Char::ArrowRight => self.prompt.right(),
+            Char::Home => self.prompt.home(),
+            Char::End => self.prompt.end(),
+            Char::Tab => self.complete(),
+            Char::ArrowUp => {
+                let age = self.history_age.map_or(0, |age| age + 1);
+                if age < history().len() {
+                    self.recall(Some(age));
+                    replaced = true;
+                }
+            }
+            Char::ArrowDown => {
+                if let Some(age) = self.history_age {
+                    self.recall(age.checked_sub(1));
+                    replaced = true;
+                }
+            }
+            Char::PageUp if shift => self.scroll_up(SCROLL_STEP),
+            Char::PageDown if shift => self.scroll_down(SCROLL_STEP),
+            _ => {}
+        };
+
+        if replaced {
+            self.redraw(0);
+        } else if self.search.is_none() && (self.prompt.cursor != before || self.prompt.len != len_before) {
+            self.redraw(before.min(self.prompt.cursor));
+        }
+        self.flush();
+    }
+
+    /// Scrolls the scrollback by `rows` of the mouse wheel, upwards if negative.
+    pub fn wheel(&mut self, rows: isize) {
+        if rows < 0 {
+            self.scroll_up(rows.unsigned_abs());
+        } else {
+            self.scroll_down(rows.unsigned_abs());
+        }
+        self.flush();
+    }
+
+    fn scroll_up(&mut self, rows: usize) {
+        for _ in 0..rows {
+            let wanted = self.rows_scrolled_up + BUFFER_HEIGHT + 1;
+            if self.screen(|screen| screen.lines().rev().take(wanted).count()) > self.rows_scrolled_up + vga::BUFFER_HEIGHT {
+                self.rows_scrolled_up += 1;
+            }
+        }
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        self.rows_scrolled_up = self.rows_scrolled_up.saturating_sub(rows);
+    }
 
-            loop {
-                // Halt CPU until next interrupt to prevent busy waiting.
-                hlt!();
+    fn execute(&mut self) {
+        self.move_to(self.prompt.len);
+        self.screen(|screen| screen.write_byte(b'\n'));
+        Cursor::hide();
 
-                if let Some(key) = self.keyboard.next() {
-                    match key {
-                        Char::ArrowUp | Char::ArrowDown => {}
-                        _ => self.rows_scrolled_up = 0,
+        history().push(&self.prompt.entries[..self.prompt.len]);
+        self.history_age = None;
+
+        self.status = self.prompt.execute(self.console, self.status);
+        self.prompt.clear();
+        self.screen(|screen| screen.write("sh> "));
+        self.screen_cursor = 0;
+    }
+
+    /// Replaces the prompt with the command of the given age, or with the draft if `None`.
+    fn recall(&mut self, age: Option<usize>) {
+        if self.history_age.is_none() {
+            self.draft = Line::from_bytes(&self.prompt.entries[..self.prompt.len]);
+        }
+        self.history_age = age;
+
+        match age.and_then(|age| history().get(age)) {
+            Some(command) => self.prompt.set(command),
+            None => self.prompt.set(self.draft.as_bytes()),
+        }
+    }
+
+    /// Completes the word before the cursor, or lists the candidates if it cannot be
+    /// completed any further.
+    fn complete(&mut self) {
+        let cursor = self.prompt.cursor;
+        let command_start = script::last_command_start(&self.prompt.entries[..cursor]);
+        let start = self.prompt.entries[command_start..cursor]
+            .iter()
+            .rposition(|c| *c == b' ')
+            .map_or(command_start, |i| command_start + i + 1);
+        if cursor - start > LINE_SIZE {
+            return;
+        }
+        let word = Line::from_bytes(&self.prompt.entries[start..cursor]);
+        let before = &self.prompt.entries[command_start..start];
+
+        let mut completions = Completions::new(word.as_bytes());
+        if before.iter().all(|c| *c == b' ') {
+            for command in command::all() {
+                completions.add(command.name.as_bytes());
+            }
+        } else {
+            let name = before.split(|c| *c == b' ').find(|w| !w.is_empty()).unwrap_or_default();
+            if let Some(complete) = command::find(name).and_then(|c| c.complete) {
+                complete(before, &mut completions);
+            }
+        }
+
+        let common = completions.common_prefix();
+        if completions.len() == 1 {
+            for c in common[word.as_bytes().len()..].iter().chain(b" ") {
+                let _ = self.prompt.insert(*c);
+            }
+        } else if common.len() > word.as_bytes().len() {
+            for c in &common[word.as_bytes().len()..] {
+                let _ = self.prompt.insert(*c);
+            }
+        } else if !completions.is_empty() {
+            self.move_to(self.prompt.len);
+            self.screen(|screen| {
+                screen.write_byte(b'\n');
+                for candidate in completions.iter() {
+                    for c in candidate.iter().chain(b"  ") {
+                        screen.write_byte(*c);
                     }
-                    match key {
-                        Char::Enter => {
-                            self.screen.push(Entry::new(b'\n'));
-                            Cursor::hide();
-                            if let Err(e) = self.prompt.execute(self.screen) {
-                                printkln!("{}", e);
-                            }
-                            self.prompt.clear();
-                            break;
-                        }
-                        Char::Backspace => {
-                            if self.prompt.len != 0 {
-                                self.screen.remove_last();
-                                self.prompt.entries[self.prompt.len - 1] = b' ';
-                                self.prompt.len -= 1;
-                            }
-                        }
-                        Char::ArrowUp => {
-                            if self.screen.lines().rev().take(self.rows_scrolled_up + BUFFER_HEIGHT + 1).count() > self.rows_scrolled_up + vga::BUFFER_HEIGHT {
-                                self.rows_scrolled_up += 1;
-                            }
-                        }
-                        Char::ArrowDown => {
-                            if self.rows_scrolled_up > 0 {
-                                self.rows_scrolled_up -= 1;
-                            }
-                        }
-                        Char::Char(c) => {
-                            let _ = Self::push(self, c as u8);
-                        }
-                        _ => {}
-                    };
-                    self.flush();
                 }
+                screen.write("\nsh> ");
+            });
+            self.screen_cursor = 0;
+            self.redraw(0);
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.search = Some(Search {
+            query: Line::new(),
+            found: None,
+            original: Line::from_bytes(&self.prompt.entries[..self.prompt.len]),
+        });
+        self.show_search();
+    }
+
+    fn handle_search(&mut self, key: CharacterFull) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        match key.character {
+            Char::Char(c) if key.modifiers.ctrl_pressed() && c.eq_ignore_ascii_case(&'r') => {
+                let from = search.found.map_or(0, |age| age + 1);
+                if let Some(age) = history().search(search.query.as_bytes(), from) {
+                    search.found = Some(age);
+                }
+            }
+            Char::Char(c) if key.modifiers.ctrl_pressed() && c.eq_ignore_ascii_case(&'g') => {
+                let original = search.original;
+                self.end_search(original.as_bytes());
+                return;
+            }
+            Char::Escape => {
+                let original = search.original;
+                self.end_search(original.as_bytes());
+                return;
+            }
+            Char::Char(c) if !key.modifiers.ctrl_pressed() => {
+                if c.encode_utf8(&mut [0; 4]).bytes().all(|b| search.query.push(b)) {
+                    search.found = history().search(search.query.as_bytes(), search.found.unwrap_or(0));
+                }
+            }
+            Char::Backspace => {
+                while search.query.pop().is_some_and(is_continuation) {}
+                search.found = history().search(search.query.as_bytes(), 0);
+            }
+            Char::Enter => {
+                self.accept_search();
+                self.execute();
+                return;
+            }
+            _ => {
+                self.accept_search();
+                return;
             }
         }
+
+        self.show_search();
     }
 
-    fn push(&mut self, c: Character) -> Result<(), PromptPushError> {
-        self.prompt.push(c)?;
-        self.screen.push(Entry::new(c));
-        Ok(())
+    /// Leaves the search with the matching command in the prompt, or the original one if
+    /// nothing matched.
+    fn accept_search(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        let original = search.original;
+        let line = search.found.and_then(|age| history().get(age)).unwrap_or(original.as_bytes());
+        self.end_search(line);
+    }
+
+    fn end_search(&mut self, line: &[u8]) {
+        self.search = None;
+        self.history_age = None;
+        self.prompt.set(line);
+        self.redraw(0);
+    }
+
+    /// Shows the search query and the matching command in place of the input.
+    fn show_search(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let query = search.query;
+        let found = search.found.and_then(|age| history().get(age));
+        let label = if found.is_none() && !query.as_bytes().is_empty() {
+            "failed reverse-i-search"
+        } else {
+            "reverse-i-search"
+        };
+
+        self.move_to(0);
+        self.screen(|screen| {
+            let _ = write!(screen, "({label})`");
+            for c in query.as_bytes().iter().chain(b"': ").chain(found.unwrap_or_default()) {
+                screen.write_byte(*c);
+            }
+            screen.write("\x1b[K");
+        });
+
+        self.screen_cursor = label.len() + 3 + query.as_bytes().len() + 3 + found.map_or(0, <[u8]>::len);
+    }
+
+    /// Rewrites the input from `from` to its end, and puts the cursor back where the prompt has it.
+    fn redraw(&mut self, from: usize) {
+        self.move_to(from);
+
+        self.screen(|screen| {
+            for c in &self.prompt.entries[from..self.prompt.len] {
+                screen.write_byte(*c);
+            }
+            screen.write("\x1b[K");
+        });
+        self.screen_cursor = self.prompt.len;
+
+        self.move_to(self.prompt.cursor);
+    }
+
+    /// Moves the screen's cursor to `position` in the input.
+    fn move_to(&mut self, position: usize) {
+        let (from, to) = (position.min(self.screen_cursor), position.max(self.screen_cursor));
+        // Characters spanning several bytes take a single column.
+        let columns = self
+            .prompt
+            .entries
+            .get(from..to)
+            .map_or(to - from, |bytes| bytes.iter().filter(|c| !is_continuation(**c)).count());
+
+        let direction = match position.cmp(&self.screen_cursor) {
+            Ordering::Equal => None,
+            Ordering::Greater => Some('C'),
+            Ordering::Less => Some('D'),
+        };
+        if let Some(direction) = direction {
+            self.screen(|screen| {
+                let _ = write!(screen, "\x1b[{columns}{direction}");
+            });
+        }
+        self.screen_cursor = position;
     }
 
     pub fn flush(&mut self) {
-        // This pushing and remove_last is so that the cursor which is ON the last
-        // element is one after the last element
-        self.screen.push(Entry::new(b' '));
-        let b = Buffer::from_screen(self.screen, self.rows_scrolled_up);
-        self.screen.remove_last();
-        b.flush();
+        let rows_scrolled_up = self.rows_scrolled_up;
+        self.screen(|screen| {
+            // The cursor placeholder below is not part of the output.
+            let serial_echo = core::mem::replace(&mut screen.serial_echo, false);
+
+            // This pushing and remove_last is so that the cursor which is ON the last
+            // element is one after the last element
+            screen.push(Entry::new(b' '));
+            let b = Buffer::from_screen(screen, rows_scrolled_up);
+            screen.remove_last();
+            b.flush();
+
+            screen.serial_echo = serial_echo;
+        });
+    }
+
+    /// Runs `f` on the screen of the shell's console. Nothing else holds it while the shell
+    /// runs, so the default value is only returned in theory.
+    fn screen<R: Default>(&self, f: impl FnOnce(&mut Screen) -> R) -> R {
+        console::with_screen(self.console, f).unwrap_or_default()
     }
 }
 
@@ -110,6 +516,8 @@ const PROMPT_SIZE: usize = (terminal::screen::BUFFER_SIZE * PROMPT_SIZE_PERCENTA
 pub struct Prompt {
     entries: [Character; PROMPT_SIZE],
     len: usize,
+    /// Position of the cursor in `entries`, between 0 and `len`.
+    cursor: usize,
 }
 
 impl Default for Prompt {
@@ -117,50 +525,134 @@ impl Default for Prompt {
         Self {
             entries: [b' '; PROMPT_SIZE],
             len: 0,
+            cursor: 0,
         }
     }
 }
 
 impl Prompt {
-    /// Executes the command contained in the prompt buffer
+    /// Executes the commands contained in the prompt buffer, `status` being the exit status
+    /// of the previous line, and returns the exit status of the last one.
     ///
-    /// # Errors
-    /// Returns an error if the command is not found in the
-    /// available commands
-    pub fn execute(&self, screen: &mut Screen) -> Result<(), &'static str> {
-        for command in COMMANDS {
-            let cmd = &self.entries[..command.name.len()];
-            let args = if command.name.len() >= self.len {
-                &[]
-            } else {
-                &self.entries[(command.name.len() + 1)..self.len]
-            };
-            let cmd_has_trailing_space = self.entries[command.name.len()] == b' ';
-
-            if cmd == command.name.as_bytes() && cmd_has_trailing_space {
-                (command.func)(args, screen);
-                return Ok(());
-            }
-        }
-        Err("command not found - run `help` for available commands")
+    /// Syntax errors and unknown commands are reported on the console.
+    #[must_use]
+    pub fn execute(&self, console: usize, status: ExitStatus) -> ExitStatus {
+        script::run(&self.entries[..self.len], console, status)
     }
 
-    /// Pushes an element to the prompt
+    /// Inserts an element at the cursor
     ///
     /// # Errors
     /// Returns an error if the prompt buffer is full
-    pub fn push(&mut self, c: Character) -> Result<(), PromptPushError> {
+    pub fn insert(&mut self, c: Character) -> Result<(), PromptPushError> {
         if self.len >= self.entries.len() {
             Err(PromptPushError::PromptFull)
         } else {
-            self.entries[self.len] = c;
+            self.entries.copy_within(self.cursor..self.len, self.cursor + 1);
+            self.entries[self.cursor] = c;
             self.len += 1;
+            self.cursor += 1;
             Ok(())
         }
     }
 
+    /// Inserts the UTF-8 encoding of `c` at the cursor
+    ///
+    /// # Errors
+    /// Returns an error if the prompt buffer cannot hold the whole character
+    pub fn insert_char(&mut self, c: char) -> Result<(), PromptPushError> {
+        let mut buffer = [0; 4];
+        let encoded = c.encode_utf8(&mut buffer);
+        if self.len + encoded.len() > self.entries.len() {
+            return Err(PromptPushError::PromptFull);
+        }
+
+        for byte in encoded.bytes() {
+            self.insert(byte)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the start of the character before `position`.
+    fn previous_char(&self, position: usize) -> usize {
+        (0..position).rev().find(|i| !is_continuation(self.entries[*i])).unwrap_or(0)
+    }
+
+    /// Returns the end of the character at `position`.
+    fn next_char(&self, position: usize) -> usize {
+        (position + 1..self.len).find(|i| !is_continuation(self.entries[*i])).unwrap_or(self.len)
+    }
+
+    /// Removes the elements in `start..end` and moves the cursor to `start`.
+    fn remove(&mut self, start: usize, end: usize) {
+        self.entries.copy_within(end..self.len, start);
+        self.entries[self.len - (end - start)..self.len].fill(b' ');
+        self.len -= end - start;
+        self.cursor = start;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.remove(self.previous_char(self.cursor), self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.len {
+            self.remove(self.cursor, self.next_char(self.cursor));
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.previous_char(self.cursor);
+    }
+
+    pub fn right(&mut self) {
+        if self.cursor < self.len {
+            self.cursor = self.next_char(self.cursor);
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.len;
+    }
+
+    pub fn kill_to_end(&mut self) {
+        self.remove(self.cursor, self.len);
+    }
+
+    pub fn kill_to_start(&mut self) {
+        self.remove(0, self.cursor);
+    }
+
+    /// Removes the word before the cursor, along with the spaces between them.
+    pub fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.entries[start - 1] == b' ' {
+            start -= 1;
+        }
+        while start > 0 && self.entries[start - 1] != b' ' {
+            start -= 1;
+        }
+        self.remove(start, self.cursor);
+    }
+
+    /// Replaces the content of the prompt with `line`, and moves the cursor to its end.
+    pub fn set(&mut self, line: &[u8]) {
+        self.clear();
+        let len = line.len().min(PROMPT_SIZE);
+        self.entries[..len].copy_from_slice(&line[..len]);
+        self.len = len;
+        self.cursor = len;
+    }
+
     pub fn clear(&mut self) {
         self.len = 0;
+        self.cursor = 0;
         self.entries = [b' '; PROMPT_SIZE];
     }
 }
@@ -168,69 +660,265 @@ impl Prompt {
 pub enum PromptPushError {
     PromptFull,
 }
-struct Command<'a> {
-    name: &'a str,
-    func: fn(args: &[u8], s: &mut Screen),
+
+/// Returns whether `byte` continues a UTF-8 character instead of starting one.
+const fn is_continuation(byte: u8) -> bool {
+    byte & 0xc0 == 0x80
 }
-const COMMANDS: &[Command] = &[
-    Command { name: "echo", func: echo_cmd },
+const BUILTINS: &[Command] = &[
+    Command {
+        name: "echo",
+        usage: "[args...]",
+        help: "echoes input to the console",
+        func: echo_cmd,
+        complete: None,
+    },
     Command {
         name: "clear",
+        usage: "",
+        help: "clears the screen",
         func: clear_cmd,
+        complete: None,
     },
     Command {
         name: "reboot",
+        usage: "",
+        help: "reboot the kernel",
         func: reboot_cmd,
+        complete: None,
     },
     Command {
         name: "prints",
+        usage: "",
+        help: "display the kernel stack from %esp to the top",
         func: prints_cmd,
+        complete: None,
+    },
+    Command {
+        name: "help",
+        usage: "",
+        help: "display this help message",
+        func: help_cmd,
+        complete: None,
     },
-    Command { name: "help", func: help_cmd },
     Command {
         name: "printsb",
+        usage: "",
+        help: "display the kernel stack boundaries",
         func: printsb_cmd,
+        complete: None,
+    },
+    Command {
+        name: "exit",
+        usage: "",
+        help: "exits the kernel",
+        func: exit_cmd,
+        complete: None,
     },
-    Command { name: "exit", func: exit_cmd },
     Command {
         name: "panic",
+        usage: "",
+        help: "panics",
         func: panic_cmd,
+        complete: None,
+    },
+    Command {
+        name: "dmesg",
+        usage: "[-l level]",
+        help: "display the kernel log, down to `level` (emerg..debug)",
+        func: dmesg_cmd,
+        complete: Some(dmesg_complete),
+    },
+    Command {
+        name: "history",
+        usage: "[-c]",
+        help: "list the previous commands, or clear them with -c",
+        func: history_cmd,
+        complete: Some(history_complete),
+    },
+    Command {
+        name: "grep",
+        usage: "[-v] [-i] pattern",
+        help: "print the piped lines containing `pattern`",
+        func: filters::grep_cmd,
+        complete: Some(filters::grep_complete),
+    },
+    Command {
+        name: "head",
+        usage: "[-n lines]",
+        help: "print the first piped lines",
+        func: filters::head_cmd,
+        complete: Some(filters::line_count_complete),
+    },
+    Command {
+        name: "tail",
+        usage: "[-n lines]",
+        help: "print the last piped lines",
+        func: filters::tail_cmd,
+        complete: Some(filters::line_count_complete),
+    },
+    Command {
+        name: "loadkeys",
+        usage: "[layout]",
+        help: "show or change the keyboard layout (qwerty, azerty, qwertz, dvorak)",
+        func: loadkeys_cmd,
+        complete: Some(loadkeys_complete),
+    },
+    Command {
+        name: "x",
+        usage: "<addr> [len]",
+        help: "hexdump `len` bytes of memory from `addr`",
+        func: memory::x_cmd,
+        complete: None,
+    },
+    Command {
+        name: "poke",
+        usage: "[-b|-h|-w] <addr> <value>",
+        help: "write a byte, half word or word (default) to memory",
+        func: memory::poke_cmd,
+        complete: Some(memory::poke_complete),
+    },
+    Command {
+        name: "vtop",
+        usage: "<addr>",
+        help: "show the page entries and physical address of `addr`",
+        func: memory::vtop_cmd,
+        complete: None,
     },
 ];
 
-fn panic_cmd(_args: &[u8], _s: &mut Screen) {
+fn panic_cmd(_argv: &Argv, _io: &mut Io) -> ExitStatus {
     panic!();
 }
 
-fn echo_cmd(args: &[u8], s: &mut Screen) {
-    for c in args.iter() {
-        s.push(Entry::new(*c));
+fn echo_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    for (i, arg) in argv.args().enumerate() {
+        if i != 0 {
+            printk!(" ");
+        }
+        print_bytes(arg);
     }
-    s.push(Entry::new(b'\n'));
+    printk!("\n");
+    SUCCESS
 }
 
-fn clear_cmd(_: &[u8], s: &mut Screen) {
-    s.head = 0;
-    s.len = 0;
+fn clear_cmd(_: &Argv, io: &mut Io) -> ExitStatus {
+    console::with_screen(io.console, |screen| screen.write("\x1b[H\x1b[2J"));
+    SUCCESS
 }
-fn reboot_cmd(_: &[u8], _: &mut Screen) {
-    unsafe { core::arch::asm!("out dx, al", in("dx") 0x64, in("al") 0xFEu8) };
+fn reboot_cmd(_: &Argv, _: &mut Io) -> ExitStatus {
+    // Pulses the CPU reset line through the PS/2 controller.
+    unsafe { Port::write_only(ps2::COMMAND_PORT).write(0xfe_u8) };
+    SUCCESS
 }
-fn exit_cmd(_: &[u8], _: &mut Screen) {
+fn exit_cmd(_: &Argv, _: &mut Io) -> ExitStatus {
     serial_println!("exited");
     unsafe { exit(ExitCode::Success) };
+    SUCCESS
 }
-#[allow(unused)]
-fn help_cmd(args: &[u8], s: &mut Screen) {
+
+/// Width of the name and usage column of `help`.
+const HELP_USAGE_WIDTH: usize = 21;
+
+fn help_cmd(_: &Argv, _: &mut Io) -> ExitStatus {
     printk!("\nAvailable commands:\n\n");
-    printk!("    echo:                echoes input to the console\n");
-    printk!("    clear:               clears the screen\n");
-    printk!("    reboot:              reboot the kernel\n");
-    printk!("    prints               display the kernel stack from %esp to the top\n");
-    printk!("    printsb              display the kernel stack boundaries\n");
-    printk!("    help                 display this help message\n\n");
-    printk!("    exit                 exits the kernel\n\n");
-    printk!("    panic                panics\n\n");
+    for command in command::all() {
+        let mut width = command.name.len();
+        printk!("    {}", command.name);
+        if !command.usage.is_empty() {
+            printk!(" {}", command.usage);
+            width += command.usage.len() + 1;
+        }
+        printk!("{:pad$}{}\n", "", command.help, pad = HELP_USAGE_WIDTH.saturating_sub(width).max(1));
+    }
+    printk!("\nAlt+F1..F5 switch between shells, Alt+F6 shows the kernel log.\n");
+    printk!("Up/Down recall previous commands, Ctrl+R searches them, Shift+PageUp/PageDown or the mouse wheel scroll.\n");
+    printk!("Tab completes commands and their arguments.\n");
+    printk!("Commands can be piped with `|`, and chained with `;`, `&&` and `||`.\n\n");
+    SUCCESS
+}
+
+fn dmesg_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+
+    let min_level = match (args.next(), args.next()) {
+        (None, _) => Level::Debug,
+        (Some(b"-l" | b"--level"), Some(name)) => {
+            let Some(level) = Level::from_name(name) else {
+                printkln!("dmesg: unknown level - expected one of emerg, alert, crit, err, warn, notice, info, debug");
+                return USAGE;
+            };
+            level
+        }
+        _ => {
+            printkln!("usage: dmesg [-l level]");
+            return USAGE;
+        }
+    };
+
+    for record in log::records().filter(|r| r.level <= min_level) {
+        printkln!("{}", record);
+    }
+    SUCCESS
+}
+
+fn dmesg_complete(before: &[u8], completions: &mut Completions) {
+    if let Some(b"-l" | b"--level") = before.split(|c| *c == b' ').rfind(|w| !w.is_empty()) {
+        for level in Level::ALL {
+            completions.add(level.name().as_bytes());
+        }
+    } else {
+        completions.add(b"-l");
+        completions.add(b"--level");
+    }
+}
+
+fn loadkeys_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+
+    match (args.next(), args.next()) {
+        (None, _) => printkln!("{}", keyboard::layout().name()),
+        (Some(name), None) => {
+            let Some(layout) = Layout::from_name(name) else {
+                printkln!("loadkeys: unknown layout - expected one of qwerty, azerty, qwertz, dvorak");
+                return USAGE;
+            };
+            keyboard::set_layout(layout);
+        }
+        _ => {
+            printkln!("usage: loadkeys [layout]");
+            return USAGE;
+        }
+    }
+    SUCCESS
+}
+
+fn loadkeys_complete(_before: &[u8], completions: &mut Completions) {
+    for layout in LAYOUTS {
+        completions.add(layout.name().as_bytes());
+    }
+}
+
+fn history_complete(_before: &[u8], completions: &mut Completions) {
+    completions.add(b"-c");
+}
+
+fn history_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    match argv.get(1) {
+        None => {
+            for (number, command) in history().iter() {
+                printk!("{:>5}  ", number);
+                print_bytes(command);
+                printk!("\n");
+            }
+        }
+        Some(b"-c") => history().clear(),
+        Some(_) => {
+            printkln!("usage: history [-c]");
+            return USAGE;
+        }
+    }
+    SUCCESS
 }
 
 fn get_stack_pointer() -> u32 {
@@ -245,8 +933,8 @@ fn get_stack_pointer() -> u32 {
     sp as u32
 }
 #[allow(static_mut_refs)]
-fn prints_cmd(args: &[u8], s: &mut Screen) {
-    printsb_cmd(args, s);
+fn prints_cmd(argv: &Argv, io: &mut Io) -> ExitStatus {
+    printsb_cmd(argv, io);
     let sp_addr = get_stack_pointer();
     let st = unsafe { (STACK.as_ptr() as usize + STACK_SIZE) as *const u8 as u32 };
     let mut row: [u8; 16];
@@ -258,13 +946,15 @@ fn prints_cmd(args: &[u8], s: &mut Screen) {
         row = unsafe { *(ptr.cast::<[u8; 16]>()) };
         dump_row(row, ptr);
     }
+    SUCCESS
 }
 
 #[allow(static_mut_refs)]
-fn printsb_cmd(_args: &[u8], _s: &mut Screen) {
+fn printsb_cmd(_argv: &Argv, _io: &mut Io) -> ExitStatus {
     printk!("ESP: {:#08x} STACK_TOP: {:#08x}\n", get_stack_pointer(), unsafe {
         (STACK.as_ptr() as usize + STACK_SIZE) as *const u8 as u32
     });
+    SUCCESS
 }
 
 /// Dumps a row of 16 bytes in the following format:
@@ -288,3 +978,72 @@ fn dump_row(row: [u8; 16], ptr: *const u8) {
     }
     printk!("\n");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    fn prompt_with(s: &[u8]) -> Prompt {
+        let mut prompt = Prompt::default();
+        for c in s {
+            let _ = prompt.insert(*c);
+        }
+        prompt
+    }
+
+    #[test_case]
+    fn insert_in_the_middle() -> Result<(), &'static str> {
+        let mut prompt = prompt_with(b"eho");
+        prompt.left();
+        prompt.left();
+        let _ = prompt.insert(b'c');
+
+        kassert_eq!(&prompt.entries[..prompt.len], b"echo");
+        kassert_eq!(prompt.cursor, 2);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn multibyte_characters_are_edited_whole() -> Result<(), &'static str> {
+        let mut prompt = Prompt::default();
+        for c in "café".chars() {
+            let _ = prompt.insert_char(c);
+        }
+        prompt.left();
+        kassert_eq!(prompt.cursor, 3);
+
+        prompt.end();
+        prompt.backspace();
+        kassert_eq!(&prompt.entries[..prompt.len], b"caf");
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn delete_word_removes_trailing_spaces() -> Result<(), &'static str> {
+        let mut prompt = prompt_with(b"echo hello  ");
+        prompt.delete_word();
+
+        kassert_eq!(&prompt.entries[..prompt.len], b"echo ");
+        kassert_eq!(prompt.entries[prompt.len + 1], b' ');
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn kill_around_cursor() -> Result<(), &'static str> {
+        let mut prompt = prompt_with(b"echo hello");
+        prompt.home();
+        prompt.right();
+        prompt.kill_to_end();
+        kassert_eq!(&prompt.entries[..prompt.len], b"e");
+
+        prompt.end();
+        prompt.kill_to_start();
+        kassert_eq!(prompt.len, 0);
+
+        Ok(())
+    }
+}
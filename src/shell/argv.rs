@@ -0,0 +1,206 @@
+//! Splitting of a command line into arguments.
+//!
+//! Arguments are separated by spaces, which can be kept in an argument by
+//! quoting or escaping them:
+//!
+//! - `'...'` keeps everything literally,
+//! - `"..."` keeps everything but `\"`, `\\` and `$?`,
+//! - `\c` outside of quotes keeps `c` literally,
+//! - `$?` outside of single quotes is replaced by the exit status of the last command.
+
+use core::fmt;
+
+use crate::shell::{PROMPT_SIZE, command::ExitStatus};
+
+/// Maximum number of arguments, including the command name.
+pub const MAX_ARGS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnterminatedQuote,
+    TrailingBackslash,
+    TooManyArguments,
+    LineTooLong,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnterminatedQuote => write!(f, "unterminated quote"),
+            Self::TrailingBackslash => write!(f, "trailing backslash"),
+            Self::TooManyArguments => write!(f, "too many arguments (at most {MAX_ARGS})"),
+            Self::LineTooLong => write!(f, "line too long"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+pub struct Argv {
+    buffer: [u8; PROMPT_SIZE],
+    /// Number of bytes of `buffer` holding arguments.
+    used: usize,
+    ends: [usize; MAX_ARGS],
+    len: usize,
+}
+
+impl Argv {
+    /// Splits `line` into arguments, replacing `$?` with `status`.
+    ///
+    /// # Errors
+    /// This function returns an error if a quote is left open, if the line ends with a
+    /// backslash, if there are more than [`MAX_ARGS`] arguments, or if the expanded line
+    /// does not fit in the prompt size.
+    pub fn parse(line: &[u8], status: ExitStatus) -> Result<Self, ParseError> {
+        let mut argv = Self {
+            buffer: [0; PROMPT_SIZE],
+            used: 0,
+            ends: [0; MAX_ARGS],
+            len: 0,
+        };
+        let mut in_arg = false;
+        let mut quote = Quote::None;
+        let mut bytes = line.iter().copied().peekable();
+
+        while let Some(c) = bytes.next() {
+            match (quote, c) {
+                (Quote::None, b' ' | b'\t') => {
+                    if in_arg {
+                        argv.end_arg()?;
+                        in_arg = false;
+                    }
+                    continue;
+                }
+                (Quote::None, b'\'') => quote = Quote::Single,
+                (Quote::None, b'"') => quote = Quote::Double,
+                (Quote::Single, b'\'') | (Quote::Double, b'"') => quote = Quote::None,
+                (Quote::None, b'\\') => argv.push(bytes.next().ok_or(ParseError::TrailingBackslash)?)?,
+                (Quote::Double, b'\\') if matches!(bytes.peek(), Some(b'"' | b'\\')) => {
+                    argv.push(bytes.next().ok_or(ParseError::TrailingBackslash)?)?;
+                }
+                (Quote::None | Quote::Double, b'$') if bytes.peek() == Some(&b'?') => {
+                    bytes.next();
+                    let mut digits = [0; 3];
+                    let mut n = 0;
+                    let mut value = status;
+                    loop {
+                        digits[n] = b'0' + value % 10;
+                        n += 1;
+                        value /= 10;
+                        if value == 0 {
+                            break;
+                        }
+                    }
+                    for digit in digits[..n].iter().rev() {
+                        argv.push(*digit)?;
+                    }
+                }
+                _ => argv.push(c)?,
+            }
+            in_arg = true;
+        }
+
+        if quote != Quote::None {
+            return Err(ParseError::UnterminatedQuote);
+        }
+        if in_arg {
+            argv.end_arg()?;
+        }
+
+        Ok(argv)
+    }
+
+    fn push(&mut self, c: u8) -> Result<(), ParseError> {
+        *self.buffer.get_mut(self.used).ok_or(ParseError::LineTooLong)? = c;
+        self.used += 1;
+        Ok(())
+    }
+
+    fn end_arg(&mut self) -> Result<(), ParseError> {
+        if self.len == MAX_ARGS {
+            return Err(ParseError::TooManyArguments);
+        }
+        self.ends[self.len] = self.used;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the `index`th argument, the command name being the 0th one.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.len {
+            return None;
+        }
+        let start = index.checked_sub(1).map_or(0, |previous| self.ends[previous]);
+        Some(&self.buffer[start..self.ends[index]])
+    }
+
+    /// Returns the command name.
+    #[must_use]
+    pub fn name(&self) -> Option<&[u8]> {
+        self.get(0)
+    }
+
+    /// Returns the arguments following the command name.
+    pub fn args(&self) -> impl Iterator<Item = &[u8]> {
+        (1..self.len).filter_map(|i| self.get(i))
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn spaces_separate_arguments() -> Result<(), &'static str> {
+        let argv = Argv::parse(b"  echo a   b ", 0).map_err(|_| "Could not parse")?;
+
+        kassert_eq!(argv.len(), 3);
+        kassert_eq!(argv.name(), Some(&b"echo"[..]));
+        kassert!(argv.args().eq([&b"a"[..], &b"b"[..]]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn quotes_and_escapes() -> Result<(), &'static str> {
+        let argv = Argv::parse(br#"echo 'a b' "c \"d\"" e\ f '' x"y"z"#, 0).map_err(|_| "Could not parse")?;
+
+        kassert!(argv.args().eq([&b"a b"[..], &br#"c "d""#[..], &b"e f"[..], &b""[..], &b"xyz"[..]]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn status_is_expanded_outside_single_quotes() -> Result<(), &'static str> {
+        let argv = Argv::parse(br#"echo $? "$?" '$?'"#, 127).map_err(|_| "Could not parse")?;
+
+        kassert!(argv.args().eq([&b"127"[..], &b"127"[..], &b"$?"[..]]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn syntax_errors_are_reported() -> Result<(), &'static str> {
+        kassert_eq!(Argv::parse(b"echo 'a", 0).err(), Some(ParseError::UnterminatedQuote));
+        kassert_eq!(Argv::parse(b"echo a\\", 0).err(), Some(ParseError::TrailingBackslash));
+
+        Ok(())
+    }
+}
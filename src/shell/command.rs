@@ -0,0 +1,114 @@
+//! Shell commands.
+//!
+//! Besides the built-in commands, subsystems can add their own with
+//! [`register`], which makes them available to every console along with their
+//! help text and completer.
+//!
+//! ```ignore
+//! fn lsirq_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus { ... }
+//!
+//! command::register(Command {
+//!     name: "lsirq",
+//!     usage: "",
+//!     help: "list the installed IRQ handlers",
+//!     func: lsirq_cmd,
+//!     complete: None,
+//! })?;
+//! ```
+//!
+//! Commands print their output with `printk!`, which the shell captures when
+//! the command is piped into another one. The piped input is then found in
+//! [`Io::stdin`].
+
+use crate::{
+    registry::{RegisterError, Registry},
+    shell::{argv::Argv, completion::Completer},
+};
+
+/// Exit status of a command, 0 meaning success.
+pub type ExitStatus = u8;
+
+pub const SUCCESS: ExitStatus = 0;
+pub const FAILURE: ExitStatus = 1;
+/// The command was called with invalid arguments.
+pub const USAGE: ExitStatus = 2;
+/// The command line could not be parsed.
+pub const SYNTAX_ERROR: ExitStatus = 2;
+pub const NOT_FOUND: ExitStatus = 127;
+
+/// Maximum number of commands registered at runtime.
+pub const MAX_COMMANDS: usize = 32;
+
+/// What a command reads from and writes to.
+pub struct Io<'a> {
+    /// Console the command runs on, see [`console::with_screen`].
+    pub console: usize,
+    /// Output of the previous command of the pipeline, if any.
+    pub stdin: Option<&'a [u8]>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Command {
+    pub name: &'static str,
+    /// Arguments shown by `help` after the name, like `[-l level]`.
+    pub usage: &'static str,
+    /// One line description shown by `help`.
+    pub help: &'static str,
+    pub func: fn(argv: &Argv, io: &mut Io) -> ExitStatus,
+    /// Completes the arguments of the command.
+    pub complete: Option<Completer>,
+}
+
+static COMMANDS: Registry<Command, MAX_COMMANDS> = Registry::new();
+
+/// Makes `command` available to the shells.
+///
+/// # Errors
+/// This function returns an error if a command with the same name exists, or if
+/// [`MAX_COMMANDS`] commands are already registered.
+pub fn register(command: Command) -> Result<(), RegisterError> {
+    if super::BUILTINS.iter().any(|c| c.name == command.name) {
+        return Err(RegisterError::AlreadyRegistered);
+    }
+
+    COMMANDS.register(command, |c| c.name == command.name)?;
+    Ok(())
+}
+
+/// Returns every available command, built-in ones first.
+pub fn all() -> impl Iterator<Item = Command> {
+    super::BUILTINS.iter().copied().chain(COMMANDS.iter().copied())
+}
+
+#[must_use]
+pub fn find(name: &[u8]) -> Option<Command> {
+    all().find(|c| c.name.as_bytes() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert;
+
+    fn true_cmd(_: &Argv, _: &mut Io) -> ExitStatus {
+        SUCCESS
+    }
+
+    #[test_case]
+    fn registered_commands_are_found_once() -> Result<(), &'static str> {
+        let command = Command {
+            name: "true",
+            usage: "",
+            help: "do nothing, successfully",
+            func: true_cmd,
+            complete: None,
+        };
+
+        kassert!(register(command).is_ok());
+        kassert!(find(b"true").is_some());
+        kassert!(matches!(register(command), Err(RegisterError::AlreadyRegistered)));
+        kassert!(matches!(register(Command { name: "echo", ..command }), Err(RegisterError::AlreadyRegistered)));
+
+        Ok(())
+    }
+}
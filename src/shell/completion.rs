@@ -0,0 +1,113 @@
+//! Tab completion.
+//!
+//! The first word of the prompt is completed from the command names. The
+//! following ones are completed by the command's completer, if it registered
+//! one, which adds its candidates to a [`Completions`] list.
+
+/// Maximum number of candidates kept, the others are dropped.
+pub const MAX_COMPLETIONS: usize = 64;
+
+/// Total length of the candidates kept.
+const COMPLETIONS_SIZE: usize = 1024;
+
+/// Adds the candidates for the word being completed, given the content of the
+/// prompt `before` that word (starting with the command name).
+pub type Completer = fn(before: &[u8], completions: &mut Completions);
+
+pub struct Completions<'a> {
+    /// Word being completed, only candidates starting with it are kept.
+    prefix: &'a [u8],
+    buffer: [u8; COMPLETIONS_SIZE],
+    ends: [usize; MAX_COMPLETIONS],
+    len: usize,
+}
+
+impl<'a> Completions<'a> {
+    #[must_use]
+    pub const fn new(prefix: &'a [u8]) -> Self {
+        Self {
+            prefix,
+            buffer: [0; COMPLETIONS_SIZE],
+            ends: [0; MAX_COMPLETIONS],
+            len: 0,
+        }
+    }
+
+    /// Adds `candidate` if it starts with the word being completed and was not added yet.
+    pub fn add(&mut self, candidate: &[u8]) {
+        if !candidate.starts_with(self.prefix) || self.iter().any(|c| c == candidate) {
+            return;
+        }
+
+        let start = self.len.checked_sub(1).map_or(0, |last| self.ends[last]);
+        let end = start + candidate.len();
+        if self.len == MAX_COMPLETIONS || end > COMPLETIONS_SIZE {
+            return;
+        }
+
+        self.buffer[start..end].copy_from_slice(candidate);
+        self.ends[self.len] = end;
+        self.len += 1;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        (0..self.len).map(|i| {
+            let start = i.checked_sub(1).map_or(0, |previous| self.ends[previous]);
+            &self.buffer[start..self.ends[i]]
+        })
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the longest prefix shared by every candidate.
+    #[must_use]
+    pub fn common_prefix(&self) -> &[u8] {
+        let mut candidates = self.iter();
+        let Some(first) = candidates.next() else {
+            return &[];
+        };
+
+        let len = candidates.fold(first.len(), |len, c| first.iter().zip(c).take(len).take_while(|(a, b)| a == b).count());
+        &first[..len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn only_matching_candidates_are_kept() -> Result<(), &'static str> {
+        let mut completions = Completions::new(b"pr");
+        completions.add(b"prints");
+        completions.add(b"echo");
+        completions.add(b"printsb");
+        completions.add(b"prints");
+
+        kassert_eq!(completions.len(), 2);
+        kassert_eq!(completions.common_prefix(), b"prints");
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn empty_prefix_matches_everything() -> Result<(), &'static str> {
+        let mut completions = Completions::new(b"");
+        completions.add(b"help");
+        completions.add(b"history");
+
+        kassert_eq!(completions.common_prefix(), b"h");
+        kassert!(completions.iter().eq([&b"help"[..], &b"history"[..]]));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+//! Commands filtering the output of another command, like `dmesg | grep kmalloc`.
+
+use crate::{
+    printk, printkln,
+    shell::{
+        argv::Argv,
+        command::{ExitStatus, FAILURE, Io, SUCCESS, USAGE},
+        completion::Completions,
+    },
+};
+
+/// Number of lines printed by `head` and `tail` unless `-n` is given.
+const DEFAULT_LINES: usize = 10;
+
+/// Prints `bytes` as is, the ones that are not valid UTF-8 being replaced.
+pub fn print_bytes(bytes: &[u8]) {
+    for chunk in bytes.utf8_chunks() {
+        printk!("{}", chunk.valid());
+        if !chunk.invalid().is_empty() {
+            printk!("{}", char::REPLACEMENT_CHARACTER);
+        }
+    }
+}
+
+/// Splits `input` into lines, without their `\n`.
+fn lines(input: &[u8]) -> impl DoubleEndedIterator<Item = &[u8]> {
+    input
+        .strip_suffix(b"\n")
+        .unwrap_or(input)
+        .split(|c| *c == b'\n')
+        .filter(move |_| !input.is_empty())
+}
+
+fn contains(line: &[u8], pattern: &[u8], ignore_case: bool) -> bool {
+    pattern.is_empty()
+        || line
+            .windows(pattern.len())
+            .any(|w| if ignore_case { w.eq_ignore_ascii_case(pattern) } else { w == pattern })
+}
+
+/// Returns the piped input, or prints an error if there is none.
+fn stdin<'a>(io: &Io<'a>, name: &str) -> Option<&'a [u8]> {
+    if io.stdin.is_none() {
+        printkln!("{}: no input - pipe a command into it, like `dmesg | {}`", name, name);
+    }
+    io.stdin
+}
+
+pub fn grep_cmd(argv: &Argv, io: &mut Io) -> ExitStatus {
+    let (mut invert, mut ignore_case, mut pattern) = (false, false, None);
+    for arg in argv.args() {
+        match arg {
+            b"-v" if pattern.is_none() => invert = true,
+            b"-i" if pattern.is_none() => ignore_case = true,
+            _ if pattern.is_none() => pattern = Some(arg),
+            _ => {
+                printkln!("usage: grep [-v] [-i] pattern");
+                return USAGE;
+            }
+        }
+    }
+    let Some(pattern) = pattern else {
+        printkln!("usage: grep [-v] [-i] pattern");
+        return USAGE;
+    };
+    let Some(input) = stdin(io, "grep") else {
+        return USAGE;
+    };
+
+    let mut status = FAILURE;
+    for line in lines(input).filter(|line| contains(line, pattern, ignore_case) != invert) {
+        print_bytes(line);
+        printk!("\n");
+        status = SUCCESS;
+    }
+    status
+}
+
+pub fn grep_complete(_before: &[u8], completions: &mut Completions) {
+    completions.add(b"-v");
+    completions.add(b"-i");
+}
+
+/// Parses the arguments of `head` and `tail`, returning the number of lines to print.
+fn line_count(argv: &Argv, name: &str) -> Option<usize> {
+    let mut args = argv.args();
+
+    let count = match (args.next(), args.next(), args.next()) {
+        (None, _, _) => Some(DEFAULT_LINES),
+        (Some(b"-n"), Some(n), None) => core::str::from_utf8(n).ok().and_then(|n| n.parse().ok()),
+        _ => None,
+    };
+    if count.is_none() {
+        printkln!("usage: {} [-n lines]", name);
+    }
+    count
+}
+
+pub fn head_cmd(argv: &Argv, io: &mut Io) -> ExitStatus {
+    let Some(count) = line_count(argv, "head") else {
+        return USAGE;
+    };
+    let Some(input) = stdin(io, "head") else {
+        return USAGE;
+    };
+
+    for line in lines(input).take(count) {
+        print_bytes(line);
+        printk!("\n");
+    }
+    SUCCESS
+}
+
+pub fn tail_cmd(argv: &Argv, io: &mut Io) -> ExitStatus {
+    let Some(count) = line_count(argv, "tail") else {
+        return USAGE;
+    };
+    let Some(input) = stdin(io, "tail") else {
+        return USAGE;
+    };
+
+    let skipped = lines(input).count().saturating_sub(count);
+    for line in lines(input).skip(skipped) {
+        print_bytes(line);
+        printk!("\n");
+    }
+    SUCCESS
+}
+
+pub fn line_count_complete(_before: &[u8], completions: &mut Completions) {
+    completions.add(b"-n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn lines_ignore_the_last_newline() -> Result<(), &'static str> {
+        kassert!(lines(b"a\nb\n").eq([&b"a"[..], &b"b"[..]]));
+        kassert!(lines(b"a\n\nb").eq([&b"a"[..], &b""[..], &b"b"[..]]));
+        kassert_eq!(lines(b"").count(), 0);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn patterns_match_anywhere_in_the_line() -> Result<(), &'static str> {
+        kassert!(contains(b"[kmalloc] out of memory", b"malloc", false));
+        kassert!(contains(b"[kmalloc] out of memory", b"OUT", true));
+        kassert!(!contains(b"[kmalloc] out of memory", b"OUT", false));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+//! History of the commands executed by the shells.
+//!
+//! The history is shared by every console. Once [`HISTORY_SIZE`] commands are
+//! stored the oldest ones get overwritten, and commands longer than
+//! [`LINE_SIZE`] are truncated.
+
+/// Number of commands kept before the oldest ones get overwritten.
+pub const HISTORY_SIZE: usize = 64;
+
+/// Maximum length of a stored command.
+pub const LINE_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Line {
+    bytes: [u8; LINE_SIZE],
+    len: usize,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Line {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { bytes: [0; LINE_SIZE], len: 0 }
+    }
+
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut line = Self::new();
+        line.len = bytes.len().min(LINE_SIZE);
+        line.bytes[..line.len].copy_from_slice(&bytes[..line.len]);
+        line
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Appends `c`, returning `false` if the line is full.
+    pub fn push(&mut self, c: u8) -> bool {
+        if self.len >= LINE_SIZE {
+            return false;
+        }
+        self.bytes[self.len] = c;
+        self.len += 1;
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.bytes[self.len])
+    }
+}
+
+pub struct History {
+    lines: [Line; HISTORY_SIZE],
+    head: usize,
+    len: usize,
+    /// Number of commands ever pushed, used to number them like `bash` does.
+    count: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            lines: [Line::new(); HISTORY_SIZE],
+            head: 0,
+            len: 0,
+            count: 0,
+        }
+    }
+
+    /// Stores `command`, unless it is blank or the same as the last one.
+    pub fn push(&mut self, command: &[u8]) {
+        if command.iter().all(|c| *c == b' ') || self.get(0).is_some_and(|last| last == command) {
+            return;
+        }
+
+        self.lines[(self.head + self.len) % HISTORY_SIZE] = Line::from_bytes(command);
+
+        if self.len == HISTORY_SIZE {
+            self.head = (self.head + 1) % HISTORY_SIZE;
+        } else {
+            self.len += 1;
+        }
+        self.count += 1;
+    }
+
+    /// Returns the `age`th most recent command, 0 being the last one.
+    #[must_use]
+    pub fn get(&self, age: usize) -> Option<&[u8]> {
+        if age >= self.len {
+            return None;
+        }
+        Some(self.lines[(self.head + self.len - 1 - age) % HISTORY_SIZE].as_bytes())
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the age of the most recent command containing `query`, starting from `age`.
+    #[must_use]
+    pub fn search(&self, query: &[u8], age: usize) -> Option<usize> {
+        (age..self.len).find(|age| self.get(*age).is_some_and(|command| contains(command, query)))
+    }
+
+    /// Returns the stored commands along with their number, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        let first = self.count - self.len + 1;
+        (0..self.len).map(move |i| (first + i, self.lines[(self.head + i) % HISTORY_SIZE].as_bytes()))
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty() || haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+static mut HISTORY: History = History::new();
+
+#[must_use]
+#[allow(static_mut_refs)]
+pub fn history() -> &'static mut History {
+    // SAFETY:
+    // The shells run one key at a time on a single thread, so the history is never borrowed
+    // twice.
+    unsafe { &mut HISTORY }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn blank_and_repeated_commands_are_skipped() -> Result<(), &'static str> {
+        let mut history = History::new();
+        history.push(b"help");
+        history.push(b"help");
+        history.push(b"  ");
+        history.push(b"clear");
+
+        kassert_eq!(history.len(), 2);
+        kassert_eq!(history.get(0), Some(&b"clear"[..]));
+        kassert_eq!(history.get(1), Some(&b"help"[..]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn oldest_commands_are_overwritten() -> Result<(), &'static str> {
+        let mut history = History::new();
+        for i in 0..HISTORY_SIZE + 2 {
+            history.push(&[b'a' + (i % 26) as u8, b'0' + (i / 26) as u8]);
+        }
+
+        kassert_eq!(history.len(), HISTORY_SIZE);
+        kassert_eq!(history.iter().next().map(|(n, _)| n), Some(3));
+        kassert_eq!(history.get(HISTORY_SIZE - 1), Some(&b"c0"[..]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn search_finds_most_recent_match() -> Result<(), &'static str> {
+        let mut history = History::new();
+        history.push(b"echo one");
+        history.push(b"dmesg");
+        history.push(b"echo two");
+
+        kassert_eq!(history.search(b"echo", 0), Some(0));
+        kassert_eq!(history.search(b"echo", 1), Some(2));
+        kassert!(history.search(b"reboot", 0).is_none());
+
+        Ok(())
+    }
+}
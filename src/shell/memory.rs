@@ -0,0 +1,159 @@
+//! Memory inspection commands.
+//!
+//! Addresses and values are hexadecimal, with or without `0x`. Every page
+//! touched is checked with [`virt_to_phys`] first, so that unmapped memory is
+//! reported instead of faulting.
+
+use crate::{
+    conv::hextou,
+    printkln,
+    shell::{
+        argv::Argv,
+        command::{ExitStatus, FAILURE, Io, SUCCESS, USAGE},
+        completion::Completions,
+        dump_row,
+    },
+    vmm::paging::mmap::{page_entries, virt_to_phys},
+};
+
+/// Number of bytes dumped by `x` unless a length is given.
+const DEFAULT_DUMP_LENGTH: usize = 64;
+
+/// Returns whether every byte of `start..start + len` is mapped.
+fn is_mapped(start: usize, len: usize) -> bool {
+    start
+        .checked_add(len - 1)
+        .is_some_and(|last| virt_to_phys(start).is_ok() && virt_to_phys(last).is_ok())
+}
+
+/// Returns whether every byte of `start..start + len` is mapped and writable.
+fn is_writable(start: usize, len: usize) -> bool {
+    let writable = |vaddr| match page_entries(vaddr) {
+        (directory, Some(table)) => directory.read_write() == 1 && table.read_write() == 1,
+        (directory, None) => directory.read_write() == 1,
+    };
+    is_mapped(start, len) && writable(start) && writable(start + len - 1)
+}
+
+pub fn x_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+    let (start, len) = match (args.next().and_then(hextou), args.next(), args.next()) {
+        (Some(start), None, None) => (start, DEFAULT_DUMP_LENGTH),
+        (Some(start), Some(len), None) => {
+            let Some(len) = core::str::from_utf8(len).ok().and_then(|len| len.parse::<usize>().ok()) else {
+                printkln!("usage: x <addr> [len]");
+                return USAGE;
+            };
+            (start, len)
+        }
+        _ => {
+            printkln!("usage: x <addr> [len]");
+            return USAGE;
+        }
+    };
+
+    for row in (0..len.div_ceil(16)).map(|i| start.wrapping_add(i * 16)) {
+        if !is_mapped(row, 16) {
+            printkln!("x: {:#010x} is not mapped", row);
+            return FAILURE;
+        }
+
+        let ptr = row as *const u8;
+        // SAFETY:
+        // Both ends of the row were checked to be mapped.
+        dump_row(unsafe { ptr.cast::<[u8; 16]>().read_unaligned() }, ptr);
+    }
+    SUCCESS
+}
+
+pub fn poke_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+    let (size, addr, value) = match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(size @ (b"-b" | b"-h" | b"-w")), Some(addr), Some(value), None) => (size, addr, value),
+        (Some(addr), Some(value), None, None) => (&b"-w"[..], addr, value),
+        _ => {
+            printkln!("usage: poke [-b|-h|-w] <addr> <value>");
+            return USAGE;
+        }
+    };
+    let size = match size {
+        b"-b" => 1,
+        b"-h" => 2,
+        _ => 4,
+    };
+    let (Some(addr), Some(value)) = (hextou(addr), hextou(value)) else {
+        printkln!("usage: poke [-b|-h|-w] <addr> <value>");
+        return USAGE;
+    };
+    if size < 4 && value >> (size * 8) != 0 {
+        printkln!("poke: {:#x} does not fit in {} bytes", value, size);
+        return USAGE;
+    }
+    if !is_writable(addr, size) {
+        printkln!("poke: {:#010x} is not mapped writable", addr);
+        return FAILURE;
+    }
+
+    // SAFETY:
+    // Both ends of the written range were checked to be mapped and writable. Whether the write
+    // makes sense is up to the user, this is a debugging tool.
+    let previous = unsafe {
+        match size {
+            1 => {
+                let ptr = addr as *mut u8;
+                let previous = ptr.read_volatile();
+                ptr.write_volatile(value as u8);
+                usize::from(previous)
+            }
+            2 => {
+                let ptr = addr as *mut u16;
+                let previous = ptr.read_unaligned();
+                ptr.write_unaligned(value as u16);
+                usize::from(previous)
+            }
+            _ => {
+                let ptr = addr as *mut u32;
+                let previous = ptr.read_unaligned();
+                ptr.write_unaligned(value as u32);
+                previous as usize
+            }
+        }
+    };
+    printkln!("{:#010x}: {:#0width$x} -> {:#0width$x}", addr, previous, value, width = size * 2 + 2);
+    SUCCESS
+}
+
+pub fn poke_complete(before: &[u8], completions: &mut Completions) {
+    if before.split(|c| *c == b' ').filter(|w| !w.is_empty()).count() == 1 {
+        completions.add(b"-b");
+        completions.add(b"-h");
+        completions.add(b"-w");
+    }
+}
+
+pub fn vtop_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+    let (Some(vaddr), None) = (args.next().and_then(hextou), args.next()) else {
+        printkln!("usage: vtop <addr>");
+        return USAGE;
+    };
+
+    let (directory, table) = page_entries(vaddr);
+    printkln!("PDE[{:4}] {}", vaddr >> 22, directory);
+    if let Some(table) = table {
+        printkln!("PTE[{:4}] {}", (vaddr >> 12) & 0x3FF, table);
+    } else if directory.present() == 1 {
+        printkln!("4MB page, no page table");
+    }
+
+    match virt_to_phys(vaddr) {
+        Ok(paddr) => {
+            printkln!("{:#010x} -> {:#010x}", vaddr, paddr);
+            SUCCESS
+        }
+        Err(e) => {
+            printkln!("vtop: {:#010x} is not mapped ({:?})", vaddr, e);
+            FAILURE
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Pipelines and command lists.
+//!
+//! A line is made of pipelines separated by operators, each pipeline being
+//! one or more commands separated by `|`:
+//!
+//! - `a | b` runs `a` with its output captured, and gives it to `b` as input,
+//! - `a ; b` runs `a` then `b`,
+//! - `a && b` runs `b` only if `a` succeeded,
+//! - `a || b` runs `b` only if `a` failed.
+//!
+//! Like in `sh`, the exit status of a pipeline is the one of its last command,
+//! and operators inside quotes or escaped with `\` are kept literally.
+
+use core::fmt;
+
+use crate::{
+    printk, printkln,
+    shell::{
+        argv::{Argv, ParseError},
+        command::{self, ExitStatus, Io, NOT_FOUND, SUCCESS, SYNTAX_ERROR},
+    },
+    vmm::allocators::collections::KVec,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separator {
+    Pipe,
+    Sequence,
+    And,
+    Or,
+}
+
+impl Separator {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pipe => "|",
+            Self::Sequence => ";",
+            Self::And => "&&",
+            Self::Or => "||",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    Parse(ParseError),
+    /// A separator is not preceded by a command.
+    UnexpectedSeparator(Separator),
+    /// The line ends with a separator expecting another command.
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::UnexpectedSeparator(separator) => write!(f, "syntax error near unexpected `{}`", separator.as_str()),
+            Self::UnexpectedEnd => write!(f, "syntax error: unexpected end of line"),
+        }
+    }
+}
+
+impl From<ParseError> for ScriptError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+/// Returns the position and kind of the first separator of `line` that is not quoted or escaped.
+fn find_separator(line: &[u8]) -> Option<(usize, Separator)> {
+    let mut quote = None;
+    let mut i = 0;
+
+    while i < line.len() {
+        let next = line.get(i + 1).copied();
+        match (quote, line[i]) {
+            (None | Some(b'"'), b'\\') => i += 1,
+            (None, c @ (b'\'' | b'"')) => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, b';') => return Some((i, Separator::Sequence)),
+            (None, b'|') if next == Some(b'|') => return Some((i, Separator::Or)),
+            (None, b'|') => return Some((i, Separator::Pipe)),
+            (None, b'&') if next == Some(b'&') => return Some((i, Separator::And)),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Splits `line` into commands, each one along with the separator following it.
+pub fn split(line: &[u8]) -> impl Iterator<Item = (&[u8], Option<Separator>)> {
+    let mut rest = Some(line);
+
+    core::iter::from_fn(move || {
+        let line = rest?;
+        if let Some((i, separator)) = find_separator(line) {
+            rest = Some(&line[i + separator.as_str().len()..]);
+            Some((&line[..i], Some(separator)))
+        } else {
+            rest = None;
+            Some((line, None))
+        }
+    })
+}
+
+/// Returns the position in `line` where its last command starts.
+#[must_use]
+pub fn last_command_start(line: &[u8]) -> usize {
+    let mut start = 0;
+    while let Some((i, separator)) = find_separator(&line[start..]) {
+        start += i + separator.as_str().len();
+    }
+    start
+}
+
+/// Checks the syntax of the whole line, so that nothing runs if part of it is invalid.
+///
+/// # Errors
+/// This function returns an error if a command cannot be parsed, or if a separator is
+/// missing a command on either side.
+pub fn check(line: &[u8]) -> Result<(), ScriptError> {
+    let mut previous = None;
+
+    for (command, separator) in split(line) {
+        if Argv::parse(command, SUCCESS)?.is_empty() {
+            match separator {
+                Some(separator) => return Err(ScriptError::UnexpectedSeparator(separator)),
+                None if matches!(previous, Some(Separator::Pipe | Separator::And | Separator::Or)) => {
+                    return Err(ScriptError::UnexpectedEnd);
+                }
+                None => {}
+            }
+        }
+        previous = separator;
+    }
+
+    Ok(())
+}
+
+/// Runs every command of `line`, `status` being the exit status of the previous line, and
+/// returns the exit status of the last command run.
+///
+/// Syntax errors and unknown commands are reported on the console.
+#[must_use]
+pub fn run(line: &[u8], console: usize, mut status: ExitStatus) -> ExitStatus {
+    if let Err(e) = check(line) {
+        printkln!("sh: {}", e);
+        return SYNTAX_ERROR;
+    }
+
+    // Whether the current pipeline is skipped because of `&&` or `||`.
+    let mut skip = false;
+    let mut stdin: Option<KVec<u8>> = None;
+
+    for (command, separator) in split(line) {
+        if !skip {
+            let argv = match Argv::parse(command, status) {
+                Ok(argv) => argv,
+                Err(e) => {
+                    printkln!("sh: {}", e);
+                    return SYNTAX_ERROR;
+                }
+            };
+
+            if separator == Some(Separator::Pipe) {
+                let mut output = KVec::new();
+                printk::capture(&mut output, || execute(&argv, console, stdin.as_deref()));
+                stdin = Some(output);
+            } else if !argv.is_empty() {
+                status = execute(&argv, console, stdin.take().as_deref());
+            }
+        }
+
+        skip = match separator {
+            Some(Separator::Pipe) => skip,
+            Some(Separator::And) => status != SUCCESS,
+            Some(Separator::Or) => status == SUCCESS,
+            Some(Separator::Sequence) | None => false,
+        };
+    }
+
+    status
+}
+
+fn execute(argv: &Argv, console: usize, stdin: Option<&[u8]>) -> ExitStatus {
+    let Some(command) = argv.name().and_then(command::find) else {
+        printkln!("command not found - run `help` for available commands");
+        return NOT_FOUND;
+    };
+
+    (command.func)(argv, &mut Io { console, stdin })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn separators_split_commands() -> Result<(), &'static str> {
+        kassert!(split(b"a | b;c && d || e").eq([
+            (&b"a "[..], Some(Separator::Pipe)),
+            (&b" b"[..], Some(Separator::Sequence)),
+            (&b"c "[..], Some(Separator::And)),
+            (&b" d "[..], Some(Separator::Or)),
+            (&b" e"[..], None),
+        ]));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn quoted_separators_are_kept() -> Result<(), &'static str> {
+        kassert!(split(br#"echo '|' "&&" \; a&b"#).eq([(&br#"echo '|' "&&" \; a&b"#[..], None)]));
+        kassert_eq!(last_command_start(b"echo ';' | gr"), 11);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn missing_commands_are_syntax_errors() -> Result<(), &'static str> {
+        kassert_eq!(check(b"| grep a"), Err(ScriptError::UnexpectedSeparator(Separator::Pipe)));
+        kassert_eq!(check(b"echo a &&"), Err(ScriptError::UnexpectedEnd));
+        kassert_eq!(check(b"echo a;; echo b"), Err(ScriptError::UnexpectedSeparator(Separator::Sequence)));
+        kassert_eq!(check(b"echo a ;"), Ok(()));
+        kassert_eq!(check(b""), Ok(()));
+
+        Ok(())
+    }
+}
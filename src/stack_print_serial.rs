@@ -15,6 +15,44 @@ fn get_stack_pointer() -> u32 {
     sp as u32
 }
 
+/// Returns the address the caller of this function returns to, or the one `depth` frames further
+/// up the call chain, by following the saved frame pointers. Returns `None` if the chain ends
+/// first.
+#[inline(never)]
+#[allow(static_mut_refs)]
+#[must_use]
+pub fn return_address(depth: usize) -> Option<usize> {
+    let mut frame: usize;
+    // SAFETY:
+    // Reading `ebp` has no side effects.
+    unsafe { core::arch::asm!("mov {0}, ebp", out(reg) frame, options(nomem, nostack)) };
+
+    // SAFETY:
+    // Only the address of the stack is taken.
+    let stack = unsafe { STACK.as_ptr() as usize };
+    // Each frame starts with the caller's frame pointer, followed by the return address.
+    let is_frame = |frame: usize| (stack..stack + STACK_SIZE - 8).contains(&frame) && frame.is_multiple_of(4);
+
+    // Our own frame is skipped as well, `ebp` is cleared before `kmain` so the chain ends at 0.
+    for _ in 0..=depth {
+        if !is_frame(frame) {
+            return None;
+        }
+        // SAFETY:
+        // `frame` is within the stack and aligned.
+        let caller = unsafe { *(frame as *const usize) };
+        // Frames only ever move towards the bottom of the stack.
+        if caller <= frame {
+            return None;
+        }
+        frame = caller;
+    }
+
+    // SAFETY:
+    // `frame` is within the stack and aligned.
+    is_frame(frame).then(|| unsafe { *(frame as *const usize).add(1) })
+}
+
 #[allow(static_mut_refs)]
 pub fn print_stack_to_serial() {
     let sp_addr = get_stack_pointer();
@@ -2,6 +2,9 @@
 #![warn(clippy::missing_errors_doc)]
 #![warn(clippy::missing_panics_doc)]
 
+pub mod ansi;
+pub mod console;
+pub mod cp437;
 pub mod cursor;
 pub mod entry;
 pub mod screen;
@@ -0,0 +1,308 @@
+//! Parser for the subset of ANSI/VT100 escape sequences understood by the
+//! terminal.
+//!
+//! Bytes are fed one at a time to [`Parser::advance`], which returns an
+//! [`Action`] once a printable character, a control character or a complete
+//! CSI sequence (`ESC [ params final`) was read. Sequences the terminal does
+//! not support are consumed and dropped.
+//!
+//! Text is decoded as UTF-8, and printed with its [`cp437`] glyph, `?` standing
+//! in for the characters the VGA font does not have.
+
+use crate::terminal::cp437;
+
+/// Maximum number of parameters kept for a single sequence, the others are ignored.
+pub const MAX_PARAMS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+}
+
+impl Params {
+    const fn new() -> Self {
+        Self {
+            values: [0; MAX_PARAMS],
+            len: 0,
+        }
+    }
+
+    /// Returns the `index`th parameter, or `default` if it was omitted or 0.
+    #[must_use]
+    pub fn get_or(&self, index: usize, default: u16) -> u16 {
+        match self.values[..self.len].get(index) {
+            Some(0) | None => default,
+            Some(value) => *value,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> {
+        self.values[..self.len].iter().copied()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// A character to display as a code page 437 byte, including `\n` and `\t`.
+    Print(u8),
+    /// Any other C0 control character, like `\r` or backspace.
+    Execute(u8),
+    /// `CSI n A`
+    CursorUp(u16),
+    /// `CSI n B`
+    CursorDown(u16),
+    /// `CSI n C`
+    CursorForward(u16),
+    /// `CSI n D`
+    CursorBack(u16),
+    /// `CSI row ; col H`, 1-based.
+    CursorPosition { row: u16, col: u16 },
+    /// `CSI n J`
+    EraseInDisplay(u16),
+    /// `CSI n K`
+    EraseInLine(u16),
+    /// `CSI params m`
+    SelectGraphicRendition(Params),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Parser {
+    state: State,
+    params: Params,
+    /// Value of the parameter currently being read.
+    current: u16,
+    /// Whether the sequence contains bytes we do not support (private markers,
+    /// intermediate bytes), in which case it is dropped once complete.
+    unsupported: bool,
+    /// Bits of the UTF-8 sequence being decoded.
+    utf8: u32,
+    /// Number of continuation bytes still expected by the UTF-8 sequence.
+    utf8_remaining: u8,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: Params::new(),
+            current: 0,
+            unsupported: false,
+            utf8: 0,
+            utf8_remaining: 0,
+        }
+    }
+
+    pub fn advance(&mut self, byte: u8) -> Option<Action> {
+        match self.state {
+            State::Ground if byte >= 0x80 => self.decode_utf8(byte),
+            State::Ground => {
+                // An unfinished UTF-8 sequence is dropped.
+                self.utf8_remaining = 0;
+
+                match byte {
+                    0x1b => {
+                        self.state = State::Escape;
+                        None
+                    }
+                    b'\n' | b'\t' | 0x20.. => Some(Action::Print(byte)),
+                    _ => Some(Action::Execute(byte)),
+                }
+            }
+            State::Escape => {
+                if byte == b'[' {
+                    self.params = Params::new();
+                    self.current = 0;
+                    self.unsupported = false;
+                    self.state = State::Csi;
+                } else {
+                    // Two-byte sequences (`ESC c`, `ESC 7`, ...) are not supported.
+                    self.state = State::Ground;
+                }
+                None
+            }
+            State::Csi => match byte {
+                b'0'..=b'9' => {
+                    self.current = self.current.saturating_mul(10).saturating_add(u16::from(byte - b'0'));
+                    None
+                }
+                b';' => {
+                    self.push_param();
+                    None
+                }
+                0x40..=0x7e => {
+                    self.push_param();
+                    self.state = State::Ground;
+
+                    if self.unsupported { None } else { self.dispatch(byte) }
+                }
+                0x20..=0x3f => {
+                    self.unsupported = true;
+                    None
+                }
+                _ => {
+                    // Control characters abort the sequence.
+                    self.state = State::Ground;
+                    None
+                }
+            },
+        }
+    }
+
+    fn decode_utf8(&mut self, byte: u8) -> Option<Action> {
+        if byte & 0xc0 == 0x80 && self.utf8_remaining > 0 {
+            self.utf8 = (self.utf8 << 6) | u32::from(byte & 0x3f);
+            self.utf8_remaining -= 1;
+            if self.utf8_remaining > 0 {
+                return None;
+            }
+            return Some(Action::Print(char::from_u32(self.utf8).and_then(cp437::from_char).unwrap_or(b'?')));
+        }
+
+        (self.utf8, self.utf8_remaining) = match byte {
+            0xc0..=0xdf => (u32::from(byte & 0x1f), 1),
+            0xe0..=0xef => (u32::from(byte & 0x0f), 2),
+            0xf0..=0xf7 => (u32::from(byte & 0x07), 3),
+            _ => return Some(Action::Print(b'?')),
+        };
+        None
+    }
+
+    fn push_param(&mut self) {
+        if self.params.len < MAX_PARAMS {
+            self.params.values[self.params.len] = self.current;
+            self.params.len += 1;
+        }
+        self.current = 0;
+    }
+
+    fn dispatch(&self, final_byte: u8) -> Option<Action> {
+        let n = self.params.get_or(0, 1);
+
+        match final_byte {
+            b'A' => Some(Action::CursorUp(n)),
+            b'B' => Some(Action::CursorDown(n)),
+            b'C' => Some(Action::CursorForward(n)),
+            b'D' => Some(Action::CursorBack(n)),
+            b'H' | b'f' => Some(Action::CursorPosition {
+                row: n,
+                col: self.params.get_or(1, 1),
+            }),
+            b'J' => Some(Action::EraseInDisplay(self.params.iter().next().unwrap_or(0))),
+            b'K' => Some(Action::EraseInLine(self.params.iter().next().unwrap_or(0))),
+            b'm' => Some(Action::SelectGraphicRendition(self.params)),
+            _ => None,
+        }
+    }
+}
+
+/// VGA color indexes of the 8 ANSI colors, in ANSI order (black, red, green,
+/// yellow, blue, magenta, cyan, white).
+const ANSI_TO_VGA: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Applies an SGR sequence to a VGA attribute byte (background in the high
+/// nibble, foreground in the low nibble), starting from `color`.
+///
+/// `0` resets to `default`, `1`/`22` toggle the bright bit of the foreground,
+/// `30-37`/`90-97` and `40-47`/`100-107` select the foreground and background,
+/// and `39`/`49` restore the default ones. Other attributes are ignored.
+#[must_use]
+pub fn apply_sgr(color: u8, default: u8, params: &Params) -> u8 {
+    if params.is_empty() {
+        return default;
+    }
+
+    params.iter().fold(color, |color, param| match param {
+        0 => default,
+        1 => color | 0x08,
+        22 => color & !0x08,
+        30..=37 => (color & 0xf8) | ANSI_TO_VGA[(param - 30) as usize],
+        39 => (color & 0xf0) | (default & 0x0f),
+        40..=47 => (color & 0x0f) | (ANSI_TO_VGA[(param - 40) as usize] << 4),
+        49 => (color & 0x0f) | (default & 0xf0),
+        90..=97 => (color & 0xf0) | ANSI_TO_VGA[(param - 90) as usize] | 0x08,
+        100..=107 => (color & 0x0f) | ((ANSI_TO_VGA[(param - 100) as usize] | 0x08) << 4),
+        _ => color,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    fn parse(bytes: &[u8]) -> Option<Action> {
+        let mut parser = Parser::new();
+        bytes.iter().filter_map(|b| parser.advance(*b)).last()
+    }
+
+    #[test_case]
+    fn cursor_movement_defaults_to_one() -> Result<(), &'static str> {
+        kassert_eq!(parse(b"\x1b[A"), Some(Action::CursorUp(1)));
+        kassert_eq!(parse(b"\x1b[0C"), Some(Action::CursorForward(1)));
+        kassert_eq!(parse(b"\x1b[12D"), Some(Action::CursorBack(12)));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn cursor_position_parses_both_params() -> Result<(), &'static str> {
+        kassert_eq!(parse(b"\x1b[5;10H"), Some(Action::CursorPosition { row: 5, col: 10 }));
+        kassert_eq!(parse(b"\x1b[H"), Some(Action::CursorPosition { row: 1, col: 1 }));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn private_sequences_are_dropped() -> Result<(), &'static str> {
+        kassert_eq!(parse(b"\x1b[?25l"), None);
+        kassert_eq!(parse(b"\x1b[?25lx"), Some(Action::Print(b'x')));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn utf8_is_printed_as_cp437() -> Result<(), &'static str> {
+        kassert_eq!(parse("é".as_bytes()), Some(Action::Print(0x82)));
+        kassert_eq!(parse("€".as_bytes()), Some(Action::Print(b'?')));
+        kassert_eq!(parse(b"\xc3x"), Some(Action::Print(b'x')));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn sgr_sets_vga_colors() -> Result<(), &'static str> {
+        let Some(Action::SelectGraphicRendition(params)) = parse(b"\x1b[1;31;44m") else {
+            return Err("Expected an SGR action");
+        };
+
+        kassert_eq!(apply_sgr(0x07, 0x07, &params), 0x1c);
+
+        let Some(Action::SelectGraphicRendition(params)) = parse(b"\x1b[m") else {
+            return Err("Expected an SGR action");
+        };
+        kassert_eq!(apply_sgr(0x1c, 0x07, &params), 0x07);
+        kassert!(params.is_empty());
+
+        Ok(())
+    }
+}
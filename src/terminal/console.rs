@@ -0,0 +1,84 @@
+//! Virtual consoles.
+//!
+//! Each console is an independent [`Screen`] with its own scrollback. Only the
+//! active one is drawn to the VGA buffer, the others keep receiving output in
+//! the background and are redrawn when switched to. The last console is
+//! reserved for the kernel log.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::terminal::{Screen, vga::Buffer};
+
+pub const CONSOLE_COUNT: usize = 6;
+
+/// Console showing the kernel log, reachable with Alt+F6.
+pub const LOG_CONSOLE: usize = CONSOLE_COUNT - 1;
+
+static mut CONSOLES: [Screen; CONSOLE_COUNT] = [const { Screen::default() }; CONSOLE_COUNT];
+
+/// Set while the screen of a console is lent out by [`with_screen`].
+static BORROWED: [AtomicBool; CONSOLE_COUNT] = [const { AtomicBool::new(false) }; CONSOLE_COUNT];
+
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub enum ConsoleError {
+    InvalidConsole,
+}
+
+/// Runs `f` on the screen of console `index`, or returns `None` if the screen is already in
+/// use, when `f` ends up writing to the same console.
+///
+/// # Panics
+/// This function panics if `index` is not lower than [`CONSOLE_COUNT`].
+#[allow(static_mut_refs)]
+pub fn with_screen<R>(index: usize, f: impl FnOnce(&mut Screen) -> R) -> Option<R> {
+    let borrowed = &BORROWED[index];
+    if borrowed.swap(true, Ordering::Acquire) {
+        return None;
+    }
+
+    // SAFETY:
+    // `BORROWED` guarantees that no other reference to this screen is alive until `f` returns.
+    let res = f(unsafe { &mut CONSOLES[index] });
+    borrowed.store(false, Ordering::Release);
+    Some(res)
+}
+
+/// Index of the console currently shown on the VGA buffer.
+#[must_use]
+pub fn active() -> usize {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Shows console `index` and redraws the VGA buffer with its content.
+///
+/// # Errors
+/// This function returns an error if `index` is not lower than [`CONSOLE_COUNT`].
+pub fn switch(index: usize) -> Result<(), ConsoleError> {
+    if index >= CONSOLE_COUNT {
+        return Err(ConsoleError::InvalidConsole);
+    }
+
+    ACTIVE.store(index, Ordering::Relaxed);
+    with_screen(index, |screen| Buffer::from_screen(screen, 0).flush());
+
+    Ok(())
+}
+
+/// Writes to console `index`, and redraws the VGA buffer if it is the active one.
+///
+/// # Panics
+/// This function panics if `index` is not lower than [`CONSOLE_COUNT`].
+pub fn print(index: usize, args: fmt::Arguments) {
+    with_screen(index, |screen| {
+        let _ = screen.write_fmt(args);
+
+        if index == active() {
+            Buffer::from_screen(screen, 0).flush();
+        }
+    });
+}
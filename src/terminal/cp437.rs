@@ -0,0 +1,44 @@
+//! Code page 437, the character set of the VGA text mode font.
+//!
+//! The lower half is ASCII, the upper half holds accented letters, box
+//! drawing and a few symbols.
+
+/// Characters of the upper half, from `0x80` to `0xff`.
+const UPPER_HALF: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Returns the code page 437 byte displaying `c`, if there is one.
+#[must_use]
+pub fn from_char(c: char) -> Option<u8> {
+    match c {
+        '\0'..='\x7f' => Some(c as u8),
+        '¶' => Some(0x14),
+        '§' => Some(0x15),
+        _ => UPPER_HALF.iter().position(|upper| *upper == c).map(|i| 0x80 + i as u8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    #[test_case]
+    fn accented_letters_are_found() -> Result<(), &'static str> {
+        kassert_eq!(from_char('a'), Some(b'a'));
+        kassert_eq!(from_char('é'), Some(0x82));
+        kassert_eq!(from_char('ß'), Some(0xe1));
+        kassert_eq!(from_char('§'), Some(0x15));
+        kassert_eq!(from_char('€'), None);
+
+        Ok(())
+    }
+}
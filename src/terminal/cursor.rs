@@ -1,6 +1,10 @@
-use core::arch::asm;
+use crate::{
+    port::Port,
+    terminal::vga::{BUFFER_HEIGHT, BUFFER_WIDTH},
+};
 
-use crate::terminal::vga::{BUFFER_HEIGHT, BUFFER_WIDTH};
+/// CRTC index register, followed by its data register.
+const CRTC_INDEX: u16 = 0x3D4;
 
 /// Abstraction for managing the [Text-mode cursor](https://wiki.osdev.org/Text_Mode_Cursor).
 #[derive(Clone, Copy, Debug)]
@@ -69,28 +73,16 @@ impl Cursor {
     /// index register. The value being loaded into it defines which CRTC
     /// functionality we want to access. The different indices that can be loaded into it are documented [here](http://www.osdever.net/FreeVGA/vga/crtcreg.htm#0A).
     ///
-    /// After the index has been loaded into the `0x3D4`, `dx`, (where the index
-    /// register is stored) can be incremented by one. This will move it to
-    /// `0x3D5`, the CRTC's data register, signifying the CRTC's readiness to
-    /// receive the input values.
+    /// The data register, `0x3D5`, follows the index register, so a single
+    /// 16-bit write loads the index in its low byte and the value in its high
+    /// byte.
     /// ## SAFETY:
     /// This writes to the VGA buffer directly, running this in a non-bare-metal
     /// environment will result in invalid memory access.
     unsafe fn update(index: u8, value: u8) {
         #[allow(clippy::undocumented_unsafe_blocks)]
         unsafe {
-            asm!(
-                "mov dx, 0x3D4",
-                "mov al, {index}",
-                "out dx, al",
-                "inc dx",
-                "mov al, {value}",
-                "out dx, al",
-                index = in(reg_byte) (index),
-                value = in(reg_byte) (value),
-                out("dx") _,
-                out("al") _,
-            );
+            Port::<u16>::write_only(CRTC_INDEX).write(u16::from(value) << 8 | u16::from(index));
         }
     }
 
@@ -1,17 +1,36 @@
-use crate::terminal::{
-    entry::{Color, Entry},
-    vga::LinesIterator,
+use core::fmt;
+
+use crate::{
+    serial,
+    terminal::{
+        ansi::{self, Action, Parser},
+        entry::{Color, Entry},
+        vga::{BUFFER_HEIGHT, LinesIterator},
+    },
 };
 
 pub const BUFFER_SIZE: usize = 0x10000;
 
-pub static mut SCREEN: Screen = Screen::default();
-
+/// Scrollback of the terminal, as a ring of entries where lines are separated by `\n`.
+///
+/// Bytes written with [`Screen::write_byte`] go through an ANSI escape sequence
+/// parser, so the output can be colored and the cursor moved with the usual
+/// `ESC [` sequences. As lines are not stored in a grid, vertical cursor
+/// movement goes from one `\n`-separated line to another, whether it wraps on
+/// screen or not.
 #[derive(Debug)]
 pub struct Screen {
     pub entries: [Entry; BUFFER_SIZE],
     pub head: usize,
     pub len: usize,
+    /// Whether bytes written to and entries removed from the screen are mirrored to COM1, for
+    /// operating the kernel over a serial console.
+    pub serial_echo: bool,
+    /// Index of the entry under the cursor, or `None` when the cursor is after the last entry.
+    cursor: Option<usize>,
+    /// Color given to the written characters, changed by SGR sequences.
+    color: u8,
+    parser: Parser,
 }
 
 impl Screen {
@@ -21,46 +40,253 @@ impl Screen {
             entries: [Entry::new(b' '); BUFFER_SIZE],
             head: 0,
             len: 0,
+            serial_echo: false,
+            cursor: None,
+            color: Color::Default as u8,
+            parser: Parser::new(),
         }
     }
+
+    /// Appends an entry after the last one, regardless of the cursor and of escape sequences.
     pub fn push(&mut self, e: Entry) {
         if self.entries.len() <= self.len {
             self.entries[self.head] = e;
             self.head += 1;
             self.head %= BUFFER_SIZE;
+
+            // The oldest entry was dropped, so every index moved back by one.
+            self.cursor = self.cursor.map(|c| c.saturating_sub(1));
         } else {
             self.entries[(self.head + self.len) % self.entries.len()] = e;
             self.len += 1;
         }
     }
 
+    /// Writes a byte at the cursor, interpreting escape sequences.
+    pub fn write_byte(&mut self, byte: u8) {
+        if self.serial_echo {
+            serial::write_byte(byte);
+        }
+
+        match self.parser.advance(byte) {
+            None => {}
+            Some(Action::Print(c)) => self.put(Entry::new_with_color(c, self.color)),
+            Some(action) => self.apply(action),
+        }
+    }
+
     pub fn write(&mut self, str: &str) {
-        for c in str.chars() {
-            self.push(Entry::new(c as u8));
+        for byte in str.bytes() {
+            self.write_byte(byte);
         }
     }
 
     pub fn write_color(&mut self, str: &str, color: Color) {
-        for c in str.chars() {
-            self.push(Entry::new_with_color(c as u8, color as u8));
-        }
+        let previous = core::mem::replace(&mut self.color, color as u8);
+        self.write(str);
+        self.color = previous;
     }
 
     pub fn remove_last(&mut self) -> Option<Entry> {
         if self.len == 0 {
             None
         } else {
+            if self.serial_echo {
+                serial::write_byte(0x08);
+            }
+
             let idx = (self.head + self.len - 1) % self.entries.len();
             let e = self.entries[idx];
             self.len -= 1;
+            self.set_cursor(self.cursor_index());
             Some(e)
         }
     }
 
+    /// Index of the entry under the cursor, or `None` when it is after the last entry.
+    #[must_use]
+    pub const fn cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
     #[must_use]
     pub fn lines<'a>(&'a mut self) -> LinesIterator<'a> {
         LinesIterator::new(self)
     }
+
+    fn cursor_index(&self) -> usize {
+        self.cursor.unwrap_or(self.len)
+    }
+
+    fn set_cursor(&mut self, index: usize) {
+        self.cursor = if index < self.len { Some(index) } else { None };
+    }
+
+    fn at(&self, index: usize) -> &Entry {
+        &self.entries[(self.head + index) % BUFFER_SIZE]
+    }
+
+    fn at_mut(&mut self, index: usize) -> &mut Entry {
+        &mut self.entries[(self.head + index) % BUFFER_SIZE]
+    }
+
+    /// Index of the first entry of the line containing `index`.
+    fn line_start(&self, index: usize) -> usize {
+        let mut start = index;
+        while start > 0 && self.at(start - 1).character() != b'\n' {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Index of the `\n` ending the line containing `index`, or `len` for the last line.
+    fn line_end(&self, index: usize) -> usize {
+        let mut end = index;
+        while end < self.len && self.at(end).character() != b'\n' {
+            end += 1;
+        }
+        end
+    }
+
+    /// Writes `e` at the cursor: characters overwrite the entry under the cursor, or are
+    /// inserted when it is at the end of a line, and `\n` moves to the start of the next line.
+    fn put(&mut self, e: Entry) {
+        let Some(index) = self.cursor else {
+            self.push(e);
+            return;
+        };
+
+        if e.character() == b'\n' {
+            let end = self.line_end(index);
+            if end < self.len {
+                self.set_cursor(end + 1);
+            } else {
+                self.cursor = None;
+                self.push(e);
+            }
+        } else if self.at(index).character() == b'\n' {
+            let index = self.insert(index, e);
+            self.set_cursor(index + 1);
+        } else {
+            *self.at_mut(index) = e;
+            self.set_cursor(index + 1);
+        }
+    }
+
+    /// Inserts `e` at `index`, dropping the oldest entry if the screen is full, and returns
+    /// the index it ended up at.
+    fn insert(&mut self, mut index: usize, e: Entry) -> usize {
+        if self.len == BUFFER_SIZE {
+            self.head = (self.head + 1) % BUFFER_SIZE;
+            self.len -= 1;
+            index = index.saturating_sub(1);
+        }
+
+        for i in (index..self.len).rev() {
+            *self.at_mut(i + 1) = *self.at(i);
+        }
+        *self.at_mut(index) = e;
+        self.len += 1;
+
+        index
+    }
+
+    /// Removes the entries in `start..end`.
+    fn remove_range(&mut self, start: usize, end: usize) {
+        for i in end..self.len {
+            *self.at_mut(start + i - end) = *self.at(i);
+        }
+        self.len -= end - start;
+    }
+
+    fn blank(&mut self, start: usize, end: usize) {
+        for i in start..end {
+            *self.at_mut(i) = Entry::new_with_color(b' ', self.color);
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        let cursor = self.cursor_index();
+        let line_start = self.line_start(cursor);
+
+        match action {
+            Action::Print(_) => {}
+            Action::Execute(b'\r') => self.set_cursor(line_start),
+            Action::Execute(0x08) => self.set_cursor(cursor.saturating_sub(1).max(line_start)),
+            Action::Execute(_) => {}
+            Action::CursorBack(n) => self.set_cursor(cursor.saturating_sub(n as usize).max(line_start)),
+            Action::CursorForward(n) => self.set_cursor((cursor + n as usize).min(self.line_end(cursor))),
+            Action::CursorUp(n) => {
+                let mut index = cursor;
+                for _ in 0..n {
+                    let start = self.line_start(index);
+                    if start == 0 {
+                        break;
+                    }
+                    let previous_start = self.line_start(start - 1);
+                    index = previous_start + (index - start).min(start - 1 - previous_start);
+                }
+                self.set_cursor(index);
+            }
+            Action::CursorDown(n) => {
+                let mut index = cursor;
+                for _ in 0..n {
+                    let end = self.line_end(index);
+                    if end >= self.len {
+                        break;
+                    }
+                    let next_start = end + 1;
+                    index = next_start + (index - self.line_start(index)).min(self.line_end(next_start) - next_start);
+                }
+                self.set_cursor(index);
+            }
+            Action::CursorPosition { row, col } => {
+                // Rows are counted from the first line on screen, so that `ESC [ H` goes to the
+                // top left corner.
+                let lines = (0..self.len).filter(|i| self.at(*i).character() == b'\n').count() + 1;
+                let first_visible = lines.saturating_sub(BUFFER_HEIGHT);
+                let target = (first_visible + row as usize - 1).min(lines - 1);
+
+                let mut start = 0;
+                for _ in 0..target {
+                    start = self.line_end(start) + 1;
+                }
+                self.set_cursor(start + (col as usize - 1).min(self.line_end(start) - start));
+            }
+            Action::EraseInDisplay(0) => {
+                self.len = cursor;
+                self.cursor = None;
+            }
+            Action::EraseInDisplay(2 | 3) => {
+                // Lines are not stored in a grid, so the scrollback cannot be kept while
+                // clearing what is on screen.
+                self.head = 0;
+                self.len = 0;
+                self.cursor = None;
+            }
+            Action::EraseInLine(mode) => {
+                let end = self.line_end(cursor);
+                if matches!(mode, 1 | 2) {
+                    self.blank(line_start, (cursor + 1).min(end));
+                }
+                if matches!(mode, 0 | 2) {
+                    self.remove_range(cursor, end);
+                    self.set_cursor(cursor);
+                }
+            }
+            Action::EraseInDisplay(_) => {}
+            Action::SelectGraphicRendition(params) => {
+                self.color = ansi::apply_sgr(self.color, Color::Default as u8, &params);
+            }
+        }
+    }
+}
+
+impl fmt::Write for Screen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s);
+        Ok(())
+    }
 }
 
 impl<'a> IntoIterator for &'a Screen {
@@ -103,3 +329,70 @@ impl<'a> DoubleEndedIterator for ScreenIterator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    static mut TEST_SCREEN: Screen = Screen::default();
+
+    #[allow(static_mut_refs)]
+    fn screen_with(bytes: &str) -> &'static mut Screen {
+        // SAFETY:
+        // Tests run one at a time, and each of them starts from a fresh screen.
+        let screen = unsafe { &mut TEST_SCREEN };
+        *screen = Screen::default();
+        screen.write(bytes);
+        screen
+    }
+
+    fn text(screen: &Screen) -> ([u8; 32], usize) {
+        let mut out = [0; 32];
+        for (i, e) in screen.into_iter().take(32).enumerate() {
+            out[i] = e.character();
+        }
+        (out, screen.len.min(32))
+    }
+
+    #[test_case]
+    fn cursor_back_overwrites() -> Result<(), &'static str> {
+        let screen = screen_with("hello\x1b[3Dy");
+        let (out, len) = text(screen);
+
+        kassert_eq!(&out[..len], b"heylo");
+        kassert_eq!(screen.cursor(), Some(3));
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn erase_in_line_removes_until_end_of_line() -> Result<(), &'static str> {
+        let screen = screen_with("one\ntwo\x1b[A\x1b[2D\x1b[K");
+        let (out, len) = text(screen);
+
+        kassert_eq!(&out[..len], b"o\ntwo");
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn sgr_colors_entries() -> Result<(), &'static str> {
+        let screen = screen_with("\x1b[31ma\x1b[0mb");
+
+        kassert_eq!(u16::from(*screen.at(0)), 0x0461);
+        kassert_eq!(u16::from(*screen.at(1)), 0x0762);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn erase_display_clears_screen() -> Result<(), &'static str> {
+        let screen = screen_with("a\nb\x1b[H\x1b[2J");
+
+        kassert_eq!(screen.len, 0);
+        kassert_eq!(screen.cursor(), None);
+
+        Ok(())
+    }
+}
@@ -28,15 +28,34 @@ impl Default for Buffer {
 impl Buffer {
     pub fn from_screen(screen: &mut Screen, rows_scrolled_up: usize) -> Self {
         let lines_push_up = BUFFER_HEIGHT - screen.lines().rev().take(BUFFER_HEIGHT).count();
+        let screen_cursor = screen.cursor();
+        let mut cursor = None;
         let mut new = Self::default();
         for (line_index, l) in screen.lines().rev().skip(rows_scrolled_up).enumerate().take(BUFFER_HEIGHT) {
+            let y = BUFFER_HEIGHT - line_index - 1 - lines_push_up;
+
+            // Lines are walked from the bottom, so a cursor at the wrapping point of a long line
+            // ends up at the start of the next row rather than past the end of the previous one.
+            if let Some(index) = screen_cursor
+                && cursor.is_none()
+                && (l.start..=l.start + l.len).contains(&index)
+                && index - l.start < BUFFER_WIDTH
+            {
+                cursor = Some(Cursor {
+                    x: (index - l.start) as u16,
+                    y: y as u16,
+                });
+            }
+
             for (char_index, c) in l.into_iter().enumerate().take(BUFFER_WIDTH) {
-                new.entries[BUFFER_HEIGHT - line_index - 1 - lines_push_up][char_index] = *c;
+                new.entries[y][char_index] = *c;
             }
         }
 
         new.cursor = if rows_scrolled_up > 0 {
             None
+        } else if screen_cursor.is_some() {
+            cursor
         } else if let Some(cursor_line) = screen.lines().next_back() {
             if let Some((last_char_index, _)) = cursor_line.into_iter().enumerate().last() {
                 Some(Cursor {
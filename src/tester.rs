@@ -1,4 +1,4 @@
-use core::panic::PanicInfo;
+use core::{fmt::Write, panic::PanicInfo};
 
 use crate::{serial_print, serial_println};
 
@@ -68,3 +68,29 @@ pub fn should_panic_panic_handler() -> ! {
     #[allow(clippy::empty_loop)]
     loop {}
 }
+
+// Like `should_panic_panic_handler`, but only for panics whose message contains
+// `expected`. Any other panic fails the test.
+pub fn expected_panic_handler(info: &PanicInfo, expected: &str) -> ! {
+    struct Message {
+        bytes: [u8; 256],
+        len: usize,
+    }
+
+    impl Write for Message {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let len = s.len().min(self.bytes.len() - self.len);
+            self.bytes[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+            self.len += len;
+            Ok(())
+        }
+    }
+
+    let mut message = Message { bytes: [0; 256], len: 0 };
+    let _ = write!(message, "{}", info.message());
+
+    if message.bytes[..message.len].windows(expected.len()).any(|w| w == expected.as_bytes()) {
+        should_panic_panic_handler()
+    }
+    panic_handler(info)
+}
@@ -0,0 +1,438 @@
+//! Virtual filesystem.
+//!
+//! Filesystems implement [`Filesystem`], whose root [`Inode`] leads to the
+//! others through [`Inode::lookup`]. They are [`mount`]ed on a directory, and a
+//! path is resolved from the root of the filesystem mounted on its longest
+//! prefix.
+//!
+//! Opening a file stores its [`File`] operations in the descriptor table of the
+//! running task, see [`fd`], along with the offset that [`read`], [`write`] and
+//! [`seek`] work from.
+//!
+//! ```ignore
+//! let fd = vfs::open("/dev/ttyS0", OpenFlags::WRITE)?;
+//! vfs::write(fd, b"hello\n")?;
+//! vfs::close(fd)?;
+//! ```
+
+use core::fmt;
+
+use crate::{
+    printk, printkln,
+    registry::{RegisterError, Registry},
+    shell::{
+        argv::Argv,
+        command::{self, Command, ExitStatus, FAILURE, Io, SUCCESS, USAGE},
+    },
+};
+
+pub mod devfs;
+pub mod fd;
+pub mod path;
+pub mod rootfs;
+
+use fd::{Fd, OpenFile, OpenFlags, SeekFrom};
+use path::Path;
+
+/// Maximum number of filesystems mounted at the same time.
+pub const MAX_MOUNTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VfsError {
+    NotFound,
+    NotADirectory,
+    IsADirectory,
+    /// The path is relative, or too deep.
+    InvalidPath,
+    /// A filesystem is already mounted on the directory.
+    Busy,
+    /// [`MAX_MOUNTS`] filesystems are already mounted.
+    TooManyMounts,
+    /// The task already has [`fd::MAX_FILES`] open files.
+    TooManyFiles,
+    /// The file descriptor is not open.
+    BadFd,
+    /// The file was not opened for this operation.
+    PermissionDenied,
+    /// The file does not support this operation.
+    NotSupported,
+    /// The offset would be negative.
+    InvalidSeek,
+}
+
+impl fmt::Display for VfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "no such file or directory"),
+            Self::NotADirectory => write!(f, "not a directory"),
+            Self::IsADirectory => write!(f, "is a directory"),
+            Self::InvalidPath => write!(f, "invalid path"),
+            Self::Busy => write!(f, "mount point busy"),
+            Self::TooManyMounts => write!(f, "too many mounts"),
+            Self::TooManyFiles => write!(f, "too many open files"),
+            Self::BadFd => write!(f, "bad file descriptor"),
+            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::NotSupported => write!(f, "operation not supported"),
+            Self::InvalidSeek => write!(f, "invalid seek"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InodeKind {
+    File,
+    Directory,
+    CharDevice,
+}
+
+/// Entry of a directory, as returned by [`Inode::entry`].
+#[derive(Clone, Copy, Debug)]
+pub struct DirEntry<'a> {
+    pub name: &'a str,
+    pub kind: InodeKind,
+}
+
+/// Node of a filesystem: a file, a directory or a device.
+pub trait Inode {
+    fn kind(&self) -> InodeKind;
+
+    /// Size of the file, in bytes.
+    fn size(&self) -> u64 {
+        0
+    }
+
+    /// Returns the entry of the directory named `name`.
+    ///
+    /// # Errors
+    /// This function returns an error if there is no such entry, or if this is not a
+    /// directory.
+    fn lookup(&self, _name: &str) -> Result<&'static dyn Inode, VfsError> {
+        Err(VfsError::NotADirectory)
+    }
+
+    /// Returns the `index`th entry of the directory, or `None` past the last one.
+    ///
+    /// # Errors
+    /// This function returns an error if this is not a directory.
+    fn entry(&self, _index: usize) -> Result<Option<DirEntry<'_>>, VfsError> {
+        Err(VfsError::NotADirectory)
+    }
+
+    /// Returns the operations used to read and write the file once opened.
+    ///
+    /// # Errors
+    /// This function returns an error if the inode cannot be opened, like a directory.
+    fn file(&self) -> Result<&dyn File, VfsError> {
+        Err(VfsError::IsADirectory)
+    }
+}
+
+/// Operations of an open file. The offset is kept by the caller, devices ignore it.
+pub trait File {
+    /// Reads from `offset` into `buf`, and returns the number of bytes read, 0 meaning the end
+    /// of the file.
+    ///
+    /// # Errors
+    /// This function returns an error if the file cannot be read.
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError>;
+
+    /// Writes `buf` at `offset`, and returns the number of bytes written.
+    ///
+    /// # Errors
+    /// This function returns an error if the file cannot be written.
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, VfsError>;
+}
+
+pub trait Filesystem {
+    /// Name of the filesystem type, like `devfs`.
+    fn name(&self) -> &'static str;
+
+    fn root(&self) -> &'static dyn Inode;
+}
+
+struct Mount {
+    path: Path<'static>,
+    fs: &'static dyn Filesystem,
+}
+
+static MOUNTS: Registry<Mount, MAX_MOUNTS> = Registry::new();
+
+/// Mounts `fs` on the directory `path`. The first filesystem must be mounted on `/`.
+///
+/// # Errors
+/// This function returns an error if `path` is not a directory, if a filesystem is already
+/// mounted on it, or if [`MAX_MOUNTS`] filesystems are already mounted.
+pub fn mount(path: &'static str, fs: &'static dyn Filesystem) -> Result<(), VfsError> {
+    let path = Path::parse(path)?;
+    if !path.components().is_empty() && lookup(&path)?.kind() != InodeKind::Directory {
+        return Err(VfsError::NotADirectory);
+    }
+
+    MOUNTS.register(Mount { path, fs }, |m| m.path == path).map_err(|e| match e {
+        RegisterError::Full => VfsError::TooManyMounts,
+        RegisterError::AlreadyRegistered => VfsError::Busy,
+    })?;
+    Ok(())
+}
+
+fn lookup(path: &Path) -> Result<&'static dyn Inode, VfsError> {
+    let (mount, rest) = MOUNTS
+        .iter()
+        .filter_map(|m| Some((m, path.strip_prefix(&m.path)?)))
+        .max_by_key(|(m, _)| m.path.components().len())
+        .ok_or(VfsError::NotFound)?;
+
+    rest.iter().try_fold(mount.fs.root(), |inode, name| inode.lookup(name))
+}
+
+/// Returns the inode at the absolute `path`.
+///
+/// # Errors
+/// This function returns an error if the path is invalid, or if one of its components does
+/// not exist or is not a directory.
+pub fn resolve(path: &str) -> Result<&'static dyn Inode, VfsError> {
+    lookup(&Path::parse(path)?)
+}
+
+/// Opens the file at `path` in the descriptor table of the running task.
+///
+/// # Errors
+/// This function returns an error if the file cannot be resolved or opened, or if the task
+/// has too many open files.
+pub fn open(path: &str, flags: OpenFlags) -> Result<Fd, VfsError> {
+    let inode = resolve(path)?;
+    let file = inode.file()?;
+    fd::current().insert(OpenFile { inode, file, flags, offset: 0 })
+}
+
+/// See [`fd::FdTable::read`].
+///
+/// # Errors
+/// This function returns an error if `fd` cannot be read.
+pub fn read(fd: Fd, buf: &mut [u8]) -> Result<usize, VfsError> {
+    fd::current().read(fd, buf)
+}
+
+/// See [`fd::FdTable::write`].
+///
+/// # Errors
+/// This function returns an error if `fd` cannot be written.
+pub fn write(fd: Fd, buf: &[u8]) -> Result<usize, VfsError> {
+    fd::current().write(fd, buf)
+}
+
+/// See [`fd::FdTable::seek`].
+///
+/// # Errors
+/// This function returns an error if `fd` is not open, or if the offset would be negative.
+pub fn seek(fd: Fd, pos: SeekFrom) -> Result<u64, VfsError> {
+    fd::current().seek(fd, pos)
+}
+
+/// # Errors
+/// This function returns an error if `fd` is not open.
+pub fn close(fd: Fd) -> Result<(), VfsError> {
+    fd::current().close(fd)
+}
+
+static ROOTFS: rootfs::RootFs = rootfs::RootFs;
+static DEVFS: devfs::DevFs = devfs::DevFs;
+
+/// Mounts the root and device filesystems, and registers the file commands.
+///
+/// # Panics
+/// This function panics if the filesystems cannot be mounted.
+pub fn init() {
+    devfs::init();
+    assert!(mount("/", &ROOTFS).is_ok(), "Failed to mount the root filesystem");
+    assert!(mount("/dev", &DEVFS).is_ok(), "Failed to mount /dev");
+
+    let _ = command::register(Command {
+        name: "ls",
+        usage: "[path]",
+        help: "list a directory",
+        func: ls_cmd,
+        complete: None,
+    });
+    let _ = command::register(Command {
+        name: "cat",
+        usage: "[path]...",
+        help: "print files, or the input",
+        func: cat_cmd,
+        complete: None,
+    });
+    let _ = command::register(Command {
+        name: "tee",
+        usage: "<path>",
+        help: "copy the input to a file and to the output",
+        func: tee_cmd,
+        complete: None,
+    });
+    let _ = command::register(Command {
+        name: "mount",
+        usage: "",
+        help: "list the mounted filesystems",
+        func: mount_cmd,
+        complete: None,
+    });
+}
+
+/// Turns an argument into a path, or prints why it is not one.
+fn path_arg<'a>(cmd: &str, arg: &'a [u8]) -> Option<&'a str> {
+    let path = core::str::from_utf8(arg).ok();
+    if path.is_none() {
+        printkln!("{}: invalid path", cmd);
+    }
+    path
+}
+
+fn ls_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+    let path = match (args.next(), args.next()) {
+        (None, None) => "/",
+        (Some(arg), None) => match path_arg("ls", arg) {
+            Some(path) => path,
+            None => return FAILURE,
+        },
+        _ => {
+            printkln!("usage: ls [path]");
+            return USAGE;
+        }
+    };
+
+    let inode = match resolve(path) {
+        Ok(inode) => inode,
+        Err(e) => {
+            printkln!("ls: {}: {}", path, e);
+            return FAILURE;
+        }
+    };
+    if inode.kind() != InodeKind::Directory {
+        printkln!("{}", path);
+        return SUCCESS;
+    }
+
+    for index in 0.. {
+        match inode.entry(index) {
+            Ok(Some(entry)) => printkln!("{}{}", entry.name, if entry.kind == InodeKind::Directory { "/" } else { "" }),
+            Ok(None) => break,
+            Err(e) => {
+                printkln!("ls: {}: {}", path, e);
+                return FAILURE;
+            }
+        }
+    }
+    SUCCESS
+}
+
+/// Prints the content of the file at `path`.
+fn cat(path: &str) -> Result<(), VfsError> {
+    let fd = open(path, OpenFlags::READ)?;
+    let mut buf = [0; 512];
+
+    let res = loop {
+        match read(fd, &mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(len) => printk::write_bytes(&buf[..len]),
+            Err(e) => break Err(e),
+        }
+    };
+    close(fd)?;
+    res
+}
+
+fn cat_cmd(argv: &Argv, io: &mut Io) -> ExitStatus {
+    if argv.args().next().is_none() {
+        printk::write_bytes(io.stdin.unwrap_or_default());
+        printk::flush();
+        return SUCCESS;
+    }
+
+    let mut status = SUCCESS;
+    for path in argv.args().map(|arg| path_arg("cat", arg)) {
+        let Some(path) = path else {
+            status = FAILURE;
+            continue;
+        };
+        if let Err(e) = cat(path) {
+            printkln!("cat: {}: {}", path, e);
+            status = FAILURE;
+        }
+    }
+    // Files need not end with a newline, which is what flushes the output otherwise.
+    printk::flush();
+    status
+}
+
+fn tee_cmd(argv: &Argv, io: &mut Io) -> ExitStatus {
+    let mut args = argv.args();
+    let (Some(path), None) = (args.next(), args.next()) else {
+        printkln!("usage: tee <path>");
+        return USAGE;
+    };
+    let Some(path) = path_arg("tee", path) else {
+        return FAILURE;
+    };
+
+    let input = io.stdin.unwrap_or_default();
+    let res = open(path, OpenFlags::WRITE).and_then(|fd| {
+        let res = write(fd, input);
+        close(fd)?;
+        res
+    });
+    printk::write_bytes(input);
+
+    match res {
+        Ok(_) => SUCCESS,
+        Err(e) => {
+            printkln!("tee: {}: {}", path, e);
+            FAILURE
+        }
+    }
+}
+
+fn mount_cmd(argv: &Argv, _io: &mut Io) -> ExitStatus {
+    if argv.args().next().is_some() {
+        printkln!("usage: mount");
+        return USAGE;
+    }
+
+    for mount in MOUNTS.iter() {
+        printk!("{} on /", mount.fs.name());
+        for (index, component) in mount.path.components().iter().enumerate() {
+            printk!("{}{}", if index == 0 { "" } else { "/" }, component);
+        }
+        printkln!("");
+    }
+    SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn files_are_opened_through_mounts() -> Result<(), &'static str> {
+        if resolve("/").is_err() {
+            init();
+        }
+
+        kassert_eq!(resolve("/dev/null").map(|i| i.kind()), Ok(InodeKind::CharDevice));
+        kassert_eq!(resolve("/dev/../dev/./null").map(|i| i.kind()), Ok(InodeKind::CharDevice));
+        kassert_eq!(resolve("/dev/nope").err(), Some(VfsError::NotFound));
+        kassert_eq!(resolve("/dev/null/x").err(), Some(VfsError::NotADirectory));
+        kassert_eq!(mount("/dev", &DEVFS), Err(VfsError::Busy));
+        kassert_eq!(open("/dev", OpenFlags::READ), Err(VfsError::IsADirectory));
+
+        let fd = open("/dev/null", OpenFlags::WRITE).map_err(|_| "Could not open")?;
+        kassert_eq!(write(fd, b"abc"), Ok(3));
+        kassert_eq!(read(fd, &mut [0; 4]), Err(VfsError::PermissionDenied));
+        kassert_eq!(seek(fd, SeekFrom::Current(-1)), Ok(2));
+        kassert_eq!(seek(fd, SeekFrom::End(-1)), Err(VfsError::InvalidSeek));
+        close(fd).map_err(|_| "Could not close")?;
+        kassert!(close(fd).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Device filesystem, mounted on `/dev`.
+//!
+//! Drivers [`register`] their character devices under a name, and they show up
+//! as files of the root directory. `console` writes to the output of `printk!`,
+//! `ttyS0` to the COM1 serial port, and `null` discards everything.
+
+use crate::{
+    printk,
+    registry::{RegisterError, Registry},
+    serial,
+    vfs::{DirEntry, File, Filesystem, Inode, InodeKind, VfsError},
+};
+
+/// Maximum number of devices registered at the same time.
+pub const MAX_DEVICES: usize = 16;
+
+/// Character device whose operations are plain functions.
+pub struct CharDevice {
+    /// Fills the buffer and returns the number of bytes read, or `None` for write-only devices.
+    pub read: Option<fn(buf: &mut [u8]) -> usize>,
+    pub write: fn(buf: &[u8]),
+}
+
+impl Inode for CharDevice {
+    fn kind(&self) -> InodeKind {
+        InodeKind::CharDevice
+    }
+
+    fn file(&self) -> Result<&dyn File, VfsError> {
+        Ok(self)
+    }
+}
+
+impl File for CharDevice {
+    fn read(&self, _offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        self.read.map(|read| read(buf)).ok_or(VfsError::NotSupported)
+    }
+
+    fn write(&self, _offset: u64, buf: &[u8]) -> Result<usize, VfsError> {
+        (self.write)(buf);
+        Ok(buf.len())
+    }
+}
+
+pub static CONSOLE: CharDevice = CharDevice {
+    read: None,
+    write: printk::write_bytes,
+};
+
+pub static SERIAL: CharDevice = CharDevice {
+    read: None,
+    write: |buf| buf.iter().for_each(|byte| serial::write_byte(*byte)),
+};
+
+pub static NULL: CharDevice = CharDevice {
+    read: Some(|_| 0),
+    write: |_| {},
+};
+
+static DEVICES: Registry<(&'static str, &'static dyn Inode), MAX_DEVICES> = Registry::new();
+
+/// Makes `device` available as `/dev/<name>`.
+///
+/// # Errors
+/// This function returns an error if a device with the same name exists, or if
+/// [`MAX_DEVICES`] devices are already registered.
+pub fn register(name: &'static str, device: &'static dyn Inode) -> Result<(), RegisterError> {
+    DEVICES.register((name, device), |(n, _)| *n == name)?;
+    Ok(())
+}
+
+/// Root directory, listing the registered devices.
+struct Root;
+
+impl Inode for Root {
+    fn kind(&self) -> InodeKind {
+        InodeKind::Directory
+    }
+
+    fn lookup(&self, name: &str) -> Result<&'static dyn Inode, VfsError> {
+        DEVICES.find(|(n, _)| *n == name).map(|(_, device)| *device).ok_or(VfsError::NotFound)
+    }
+
+    fn entry(&self, index: usize) -> Result<Option<DirEntry<'_>>, VfsError> {
+        Ok(DEVICES.iter().nth(index).map(|(name, device)| DirEntry { name, kind: device.kind() }))
+    }
+}
+
+pub struct DevFs;
+
+impl Filesystem for DevFs {
+    fn name(&self) -> &'static str {
+        "devfs"
+    }
+
+    fn root(&self) -> &'static dyn Inode {
+        &Root
+    }
+}
+
+/// Registers the devices of the kernel itself.
+pub(super) fn init() {
+    let _ = register("console", &CONSOLE);
+    let _ = register("ttyS0", &SERIAL);
+    let _ = register("null", &NULL);
+}
@@ -0,0 +1,142 @@
+//! File descriptors.
+//!
+//! Each task owns an [`FdTable`] mapping its file descriptors to the files it
+//! opened, along with their offset. Until the kernel runs tasks of its own,
+//! [`current`] is the table of the kernel itself.
+
+use crate::vfs::{File, Inode, VfsError};
+
+/// Maximum number of files opened by a task at the same time.
+pub const MAX_FILES: usize = 16;
+
+/// Index in an [`FdTable`].
+pub type Fd = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenFlags {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl OpenFlags {
+    pub const READ: Self = Self { read: true, write: false };
+    pub const WRITE: Self = Self { read: false, write: true };
+    pub const READ_WRITE: Self = Self { read: true, write: true };
+}
+
+/// Reference point of [`FdTable::seek`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+/// File opened by a task.
+pub struct OpenFile {
+    pub inode: &'static dyn Inode,
+    pub file: &'static dyn File,
+    pub flags: OpenFlags,
+    pub offset: u64,
+}
+
+pub struct FdTable {
+    files: [Option<OpenFile>; MAX_FILES],
+}
+
+impl Default for FdTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FdTable {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            files: [const { None }; MAX_FILES],
+        }
+    }
+
+    /// Stores `file` under the lowest free descriptor, and returns it.
+    ///
+    /// # Errors
+    /// This function returns an error if [`MAX_FILES`] files are already open.
+    pub fn insert(&mut self, file: OpenFile) -> Result<Fd, VfsError> {
+        let fd = self.files.iter().position(Option::is_none).ok_or(VfsError::TooManyFiles)?;
+        self.files[fd] = Some(file);
+        Ok(fd)
+    }
+
+    /// # Errors
+    /// This function returns an error if `fd` is not open.
+    pub fn get(&mut self, fd: Fd) -> Result<&mut OpenFile, VfsError> {
+        self.files.get_mut(fd).and_then(Option::as_mut).ok_or(VfsError::BadFd)
+    }
+
+    /// # Errors
+    /// This function returns an error if `fd` is not open.
+    pub fn close(&mut self, fd: Fd) -> Result<(), VfsError> {
+        self.files.get_mut(fd).and_then(Option::take).map(|_| ()).ok_or(VfsError::BadFd)
+    }
+
+    /// Reads from the offset of `fd` into `buf`, advancing it, and returns the number of bytes
+    /// read, 0 meaning the end of the file.
+    ///
+    /// # Errors
+    /// This function returns an error if `fd` is not open for reading, or if the file fails to
+    /// be read.
+    pub fn read(&mut self, fd: Fd, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let file = self.get(fd)?;
+        if !file.flags.read {
+            return Err(VfsError::PermissionDenied);
+        }
+
+        let len = file.file.read(file.offset, buf)?;
+        file.offset += len as u64;
+        Ok(len)
+    }
+
+    /// Writes `buf` at the offset of `fd`, advancing it, and returns the number of bytes
+    /// written.
+    ///
+    /// # Errors
+    /// This function returns an error if `fd` is not open for writing, or if the file fails to
+    /// be written.
+    pub fn write(&mut self, fd: Fd, buf: &[u8]) -> Result<usize, VfsError> {
+        let file = self.get(fd)?;
+        if !file.flags.write {
+            return Err(VfsError::PermissionDenied);
+        }
+
+        let len = file.file.write(file.offset, buf)?;
+        file.offset += len as u64;
+        Ok(len)
+    }
+
+    /// Moves the offset of `fd`, and returns the new one.
+    ///
+    /// # Errors
+    /// This function returns an error if `fd` is not open, or if the offset would be negative.
+    pub fn seek(&mut self, fd: Fd, pos: SeekFrom) -> Result<u64, VfsError> {
+        let file = self.get(fd)?;
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => file.offset.checked_add_signed(delta),
+            SeekFrom::End(delta) => file.inode.size().checked_add_signed(delta),
+        };
+
+        file.offset = offset.ok_or(VfsError::InvalidSeek)?;
+        Ok(file.offset)
+    }
+}
+
+static mut KERNEL_FILES: FdTable = FdTable::new();
+
+/// Returns the file descriptor table of the running task.
+#[allow(static_mut_refs)]
+pub fn current() -> &'static mut FdTable {
+    // SAFETY:
+    // The kernel is the only task, and it is single threaded.
+    unsafe { &mut KERNEL_FILES }
+}
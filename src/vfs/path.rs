@@ -0,0 +1,78 @@
+//! Absolute paths.
+//!
+//! Paths are resolved lexically: `.` components are dropped and `..` removes
+//! the previous component, stopping at the root, before any filesystem is
+//! looked at.
+
+use crate::vfs::VfsError;
+
+/// Maximum number of components of a path.
+pub const MAX_DEPTH: usize = 16;
+
+/// Normalized absolute path, borrowing its components from the string it was parsed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Path<'a> {
+    components: [&'a str; MAX_DEPTH],
+    len: usize,
+}
+
+impl<'a> Path<'a> {
+    /// Parses `path`, which must be absolute.
+    ///
+    /// # Errors
+    /// This function returns an error if `path` is relative or has more than [`MAX_DEPTH`]
+    /// components once normalized.
+    pub fn parse(path: &'a str) -> Result<Self, VfsError> {
+        let Some(path) = path.strip_prefix('/') else {
+            return Err(VfsError::InvalidPath);
+        };
+
+        let mut res = Self {
+            components: [""; MAX_DEPTH],
+            len: 0,
+        };
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => res.len = res.len.saturating_sub(1),
+                _ => {
+                    *res.components.get_mut(res.len).ok_or(VfsError::InvalidPath)? = component;
+                    res.len += 1;
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    #[must_use]
+    pub fn components(&self) -> &[&'a str] {
+        &self.components[..self.len]
+    }
+
+    /// Returns the components following `prefix`, if it is a prefix of the path.
+    #[must_use]
+    pub fn strip_prefix(&self, prefix: &Path) -> Option<&[&'a str]> {
+        let (head, rest) = self.components().split_at_checked(prefix.len)?;
+        (head == prefix.components()).then_some(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    #[test_case]
+    fn paths_are_normalized() -> Result<(), &'static str> {
+        let path = Path::parse("//dev/./../dev//ttyS0/").map_err(|_| "Could not parse")?;
+        kassert_eq!(path.components(), ["dev", "ttyS0"]);
+        kassert_eq!(Path::parse("/..").map(|p| p.len), Ok(0));
+        kassert!(Path::parse("dev").is_err());
+
+        let dev = Path::parse("/dev").map_err(|_| "Could not parse")?;
+        kassert_eq!(path.strip_prefix(&dev), Some(&["ttyS0"][..]));
+        kassert_eq!(dev.strip_prefix(&path), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+//! Root filesystem.
+//!
+//! Until a disk filesystem can be mounted on `/`, the root is a fixed tree of
+//! empty directories used as mount points.
+
+use crate::vfs::{DirEntry, Filesystem, Inode, InodeKind, VfsError};
+
+/// Directory whose entries are known at compile time.
+pub struct StaticDir {
+    pub entries: &'static [(&'static str, &'static (dyn Inode + Sync))],
+}
+
+impl Inode for StaticDir {
+    fn kind(&self) -> InodeKind {
+        InodeKind::Directory
+    }
+
+    fn lookup(&self, name: &str) -> Result<&'static dyn Inode, VfsError> {
+        self.entries
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, inode)| *inode as &dyn Inode)
+            .ok_or(VfsError::NotFound)
+    }
+
+    fn entry(&self, index: usize) -> Result<Option<DirEntry<'_>>, VfsError> {
+        Ok(self.entries.get(index).map(|(name, inode)| DirEntry { name, kind: inode.kind() }))
+    }
+}
+
+static DEV: StaticDir = StaticDir { entries: &[] };
+static ROOT: StaticDir = StaticDir { entries: &[("dev", &DEV)] };
+
+pub struct RootFs;
+
+impl Filesystem for RootFs {
+    fn name(&self) -> &'static str {
+        "rootfs"
+    }
+
+    fn root(&self) -> &'static dyn Inode {
+        &ROOT
+    }
+}
@@ -3,4 +3,8 @@
 #![warn(clippy::wildcard_enum_match_arm)]
 
 pub mod backend;
+pub mod collections;
+pub mod kasan;
 pub mod kmalloc;
+pub mod oom;
+pub mod vmalloc;
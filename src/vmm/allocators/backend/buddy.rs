@@ -3,7 +3,13 @@ use core::ptr::NonNull;
 use crate::{
     bitmap::StaticBitmap,
     expect_opt,
-    vmm::{allocators::kmalloc::KfreeError, paging::PAGE_SIZE},
+    vmm::{
+        allocators::{
+            kasan::{self, Origin},
+            kmalloc::KfreeError,
+        },
+        paging::PAGE_SIZE,
+    },
 };
 
 pub const BUDDY_ALLOCATOR_SIZE: usize = 1 << 29;
@@ -47,6 +53,17 @@ impl const From<BuddyAllocatorNode> for u8 {
     }
 }
 
+/// Snapshot of the usage of a [`BuddyAllocator`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuddyStats {
+    /// Size of the memory managed by the allocator.
+    pub size: usize,
+    /// Total number of free bytes.
+    pub free: usize,
+    /// Size of the largest block that can currently be allocated.
+    pub largest_free: usize,
+}
+
 const MAX_BUDDY_ALLOCATOR_LEVEL_INDEX: usize = ((1u64 << 32).ilog2() - 4096u64.ilog2()) as usize;
 pub const BUDDY_ALLOCATOR_LEVELS_SIZE: usize = MAX_BUDDY_ALLOCATOR_LEVEL_INDEX + 1;
 
@@ -249,7 +266,35 @@ impl BuddyAllocator {
             .alloc_internal(size, root.as_ptr(), self.size, self.root_level, 0)
             .ok_or(BuddyAllocationError::NotEnoughMemory)?;
 
-        Ok(ptr)
+        // SAFETY:
+        // `ptr` points to a block of `size` bytes we just reserved, which is page-aligned. Its left
+        // redzone is a whole page, so the object handed out is page-aligned as well.
+        Ok(unsafe { kasan::on_alloc(ptr, size - kasan::overhead(Origin::Buddy), Origin::Buddy) })
+    }
+
+    fn stats_internal(&self, stats: &mut BuddyStats, level_block_size: usize, level: usize, index: usize) {
+        let state = self.levels[level].get(index);
+
+        if state == BuddyAllocatorNode::Free as u8 {
+            stats.free += level_block_size;
+            stats.largest_free = stats.largest_free.max(level_block_size);
+        } else if state == BuddyAllocatorNode::PartiallyAllocated as u8 && level < self.levels.len() - 1 {
+            self.stats_internal(stats, level_block_size / 2, level + 1, index * 2);
+            self.stats_internal(stats, level_block_size / 2, level + 1, index * 2 + 1);
+        }
+    }
+
+    /// Walks the tree and returns how much of the managed memory is free.
+    #[must_use]
+    pub fn stats(&self) -> BuddyStats {
+        let mut stats = BuddyStats {
+            size: self.size,
+            ..Default::default()
+        };
+
+        self.stats_internal(&mut stats, self.size, self.root_level, 0);
+
+        stats
     }
 
     /// Gets the base index (level 20, page granularity) for a given `addr`.
@@ -320,26 +365,49 @@ impl BuddyAllocator {
     ///
     /// # Errors
     /// Returns an error when passed a pointer the `BuddyAllocator` does not
-    /// own, not sure about this design yet.
+    /// own, or which does not point to the start of an allocated block (which
+    /// includes blocks that were already freed).
     ///
     /// # Panics
     /// This function will panic if passed invalid arguments, like if `addr` is
     /// null, or if the `BuddyAllocator` is not initialized
-    /// (`self.root.is_none()`).
+    /// (`self.root.is_none()`). With the `kasan` feature, invalid and double
+    /// frees panic instead of returning an error.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn free(&mut self, addr: *const u8) -> Result<(), KfreeError> {
         assert!(!addr.is_null(), "Cannot free null pointer");
-        assert!(self.root.is_some(), "free called on BuddyAllocator without root");
+        let root = expect_opt!(self.root, "free called on BuddyAllocator without root");
 
-        let mut index = self.get_base_index(addr)?;
+        let block = addr.wrapping_sub(kasan::left_redzone(Origin::Buddy));
+        let mut index = self.get_base_index(block)?;
+        let mut block_size = PAGE_SIZE;
 
         for level in (self.root_level..self.levels.len()).rev() {
             if self.levels[level].get(index) == BuddyAllocatorNode::FullyAllocated as u8 {
+                // An allocated ancestor which does not start at `block` means `addr` itself was
+                // never handed out, or was already freed.
+                if root.as_ptr() as usize + index * block_size != block as usize {
+                    break;
+                }
+
+                // SAFETY:
+                // `addr` is the start of a live allocation of `block_size` bytes.
+                let _ = unsafe { kasan::on_free(addr, block_size - kasan::overhead(Origin::Buddy), Origin::Buddy) };
+
                 self.levels[level].set(index, BuddyAllocatorNode::Free as u8);
                 self.coalesce(level, index);
 
                 return Ok(());
             }
             index /= 2;
+            block_size *= 2;
+        }
+
+        if kasan::ENABLED {
+            // SAFETY:
+            // `block` is within the memory managed by this allocator, so the kasan header in front of
+            // `addr` is readable.
+            unsafe { kasan::on_invalid_free(addr, Origin::Buddy) };
         }
 
         Err(KfreeError::InvalidPointer)
@@ -3,7 +3,10 @@ use core::{fmt::Debug, ptr::NonNull};
 use crate::{
     expect_opt,
     vmm::{
-        allocators::kmalloc::{IntrusiveLink, KfreeError, KmallocError, List},
+        allocators::{
+            kasan::{self, Origin},
+            kmalloc::{IntrusiveLink, KfreeError, KmallocError, List},
+        },
         paging::PAGE_SIZE,
     },
 };
@@ -158,13 +161,19 @@ impl<const ORDER: usize> SlabOps for Slab<ORDER> {
         self.free_list_next = unsafe { *allocation.as_ptr() }.next;
         self.allocated += 1;
 
-        Ok(allocation.as_ptr().cast())
+        // SAFETY:
+        // `allocation` is the start of one of this `Slab`'s slots, which were sized by
+        // `kasan::slot_size` in `Slab::init`.
+        Ok(unsafe { kasan::on_alloc(allocation.as_ptr().cast(), self.object_size, Origin::Slab) })
     }
 
     // We cast from `*const u8` to more strictly aligned pointers (`*mut Payload`),
     // however the assertion in the beginning of the function ensures that no
     // pointer is passed that is not at least 8-bytes aligned.
+    //
+    // `addr` is only dereferenced once it is known to be within this `Slab`.
     #[allow(clippy::cast_ptr_alignment)]
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     fn free(&mut self, addr: *const u8) -> Result<(), SlabFreeError> {
         assert!(addr.is_aligned_to(8));
 
@@ -173,15 +182,20 @@ impl<const ORDER: usize> SlabOps for Slab<ORDER> {
             return Err(SlabFreeError::InvalidPointer);
         }
 
+        // SAFETY:
+        // `addr` is within this `Slab`. If it was not handed out by `alloc`, `kasan::on_free` panics
+        // instead of touching the slot.
+        let addr = unsafe { kasan::on_free(addr, self.object_size, Origin::Slab) };
+
         let next = self.free_list_next;
-        self.free_list_next = NonNull::new(addr as *mut Payload);
+        self.free_list_next = NonNull::new(addr.cast::<Payload>());
         self.allocated -= 1;
 
         // SAFETY:
         // If this `Slab` was intialized according to its safety documentation,
         // `addr` is guaranteed to be memory owned by this slab that we can safely
         // access.
-        unsafe { (*(addr as *mut Payload)).next = next };
+        unsafe { (*addr.cast::<Payload>()).next = next };
 
         Ok(())
     }
@@ -200,7 +214,8 @@ impl<const ORDER: usize> SlabOps for Slab<ORDER> {
         let objects_start_addr = unsafe { addr.add(header_overhead) };
 
         let available_space = Self::SLAB_SIZE - header_overhead;
-        let n_objects = available_space / object_size;
+        let slot_size = kasan::slot_size(object_size);
+        let n_objects = available_space / slot_size;
 
         assert!(n_objects > 0, "object_size is too large for order {} slab", ORDER);
 
@@ -222,7 +237,12 @@ impl<const ORDER: usize> SlabOps for Slab<ORDER> {
             // to a valid allocation of at least `0x1000 * Self::ORDER` bytes that we can safely access.
             // The loop is bounded to `n_objects`, which guarantees that no address after
             // `slab_ptr + 0x1000 * Self::ORDER` will be accessed.
-            let next_obj_ptr = unsafe { current_obj_ptr.add(object_size) };
+            let next_obj_ptr = unsafe { current_obj_ptr.add(slot_size) };
+
+            // SAFETY:
+            // `current_obj_ptr` points to a slot of `slot_size` bytes within the allocation, aligned to
+            // at least 8 bytes.
+            unsafe { kasan::init_slot(current_obj_ptr, object_size) };
 
             // We are casting `*const u8` to a more strictly aligned pointer
             // (`*mut *const u8`), however we know that `current_obj_ptr` is
@@ -257,10 +277,24 @@ impl<const ORDER: usize> SlabOps for Slab<ORDER> {
 
     #[inline]
     fn max_objects(&self) -> usize {
-        (Self::SLAB_SIZE - slab_header_overhead::<ORDER>()) / self.object_size
+        (Self::SLAB_SIZE - slab_header_overhead::<ORDER>()) / kasan::slot_size(self.object_size)
     }
 }
 
+/// Snapshot of the usage of a [`SlabCache`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SlabCacheStats {
+    pub object_size: usize,
+    pub slabs: usize,
+    pub full: usize,
+    pub partial: usize,
+    pub empty: usize,
+    /// Total number of objects the cache can hold.
+    pub objects: usize,
+    /// Number of objects currently allocated.
+    pub allocated: usize,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct SlabCache<S: SlabOps> {
     empty_slabs: List<S>,
@@ -343,6 +377,57 @@ where
         }
     }
 
+    /// Moves one of the empty slabs of `self` to `to`, re-initialized for its object size, and
+    /// returns the number of bytes `to` can now hand out.
+    fn move_empty_slab(&mut self, to: &mut Self) -> usize {
+        let Some(mut slab) = self.empty_slabs.take_head() else {
+            return 0;
+        };
+        self.n_slabs -= 1;
+
+        // SAFETY:
+        // `slab` was taken off the empty list, so none of its objects are in use, and it is of the
+        // same order as the slabs of `to`.
+        #[allow(clippy::multiple_unsafe_ops_per_block)]
+        unsafe {
+            S::init(slab.as_ptr(), to.object_size);
+            to.add_slab(slab);
+        }
+
+        // SAFETY:
+        // `slab` was just initialized.
+        unsafe { slab.as_mut() }.max_objects() * to.object_size
+    }
+
+    fn stats(&self) -> SlabCacheStats {
+        let mut stats = SlabCacheStats {
+            object_size: self.object_size,
+            slabs: self.n_slabs,
+            ..Default::default()
+        };
+
+        for (list, count) in [
+            (self.full_slabs, &mut stats.full),
+            (self.partial_slabs, &mut stats.partial),
+            (self.empty_slabs, &mut stats.empty),
+        ] {
+            for slab in list {
+                // SAFETY:
+                // We are calling `as_ref()` on `slab`, which cannot be null due to its type.
+                // The slabs themselves are initialized by the `SlabAllocator`,
+                // which ensures that each allocation is successful before
+                // considering using it as a slab.
+                let slab = unsafe { slab.as_ref() };
+
+                *count += 1;
+                stats.objects += slab.max_objects();
+                stats.allocated += slab.allocated();
+            }
+        }
+
+        stats
+    }
+
     // Freeing is currently very slow, need to find a clean way for the slabs to be
     // sorted by address for O(logn) lookups.
     fn free(&mut self, addr: *const u8) -> Result<(), SlabFreeError> {
@@ -398,6 +483,20 @@ where
                 return Ok(());
             }
         }
+
+        // An object belonging to an empty slab can only be freed twice, let kasan report it along
+        // with its allocation site.
+        if kasan::ENABLED {
+            for mut slab in self.empty_slabs {
+                // SAFETY:
+                // We are calling `as_mut()` on `slab`, which cannot be null due to its type.
+                // The slabs themselves are initialized by the `SlabAllocator`,
+                // which ensures that each allocation is successful before
+                // considering using it as a slab.
+                let _ = unsafe { slab.as_mut() }.free(addr);
+            }
+        }
+
         Err(SlabFreeError::InvalidPointer)
     }
 }
@@ -477,6 +576,38 @@ impl SlabCacheType {
         }
     }
 
+    #[must_use]
+    pub fn stats(&self) -> SlabCacheStats {
+        match self {
+            Self::Order0(cache) => cache.stats(),
+            Self::Order1(cache) => cache.stats(),
+            Self::Order2(cache) => cache.stats(),
+            Self::Order3(cache) => cache.stats(),
+            Self::Order4(cache) => cache.stats(),
+            Self::Order5(cache) => cache.stats(),
+            Self::Order6(cache) => cache.stats(),
+            Self::Order7(cache) => cache.stats(),
+            Self::Order8(cache) => cache.stats(),
+        }
+    }
+
+    /// Moves one of the empty slabs of `self` to `to`, if both hold slabs of the same order.
+    /// Returns the number of bytes `to` can now hand out.
+    pub fn move_empty_slab(&mut self, to: &mut Self) -> usize {
+        match (self, to) {
+            (Self::Order0(from), Self::Order0(to)) => from.move_empty_slab(to),
+            (Self::Order1(from), Self::Order1(to)) => from.move_empty_slab(to),
+            (Self::Order2(from), Self::Order2(to)) => from.move_empty_slab(to),
+            (Self::Order3(from), Self::Order3(to)) => from.move_empty_slab(to),
+            (Self::Order4(from), Self::Order4(to)) => from.move_empty_slab(to),
+            (Self::Order5(from), Self::Order5(to)) => from.move_empty_slab(to),
+            (Self::Order6(from), Self::Order6(to)) => from.move_empty_slab(to),
+            (Self::Order7(from), Self::Order7(to)) => from.move_empty_slab(to),
+            (Self::Order8(from), Self::Order8(to)) => from.move_empty_slab(to),
+            _ => 0,
+        }
+    }
+
     /// # Errors
     /// This function returns an error if `addr` is not managed by `self`.
     pub fn free(&mut self, addr: *const u8) -> Result<(), SlabFreeError> {
@@ -588,11 +719,14 @@ impl SlabAllocator {
         &self.caches
     }
 
-    /// # Errors
-    /// This function will return an error if allocation fails due to
-    /// insufficient memory.
-    pub fn alloc(&mut self, size: usize) -> Result<*mut u8, KmallocError> {
-        let slab_cache_index = if size <= 8 {
+    /// Returns the usage of each cache, ordered by object size.
+    #[must_use]
+    pub fn stats(&self) -> [SlabCacheStats; SLAB_CONFIGS.len()] {
+        self.caches.each_ref().map(SlabCacheType::stats)
+    }
+
+    fn cache_index(size: usize) -> usize {
+        if size <= 8 {
             0
         } else {
             let index = SLAB_CONFIGS
@@ -600,8 +734,35 @@ impl SlabAllocator {
                 .map_windows(|[x, y]| size > x.object_size && size <= y.object_size)
                 .position(|x| x);
             expect_opt!(index, "Called SlabAllocator::alloc with an invalid size") + 1
+        }
+    }
+
+    /// Gives the cache serving objects of `size` bytes an empty slab taken from another cache of
+    /// the same order, and returns the number of bytes it can now hand out. Slabs are carved
+    /// out of a fixed region, so empty slabs are only useful to the other caches.
+    ///
+    /// # Panics
+    /// This function panics if `size` is larger than the largest object size.
+    pub fn shrink_empty(&mut self, size: usize) -> usize {
+        let target = Self::cache_index(size);
+        let (before, rest) = self.caches.split_at_mut(target);
+        let Some((to, after)) = rest.split_first_mut() else {
+            return 0;
         };
 
+        before
+            .iter_mut()
+            .chain(after)
+            .map(|from| from.move_empty_slab(to))
+            .find(|&bytes| bytes > 0)
+            .unwrap_or_default()
+    }
+
+    /// # Errors
+    /// This function will return an error if allocation fails due to
+    /// insufficient memory.
+    pub fn alloc(&mut self, size: usize) -> Result<*mut u8, KmallocError> {
+        let slab_cache_index = Self::cache_index(size);
         let ptr = self.caches[slab_cache_index].alloc().map_err(|_| KmallocError::NotEnoughMemory)?;
 
         Ok(ptr)
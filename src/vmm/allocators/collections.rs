@@ -0,0 +1,216 @@
+//! Fallible counterparts to the `alloc` collections.
+//!
+//! Allocations made through the global allocator end up in
+//! [`oom::out_of_memory`](super::oom::out_of_memory) when they cannot be
+//! satisfied. [`KBox`] and [`KVec`] allocate through [`Kmalloc`] instead, and
+//! hand a [`KmallocError`] back to the caller, which can then decide how to
+//! degrade.
+//!
+//! ```ignore
+//! let mut v = KVec::new();
+//! if v.try_push(42).is_err() {
+//!     printkln!("not enough memory, dropping packet");
+//! }
+//! ```
+
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    fmt,
+    ops::{Deref, DerefMut},
+    panic::Location,
+    ptr::NonNull,
+};
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::vmm::allocators::kmalloc::{self, KmallocError, kfree};
+
+/// [`Allocator`] backed by [`kmalloc`](kmalloc::kmalloc), reporting failures to the caller
+/// instead of aborting.
+///
+/// Allocations are recorded at the site passed to the `#[track_caller]` methods of [`KBox`] and
+/// [`KVec`], or in this file when the allocator is used directly.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Kmalloc;
+
+/// Site of the allocation being made through [`KBox`] or [`KVec`]. The `Allocator` methods that
+/// `alloc` calls cannot be `#[track_caller]`, so their caller's location is left here for
+/// [`Kmalloc::allocate`] to pick up.
+static mut SITE: Option<&'static Location<'static>> = None;
+
+/// Runs `f`, recording the allocations it makes through [`Kmalloc`] at `site`.
+fn allocating_at<R>(site: &'static Location<'static>, f: impl FnOnce() -> R) -> R {
+    // SAFETY:
+    // The kernel is single threaded, and no reference to `SITE` is ever taken.
+    let previous = unsafe { SITE };
+    // SAFETY:
+    // Same as above. The previous site is put back before returning, for nested allocations.
+    unsafe { SITE = Some(site) };
+    let res = f();
+    // SAFETY:
+    // Same as above.
+    unsafe { SITE = previous };
+    res
+}
+
+// SAFETY:
+// Blocks returned by `kmalloc` stay valid until passed to `kfree`, and `Kmalloc` is a ZST, so
+// all of its copies share the same allocator.
+unsafe impl Allocator for Kmalloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // SAFETY:
+        // The kernel is single threaded, and no reference to `SITE` is ever taken.
+        let site = unsafe { SITE }.unwrap_or(Location::caller());
+        let ptr = kmalloc::alloc_aligned(layout.size(), layout.align(), site.into()).map_err(|_| AllocError)?;
+        let ptr = NonNull::new(ptr).ok_or(AllocError)?;
+
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, _layout: Layout) {
+        // SAFETY:
+        // As per `Allocator::deallocate`'s contract, `ptr` was allocated by this allocator.
+        assert!(unsafe { kfree(ptr.as_ptr()) }.is_ok());
+    }
+}
+
+/// Heap allocated `T`, whose allocation failures are reported to the caller.
+pub struct KBox<T>(Box<T, Kmalloc>);
+
+impl<T> KBox<T> {
+    /// # Errors
+    /// This function returns an error if there is not enough memory to hold a `T`.
+    #[track_caller]
+    pub fn try_new(value: T) -> Result<Self, KmallocError> {
+        let b = allocating_at(Location::caller(), || Box::try_new_in(value, Kmalloc));
+        Ok(Self(b.map_err(|_| KmallocError::NotEnoughMemory)?))
+    }
+
+    #[must_use]
+    pub fn into_inner(b: Self) -> T {
+        *b.0
+    }
+}
+
+impl<T> Deref for KBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for KBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for KBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Growable array, whose allocation failures are reported to the caller.
+///
+/// Only the operations that may allocate are wrapped, everything else is
+/// available through its `Deref<Target = [T]>` implementation.
+pub struct KVec<T>(Vec<T, Kmalloc>);
+
+impl<T> KVec<T> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Vec::new_in(Kmalloc))
+    }
+
+    /// # Errors
+    /// This function returns an error if there is not enough memory to hold `capacity` elements.
+    #[track_caller]
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, KmallocError> {
+        let mut v = Self::new();
+        v.try_reserve(capacity)?;
+
+        Ok(v)
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// # Errors
+    /// This function returns an error if there is not enough memory for the new capacity.
+    #[track_caller]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), KmallocError> {
+        allocating_at(Location::caller(), || self.0.try_reserve(additional)).map_err(|_| KmallocError::NotEnoughMemory)
+    }
+
+    /// Appends `value` to the back of the vector.
+    ///
+    /// # Errors
+    /// This function returns an error if the vector needed to grow but there
+    /// was not enough memory. In that case, the vector is left untouched.
+    #[track_caller]
+    pub fn try_push(&mut self, value: T) -> Result<(), KmallocError> {
+        self.try_reserve(1)?;
+        self.0.push(value);
+
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+}
+
+impl<T: Clone> KVec<T> {
+    /// Appends all elements of `other` to the back of the vector.
+    ///
+    /// # Errors
+    /// This function returns an error if the vector needed to grow but there
+    /// was not enough memory. In that case, the vector is left untouched.
+    #[track_caller]
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), KmallocError> {
+        self.try_reserve(other.len())?;
+        self.0.extend_from_slice(other);
+
+        Ok(())
+    }
+}
+
+impl<T> Default for KVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for KVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for KVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for KVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
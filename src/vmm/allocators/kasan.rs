@@ -0,0 +1,466 @@
+//! Kernel address sanitizer for the slab and buddy allocators.
+//!
+//! When the `kasan` feature is enabled, every object handed out by the backends lives in a slot
+//! surrounded by redzones:
+//! ```
+//! | left redzone | Header | object (`capacity` bytes) | right redzone |
+//! ^ slot                  ^ pointer returned to the caller
+//! ```
+//! The [`Header`] always takes the last [`REDZONE_SIZE`] bytes in front of the object. Slab slots
+//! have nothing else on their left, buddy blocks get a whole page so that their objects stay
+//! page-aligned like the blocks themselves, see [`left_redzone`].
+//! Freed objects are filled with [`FREE_POISON`], redzones (and the unused tail of an object, once
+//! [`annotate`] told us how many bytes were actually requested) with [`REDZONE_POISON`]. Both
+//! patterns are verified when the object is freed and when it is handed out again, which catches
+//! out-of-bounds writes, writes after free and double frees. Violations panic with the site the
+//! object was allocated from.
+//!
+//! Without the feature, [`REDZONE_SIZE`] is 0 and all hooks compile down to returning the pointer
+//! they were given, so the backends do not need to be littered with `cfg`s.
+
+use core::{fmt, panic::Location};
+
+use crate::vmm::paging::PAGE_SIZE;
+
+pub const ENABLED: bool = cfg!(feature = "kasan");
+
+const KASAN_REDZONE_SIZE: usize = 32;
+
+/// Size of the right redzone of an object, and of the part of its left redzone holding the
+/// [`Header`].
+pub const REDZONE_SIZE: usize = if ENABLED { KASAN_REDZONE_SIZE } else { 0 };
+
+/// Pattern freed objects are filled with.
+pub const FREE_POISON: u8 = 0x6b;
+
+/// Pattern redzones are filled with.
+pub const REDZONE_POISON: u8 = 0xbb;
+
+const MAGIC_ALLOCATED: u32 = 0xa110_c8ed;
+const MAGIC_FREED: u32 = 0xf4ee_d00d;
+
+/// Number of return addresses recorded for allocations made through the global allocator.
+pub const RETURN_DEPTH: usize = 3;
+
+/// Where an allocation was requested from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocSite {
+    /// Source location captured through `#[track_caller]` by `kmalloc`, or by the `try_*`
+    /// methods of the [collections](super::collections).
+    Location(&'static Location<'static>),
+    /// Return addresses of the code that called into the global allocator, innermost first, and
+    /// 0 past the end of the chain. Growing a `Vec` goes through a couple of out-of-line `alloc`
+    /// functions before reaching the code that pushed, hence more than one.
+    Return([usize; RETURN_DEPTH]),
+}
+
+impl From<&'static Location<'static>> for AllocSite {
+    fn from(location: &'static Location<'static>) -> Self {
+        Self::Location(location)
+    }
+}
+
+impl fmt::Display for AllocSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Location(location) => write!(f, "{location}"),
+            Self::Return(addresses) => {
+                for (depth, address) in addresses.iter().take_while(|&&a| a != 0).enumerate() {
+                    write!(f, "{}{address:#010x}", if depth == 0 { "" } else { " <- " })?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Metadata stored at the start of each slot.
+#[repr(C)]
+struct Header {
+    /// Reserved for the slab allocator's intrusive free list, must stay the first field.
+    link: usize,
+    magic: u32,
+    /// Usable bytes between the redzones.
+    capacity: usize,
+    /// Bytes actually requested, `capacity` until [`annotate`] is called.
+    size: usize,
+    site: Option<AllocSite>,
+}
+
+const _: () = assert!(size_of::<Header>() <= KASAN_REDZONE_SIZE);
+
+/// Which backend a slot belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// Slab slots are poisoned as soon as the slab is initialized, so their state is always known.
+    Slab,
+    /// Buddy blocks may be split and merged between allocations, so poison is only verified when
+    /// the exact same block is reused.
+    Buddy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    DoubleFree,
+    InvalidFree,
+    /// A redzone was overwritten, `offset` is relative to the start of the object.
+    OutOfBounds {
+        offset: isize,
+    },
+    /// A freed object was written to, `offset` is relative to the start of the object.
+    UseAfterFree {
+        offset: usize,
+    },
+    /// A slot taken from a free list was not marked as free.
+    CorruptedSlot,
+}
+
+/// Returns the size of the redzone in front of the objects of `origin`.
+#[must_use]
+pub const fn left_redzone(origin: Origin) -> usize {
+    match origin {
+        Origin::Slab => REDZONE_SIZE,
+        Origin::Buddy if ENABLED => PAGE_SIZE,
+        Origin::Buddy => 0,
+    }
+}
+
+/// Returns the number of bytes a slot of `origin` takes on top of its object.
+#[must_use]
+pub const fn overhead(origin: Origin) -> usize {
+    left_redzone(origin) + REDZONE_SIZE
+}
+
+/// Returns the size of a slab slot holding an object of `capacity` bytes.
+#[must_use]
+pub const fn slot_size(capacity: usize) -> usize {
+    capacity + overhead(Origin::Slab)
+}
+
+/// Returns the header of the object at `object`.
+///
+/// # Safety
+/// `object` must be preceded by [`REDZONE_SIZE`] bytes of its slot.
+#[allow(clippy::cast_ptr_alignment)]
+unsafe fn header_of<'a>(object: *const u8) -> &'a mut Header {
+    // SAFETY:
+    // The header takes the last `REDZONE_SIZE` bytes in front of the object, which the backends
+    // keep aligned for it.
+    unsafe { &mut *object.wrapping_sub(REDZONE_SIZE).cast::<Header>().cast_mut() }
+}
+
+/// Returns the offset of the first byte in `start..start + len` which does not match `pattern`.
+///
+/// # Safety
+/// `start` must be valid for reads of `len` bytes.
+unsafe fn find_mismatch(start: *const u8, len: usize, pattern: u8) -> Option<usize> {
+    // SAFETY:
+    // The caller guarantees `start` is valid for reads of `len` bytes.
+    let bytes = unsafe { core::slice::from_raw_parts(start, len) };
+    bytes.iter().position(|b| *b != pattern)
+}
+
+/// # Safety
+/// `start` must be valid for writes of `len` bytes.
+unsafe fn fill(start: *mut u8, len: usize, pattern: u8) {
+    // SAFETY:
+    // The caller guarantees `start` is valid for writes of `len` bytes.
+    unsafe { core::ptr::write_bytes(start, pattern, len) };
+}
+
+/// Verifies both redzones of the object at `object`, as well as its tail past `size`.
+///
+/// # Safety
+/// `object` must be the object of a slot of `origin` with a valid [`Header`], spanning
+/// `capacity` bytes plus the redzones.
+unsafe fn verify_redzones(object: *mut u8, origin: Origin, header: &Header) -> Result<(), Violation> {
+    let left = left_redzone(origin);
+    let slot = object.wrapping_sub(left);
+
+    // SAFETY:
+    // The left redzone spans `left` bytes from `slot`, the header takes up its last
+    // `REDZONE_SIZE` bytes.
+    if let Some(i) = unsafe { find_mismatch(slot, left - REDZONE_SIZE, REDZONE_POISON) } {
+        return Err(Violation::OutOfBounds {
+            offset: i.cast_signed() - left.cast_signed(),
+        });
+    }
+    let after_header = REDZONE_SIZE - size_of::<Header>();
+    // SAFETY:
+    // The bytes between the header and the object are redzone.
+    if let Some(i) = unsafe { find_mismatch(object.wrapping_sub(after_header), after_header, REDZONE_POISON) } {
+        return Err(Violation::OutOfBounds {
+            offset: i.cast_signed() - after_header.cast_signed(),
+        });
+    }
+
+    let right_len = header.capacity - header.size + REDZONE_SIZE;
+    // SAFETY:
+    // Everything from the end of the requested bytes to the end of the slot is redzone.
+    if let Some(i) = unsafe { find_mismatch(object.wrapping_add(header.size), right_len, REDZONE_POISON) } {
+        return Err(Violation::OutOfBounds {
+            offset: (header.size + i).cast_signed(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Reports `violation` for the object at `ptr` and panics.
+fn report(violation: Violation, ptr: *const u8, origin: Origin, header: &Header) -> ! {
+    match header.site {
+        Some(site) => panic!(
+            "kasan: {:?} on {:?} object {:#010x} ({} bytes) allocated at {}",
+            violation, origin, ptr as usize, header.size, site
+        ),
+        None => panic!(
+            "kasan: {:?} on {:?} object {:#010x} ({} bytes) allocated at <unknown>",
+            violation, origin, ptr as usize, header.size
+        ),
+    }
+}
+
+/// Poisons a fresh slot and marks it as free. Called for each object when a slab is initialized.
+///
+/// # Safety
+/// `slot` must be valid for writes of [`slot_size`]`(capacity)` bytes and suitably aligned for a
+/// [`Header`].
+#[allow(clippy::cast_ptr_alignment)]
+pub unsafe fn init_slot(slot: *mut u8, capacity: usize) {
+    if !ENABLED {
+        return;
+    }
+
+    // SAFETY:
+    // The caller guarantees `slot` spans `slot_size(capacity)` bytes and is aligned for a `Header`.
+    #[allow(clippy::multiple_unsafe_ops_per_block)]
+    unsafe {
+        fill(slot, REDZONE_SIZE, REDZONE_POISON);
+        fill(slot.add(REDZONE_SIZE), capacity, FREE_POISON);
+        fill(slot.add(REDZONE_SIZE + capacity), REDZONE_SIZE, REDZONE_POISON);
+        slot.cast::<Header>().write(Header {
+            link: 0,
+            magic: MAGIC_FREED,
+            capacity,
+            size: capacity,
+            site: None,
+        });
+    }
+}
+
+/// Hands out the object in `slot`, verifying that it was not touched since it was freed. Returns
+/// the pointer to give to the caller.
+///
+/// # Safety
+/// `slot` must be valid for reads and writes of `capacity` bytes plus the [`overhead`] of
+/// `origin`, and suitably aligned for a [`Header`].
+#[must_use]
+pub unsafe fn on_alloc(slot: *mut u8, capacity: usize, origin: Origin) -> *mut u8 {
+    if !ENABLED {
+        return slot;
+    }
+
+    // SAFETY:
+    // The object starts after the left redzone, within the slot.
+    let object = unsafe { slot.add(left_redzone(origin)) };
+    // SAFETY:
+    // The header is in the left redzone, which the caller guarantees is valid and aligned.
+    let header = unsafe { header_of(object) };
+
+    let reused = header.magic == MAGIC_FREED && header.capacity == capacity;
+    if reused {
+        // SAFETY:
+        // `header` describes this very slot, which spans the object and its redzones.
+        if let Err(violation) = unsafe { verify_redzones(object, origin, header) } {
+            report(violation, object, origin, header);
+        }
+        // SAFETY:
+        // The object spans `capacity` bytes.
+        if let Some(offset) = unsafe { find_mismatch(object, capacity, FREE_POISON) } {
+            report(Violation::UseAfterFree { offset }, object, origin, header);
+        }
+    } else if origin == Origin::Slab {
+        report(Violation::CorruptedSlot, object, origin, header);
+    }
+
+    // SAFETY:
+    // The redzones and the header are within the slot.
+    #[allow(clippy::multiple_unsafe_ops_per_block)]
+    unsafe {
+        fill(slot, left_redzone(origin), REDZONE_POISON);
+        fill(object.add(capacity), REDZONE_SIZE, REDZONE_POISON);
+        *header_of(object) = Header {
+            link: 0,
+            magic: MAGIC_ALLOCATED,
+            capacity,
+            size: capacity,
+            site: None,
+        };
+    }
+
+    object
+}
+
+/// Verifies the redzones of the object at `ptr` and poisons it. Returns the start of its slot.
+///
+/// # Panics
+/// This function panics if the object was already freed, if `ptr` does not point to the start of
+/// an object, or if any of its redzones were overwritten.
+///
+/// # Safety
+/// `ptr` must have been returned by [`on_alloc`] for an object of `capacity` bytes.
+#[must_use]
+pub unsafe fn on_free(ptr: *const u8, capacity: usize, origin: Origin) -> *mut u8 {
+    if !ENABLED {
+        return ptr.cast_mut();
+    }
+
+    let slot = ptr.wrapping_sub(left_redzone(origin)).cast_mut();
+    // SAFETY:
+    // As per this function's safety contract, `ptr` is preceded by its `Header`.
+    let header = unsafe { header_of(ptr) };
+
+    match header.magic {
+        MAGIC_ALLOCATED if header.capacity == capacity => {}
+        MAGIC_FREED => report(Violation::DoubleFree, ptr, origin, header),
+        _ => report(Violation::InvalidFree, ptr, origin, header),
+    }
+
+    // SAFETY:
+    // `header` describes this very slot, which spans the object and its redzones.
+    if let Err(violation) = unsafe { verify_redzones(ptr.cast_mut(), origin, header) } {
+        report(violation, ptr, origin, header);
+    }
+
+    // SAFETY:
+    // The object spans `capacity` bytes.
+    unsafe { fill(ptr.cast_mut(), capacity, FREE_POISON) };
+    header.magic = MAGIC_FREED;
+    header.size = capacity;
+
+    slot
+}
+
+/// Called on a free the backend could not match to a live allocation. Panics with the most
+/// precise diagnostic available.
+///
+/// # Safety
+/// `ptr` must point `REDZONE_SIZE` bytes past readable memory large enough for a [`Header`].
+pub unsafe fn on_invalid_free(ptr: *const u8, origin: Origin) -> ! {
+    // SAFETY:
+    // The caller guarantees the header in front of `ptr` is readable.
+    let header = unsafe { header_of(ptr) };
+
+    if header.magic == MAGIC_FREED {
+        report(Violation::DoubleFree, ptr, origin, header);
+    }
+    report(Violation::InvalidFree, ptr, origin, header);
+}
+
+/// Records the requested `size` and the allocation `site` of the object at `ptr`, and turns the
+/// bytes between `size` and the object's capacity into redzone.
+///
+/// # Panics
+/// This function panics if `size` exceeds the object's capacity.
+///
+/// # Safety
+/// `ptr` must have been returned by [`on_alloc`].
+pub unsafe fn annotate(ptr: *mut u8, size: usize, site: AllocSite) {
+    if !ENABLED {
+        return;
+    }
+
+    // SAFETY:
+    // As per this function's safety contract, `ptr` is preceded by its `Header`.
+    let header = unsafe { header_of(ptr) };
+    assert!(size <= header.capacity, "kasan: annotated size exceeds the object's capacity");
+
+    header.size = size;
+    header.site = Some(site);
+
+    // SAFETY:
+    // `size..capacity` is within the object.
+    unsafe { fill(ptr.wrapping_add(size), header.capacity - size, REDZONE_POISON) };
+}
+
+#[allow(clippy::undocumented_unsafe_blocks)]
+#[allow(clippy::multiple_unsafe_ops_per_block)]
+#[allow(clippy::cast_ptr_alignment)]
+#[cfg(all(test, feature = "kasan"))]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+
+    const CAPACITY: usize = 64;
+
+    #[repr(C, align(8))]
+    struct MockSlot([u8; slot_size(CAPACITY)]);
+
+    #[test_case]
+    fn fresh_slot_is_poisoned() -> Result<(), &'static str> {
+        let mut slot = MockSlot([0; slot_size(CAPACITY)]);
+        let slot = slot.0.as_mut_ptr();
+
+        unsafe { init_slot(slot, CAPACITY) };
+        let object = unsafe { on_alloc(slot, CAPACITY, Origin::Slab) };
+
+        kassert_eq!(object, unsafe { slot.add(REDZONE_SIZE) });
+        kassert!(unsafe { find_mismatch(object, CAPACITY, FREE_POISON) }.is_none());
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn overflow_into_tail_is_detected() -> Result<(), &'static str> {
+        let mut slot = MockSlot([0; slot_size(CAPACITY)]);
+        let slot = slot.0.as_mut_ptr();
+
+        unsafe { init_slot(slot, CAPACITY) };
+        let object = unsafe { on_alloc(slot, CAPACITY, Origin::Slab) };
+        unsafe { annotate(object, 10, Location::caller().into()) };
+
+        let header = unsafe { &*slot.cast::<Header>() };
+        kassert!(unsafe { verify_redzones(object, Origin::Slab, header) }.is_ok());
+
+        unsafe { *object.add(10) = 0 };
+        kassert_eq!(
+            unsafe { verify_redzones(object, Origin::Slab, header) },
+            Err(Violation::OutOfBounds { offset: 10 })
+        );
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn underflow_is_detected() -> Result<(), &'static str> {
+        let mut slot = MockSlot([0; slot_size(CAPACITY)]);
+        let slot = slot.0.as_mut_ptr();
+
+        unsafe { init_slot(slot, CAPACITY) };
+        let object = unsafe { on_alloc(slot, CAPACITY, Origin::Slab) };
+
+        unsafe { *object.sub(1) = 0 };
+        let header = unsafe { &*slot.cast::<Header>() };
+        kassert_eq!(
+            unsafe { verify_redzones(object, Origin::Slab, header) },
+            Err(Violation::OutOfBounds { offset: -1 })
+        );
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn free_poisons_object() -> Result<(), &'static str> {
+        let mut slot = MockSlot([0; slot_size(CAPACITY)]);
+        let slot = slot.0.as_mut_ptr();
+
+        unsafe { init_slot(slot, CAPACITY) };
+        let object = unsafe { on_alloc(slot, CAPACITY, Origin::Slab) };
+        unsafe { core::ptr::write_bytes(object, 0x42, CAPACITY) };
+
+        kassert_eq!(unsafe { on_free(object, CAPACITY, Origin::Slab) }, slot);
+        kassert!(unsafe { find_mismatch(object, CAPACITY, FREE_POISON) }.is_none());
+
+        Ok(())
+    }
+}
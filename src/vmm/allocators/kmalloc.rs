@@ -1,9 +1,14 @@
 use crate::{
     buddy_allocator_levels,
+    stack_print_serial::return_address,
     vmm::{
-        allocators::backend::{
-            buddy::{BUDDY_ALLOCATOR_SIZE, BuddyAllocator},
-            slab::{SLAB_CONFIGS, SlabAllocator},
+        allocators::{
+            backend::{
+                buddy::{BUDDY_ALLOCATOR_SIZE, BuddyAllocator},
+                slab::{SLAB_CONFIGS, SlabAllocator},
+            },
+            kasan::{self, AllocSite, Origin, RETURN_DEPTH},
+            oom,
         },
         paging::{
             Access, PAGE_SIZE, Permissions,
@@ -12,8 +17,10 @@ use crate::{
     },
 };
 
-use core::{alloc::GlobalAlloc, ptr::NonNull};
+use core::{alloc::GlobalAlloc, panic::Location, ptr::NonNull};
 
+#[cfg(feature = "leak-tracking")]
+pub mod leak;
 mod list;
 mod state;
 
@@ -48,10 +55,43 @@ pub struct KernelAllocator {
 ///   * _Ideally_, the IDT should also be initialized ([`kfs::arch::x86::idt::init`]) in order to
 ///     catch possible page faults
 unsafe impl GlobalAlloc for KernelAllocator {
+    // Inlined into the `__rust_alloc` shim, whose return address is the code that allocated.
+    #[inline(always)]
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        let size = layout.size().max(layout.align());
+        if layout.align() > PAGE_SIZE {
+            return core::ptr::null_mut();
+        }
+        let mut addresses = [0; RETURN_DEPTH];
+        for (depth, address) in addresses.iter_mut().enumerate() {
+            *address = return_address(depth).unwrap_or_default();
+        }
+        let site = AllocSite::Return(addresses);
 
-        kmalloc(size).unwrap_or_default()
+        // Failures are reported by `alloc_error`, unless the caller handles them itself, like
+        // `Vec::try_reserve` does.
+        alloc_aligned(layout.size(), layout.align(), site).unwrap_or_default()
+    }
+
+    // Inlined into the `__rust_realloc` shim as well, for `alloc` to see the code that grows the
+    // allocation.
+    #[inline(always)]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: core::alloc::Layout, new_size: usize) -> *mut u8 {
+        // SAFETY:
+        // As per `GlobalAlloc::realloc`'s contract, `new_size` rounded up to the alignment does not
+        // overflow.
+        let new_layout = unsafe { core::alloc::Layout::from_size_align_unchecked(new_size, layout.align()) };
+        // SAFETY:
+        // `new_layout` has a non-zero size, like `layout`.
+        let new_ptr = unsafe { self.alloc(new_layout) };
+        if !new_ptr.is_null() {
+            // SAFETY:
+            // Both blocks hold at least the smallest of the two sizes, and are distinct.
+            unsafe { core::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size)) };
+            // SAFETY:
+            // `ptr` was allocated by this allocator with `layout`, and is not used anymore.
+            unsafe { self.dealloc(ptr, layout) };
+        }
+        new_ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, _layout: core::alloc::Layout) {
@@ -63,10 +103,19 @@ unsafe impl GlobalAlloc for KernelAllocator {
     }
 }
 
+/// Called when an allocation made through the `alloc` API fails and the caller does not handle
+/// it.
+#[alloc_error_handler]
+fn alloc_error(layout: core::alloc::Layout) -> ! {
+    let size = layout.size().max(layout.align());
+
+    oom::out_of_memory(size, backend_for_aligned(size, layout.align()))
+}
+
 #[cfg(all(not(test), not(feature = "test-utils")))]
 #[global_allocator]
 #[allow(clippy::multiple_unsafe_ops_per_block)]
-static mut KERNEL_ALLOCATOR: KernelAllocator = KernelAllocator {
+pub(crate) static mut KERNEL_ALLOCATOR: KernelAllocator = KernelAllocator {
     // SAFETY:
     // - We are creating references to static mutable variables. We make this safe by ensuring that the buddy allocator is the sole owner of these references,
     //   and they are never touched by anything without going through the buddy allocator's API.
@@ -112,26 +161,108 @@ pub unsafe fn kfree(addr: *const u8) -> Result<(), KfreeError> {
         allocator.slab_allocator.free(addr)
     } else {
         allocator.buddy_allocator.free(addr)
+    }?;
+
+    #[cfg(feature = "leak-tracking")]
+    leak::untrack(addr);
+
+    Ok(())
+}
+
+/// Alignment of the objects handed out by the slab allocator.
+pub const SLAB_ALIGN: usize = 8;
+
+/// Returns the backend serving allocations of `size` bytes.
+#[must_use]
+pub const fn backend_for(size: usize) -> Origin {
+    match size {
+        0..=2048 => Origin::Slab,
+        2049.. => Origin::Buddy,
     }
 }
 
+/// If the allocation fails, the shrinkers registered in [`oom`] are run, and it
+/// is retried for as long as they manage to release memory.
+///
+/// With the `kasan` feature, the caller's location is recorded as the
+/// allocation site and reported on invalid accesses to the allocation. With the
+/// `leak-tracking` feature, it is recorded in [`leak`] until the allocation is
+/// freed.
+///
 /// # Errors
 /// This function will return an error if it fails to find a sufficiently large
-/// block of memory for the allocation.
-#[allow(static_mut_refs)]
+/// block of memory for the allocation, or if `size` exceeds the size of the
+/// buddy allocator.
+#[track_caller]
 pub fn kmalloc(size: usize) -> Result<*mut u8, KmallocError> {
+    alloc_from(size, backend_for(size), Location::caller().into())
+}
+
+/// Like [`kmalloc`], but the returned pointer is aligned to `align`, which must be a power of
+/// two.
+///
+/// # Errors
+/// This function will return an error if the allocation fails, or if `align` is larger than a
+/// page.
+#[track_caller]
+pub fn kmalloc_aligned(size: usize, align: usize) -> Result<*mut u8, KmallocError> {
+    alloc_aligned(size, align, Location::caller().into())
+}
+
+/// Like [`kmalloc_aligned`], on behalf of `site`.
+pub(super) fn alloc_aligned(size: usize, align: usize, site: AllocSite) -> Result<*mut u8, KmallocError> {
+    if align > PAGE_SIZE {
+        return Err(KmallocError::NotEnoughMemory);
+    }
+    let size = size.max(align);
+
+    alloc_from(size, backend_for_aligned(size, align), site)
+}
+
+/// Returns the backend serving allocations of `size` bytes aligned to `align`. Slab objects are
+/// only guaranteed to be [`SLAB_ALIGN`]-aligned, while buddy objects are page-aligned.
+const fn backend_for_aligned(size: usize, align: usize) -> Origin {
+    if align > SLAB_ALIGN { Origin::Buddy } else { backend_for(size) }
+}
+
+/// Allocates `size` bytes from `origin` on behalf of `site`, see [`kmalloc`].
+#[allow(static_mut_refs)]
+fn alloc_from(size: usize, origin: Origin, site: AllocSite) -> Result<*mut u8, KmallocError> {
     // SAFETY:
     // We are accessing a static mutable allocator, which is only accessible through this crate.
     // The API of this crate ensures we are not touching it outside of its expected usage.
     let allocator = unsafe { &mut KERNEL_ALLOCATOR };
 
-    match size {
-        0..=2048 => allocator.slab_allocator.alloc(size).map_err(|_| KmallocError::NotEnoughMemory),
-        2049.. => allocator
-            .buddy_allocator
-            .alloc(1 << ((size - 1).ilog2() + 1))
-            .map_err(|_| KmallocError::NotEnoughMemory),
-    }
+    let ptr = loop {
+        let allocation = match origin {
+            Origin::Slab => allocator.slab_allocator.alloc(size).map_err(|_| KmallocError::NotEnoughMemory),
+            Origin::Buddy => {
+                let block_size = size
+                    .checked_add(kasan::overhead(Origin::Buddy))
+                    .and_then(usize::checked_next_power_of_two)
+                    .map(|block_size| block_size.max(PAGE_SIZE))
+                    .filter(|&block_size| block_size <= BUDDY_ALLOCATOR_SIZE)
+                    .ok_or(KmallocError::NotEnoughMemory)?;
+
+                allocator.buddy_allocator.alloc(block_size).map_err(|_| KmallocError::NotEnoughMemory)
+            }
+        };
+
+        match allocation {
+            Ok(ptr) => break ptr,
+            Err(e) if oom::reclaim(size) == 0 => return Err(e),
+            Err(_) => {}
+        }
+    };
+
+    // SAFETY:
+    // `ptr` was just handed out by one of the backends, with a capacity of at least `size` bytes.
+    unsafe { kasan::annotate(ptr, size, site) };
+
+    #[cfg(feature = "leak-tracking")]
+    leak::track(ptr, size, site);
+
+    Ok(ptr)
 }
 
 /// Direct access to buddy allocator for testing purposes.
@@ -228,5 +359,25 @@ pub fn init() -> Result<(), KmallocError> {
         unsafe { &mut KERNEL_ALLOCATOR },
     )?;
 
+    let _ = oom::register_shrinker(EMPTY_SLABS_SHRINKER);
+
     Ok(())
 }
+
+/// Hands empty slabs over to the slab cache that ran out of objects.
+pub const EMPTY_SLABS_SHRINKER: oom::Shrinker = oom::Shrinker {
+    name: "empty slabs",
+    reclaim: shrink_empty_slabs,
+};
+
+#[allow(static_mut_refs)]
+fn shrink_empty_slabs(size: usize) -> usize {
+    if backend_for(size) != Origin::Slab {
+        return 0;
+    }
+
+    // SAFETY:
+    // We are accessing a static mutable allocator, which is only accessible through this crate.
+    // Shrinkers run from `kmalloc` while it is not using the slab allocator.
+    unsafe { KERNEL_ALLOCATOR.slab_allocator.shrink_empty(size) }
+}
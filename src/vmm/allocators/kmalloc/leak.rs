@@ -0,0 +1,199 @@
+//! Bookkeeping of live `kmalloc` allocations, used to assert that a piece of
+//! code does not leak.
+//!
+//! Every successful [`kmalloc`](super::kmalloc) inserts a [`Allocation`] into a
+//! fixed-size table, and every successful [`kfree`](super::kfree) removes it.
+//! The table lives in static memory so that tracking never recurses into the
+//! allocator.
+//!
+//! Allocations are stamped with a monotonic generation, which makes a
+//! [`Snapshot`] a single counter: the allocations that survived since the
+//! snapshot are exactly the live ones with a newer generation.
+//!
+//! ```ignore
+//! let snapshot = leak::snapshot();
+//! do_something();
+//! assert!(snapshot.leaks().count() == 0);
+//! ```
+
+use core::fmt;
+
+pub use crate::vmm::allocators::kasan::AllocSite;
+
+/// Maximum number of allocations that can be tracked at the same time. Allocations made
+/// while the table is full are counted in [`dropped`] instead.
+pub const MAX_TRACKED: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    pub ptr: usize,
+    pub size: usize,
+    pub site: AllocSite,
+    generation: u64,
+}
+
+impl fmt::Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes at {:#010x} allocated at {}", self.size, self.ptr, self.site)
+    }
+}
+
+struct Tracker {
+    entries: [Option<Allocation>; MAX_TRACKED],
+    /// One past the highest slot that was ever used, bounds the scans.
+    high_water: usize,
+    generation: u64,
+    dropped: usize,
+}
+
+static mut TRACKER: Tracker = Tracker {
+    entries: [None; MAX_TRACKED],
+    high_water: 0,
+    generation: 0,
+    dropped: 0,
+};
+
+#[allow(static_mut_refs)]
+fn tracker() -> &'static mut Tracker {
+    // SAFETY:
+    // The tracker is only reachable through this module, whose callers run with the same
+    // exclusivity guarantees as the allocator itself.
+    unsafe { &mut TRACKER }
+}
+
+/// Records a new live allocation.
+pub(super) fn track(ptr: *const u8, size: usize, site: AllocSite) {
+    let tracker = tracker();
+
+    let generation = tracker.generation;
+    tracker.generation += 1;
+
+    let Some(index) = tracker.entries.iter().position(Option::is_none) else {
+        tracker.dropped += 1;
+        return;
+    };
+
+    tracker.entries[index] = Some(Allocation {
+        ptr: ptr as usize,
+        size,
+        site,
+        generation,
+    });
+    tracker.high_water = tracker.high_water.max(index + 1);
+}
+
+/// Removes `ptr` from the live allocations, if it was tracked.
+pub(super) fn untrack(ptr: *const u8) {
+    let tracker = tracker();
+
+    if let Some(entry) = tracker.entries[..tracker.high_water]
+        .iter_mut()
+        .find(|e| e.is_some_and(|a| a.ptr == ptr as usize))
+    {
+        *entry = None;
+    }
+}
+
+/// Marker of the allocator state at a point in time, see [`snapshot`].
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    generation: u64,
+    dropped: usize,
+}
+
+/// Takes a snapshot of the currently outstanding allocations.
+#[must_use]
+pub fn snapshot() -> Snapshot {
+    let tracker = tracker();
+
+    Snapshot {
+        generation: tracker.generation,
+        dropped: tracker.dropped,
+    }
+}
+
+impl Snapshot {
+    /// Returns the allocations made after this snapshot was taken that are still live.
+    pub fn leaks(&self) -> impl Iterator<Item = Allocation> + use<> {
+        let generation = self.generation;
+        let tracker = tracker();
+
+        tracker.entries[..tracker.high_water]
+            .iter()
+            .filter_map(move |e| e.filter(|a| a.generation >= generation))
+    }
+
+    /// Returns the number of allocations made after this snapshot that could not be tracked
+    /// because the table was full. If this is not zero, [`Snapshot::leaks`] may be incomplete.
+    #[must_use]
+    pub fn untracked(&self) -> usize {
+        tracker().dropped - self.dropped
+    }
+}
+
+/// Returns the number of allocations that could not be tracked because the table was full.
+#[must_use]
+pub fn dropped() -> usize {
+    tracker().dropped
+}
+
+/// Runs `f` and returns its result, or the first allocation made by `f` that
+/// was not freed by the time it returned.
+///
+/// # Errors
+/// This function returns an error if any allocation made during `f` is still
+/// live once it returns.
+pub fn check<R>(f: impl FnOnce() -> R) -> Result<R, Allocation> {
+    let snapshot = snapshot();
+    let ret = f();
+
+    match snapshot.leaks().next() {
+        Some(leak) => Err(leak),
+        None => Ok(ret),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kassert, kassert_eq};
+    use core::panic::Location;
+
+    #[test_case]
+    fn freed_allocation_is_not_a_leak() -> Result<(), &'static str> {
+        let snapshot = snapshot();
+
+        track(0xdead_0000 as *const u8, 16, Location::caller().into());
+        untrack(0xdead_0000 as *const u8);
+
+        kassert_eq!(snapshot.leaks().count(), 0);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn live_allocation_is_reported() -> Result<(), &'static str> {
+        let snapshot = snapshot();
+
+        track(0xdead_0000 as *const u8, 16, Location::caller().into());
+        let leak = snapshot.leaks().next();
+        untrack(0xdead_0000 as *const u8);
+
+        kassert!(leak.is_some_and(|l| l.ptr == 0xdead_0000 && l.size == 16));
+        kassert_eq!(snapshot.leaks().count(), 0);
+
+        Ok(())
+    }
+
+    #[test_case]
+    fn older_allocations_are_ignored() -> Result<(), &'static str> {
+        track(0xdead_0000 as *const u8, 16, Location::caller().into());
+        let snapshot = snapshot();
+        let leaks = snapshot.leaks().count();
+        untrack(0xdead_0000 as *const u8);
+
+        kassert_eq!(leaks, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Out-of-memory handling.
+//!
+//! Subsystems holding memory they can give back (caches, pools, ...) register a
+//! [`Shrinker`] with [`register_shrinker`]. When an allocation fails,
+//! [`kmalloc`](super::kmalloc::kmalloc) runs the shrinkers through [`reclaim`]
+//! and retries as long as they manage to free something.
+//!
+//! If the allocation still cannot be satisfied, `kmalloc` returns an error, and
+//! the global allocator a null pointer. Allocations made through the `alloc`
+//! API which do not handle the failure themselves end up in [`out_of_memory`],
+//! which prints a report of the allocator state and panics.
+
+use crate::{
+    pr_emerg,
+    vmm::allocators::{
+        backend::{buddy::BuddyStats, slab::SlabCacheStats},
+        kasan::Origin,
+        kmalloc::KERNEL_ALLOCATOR,
+    },
+};
+
+/// Maximum number of shrinkers that can be registered.
+pub const MAX_SHRINKERS: usize = 16;
+
+#[derive(Debug)]
+pub enum OomError {
+    TooManyShrinkers,
+}
+
+/// A callback able to release memory back to the kernel allocator.
+#[derive(Clone, Copy)]
+pub struct Shrinker {
+    pub name: &'static str,
+
+    /// Tries to release memory in order to satisfy an allocation of `size` bytes, and returns
+    /// the number of bytes that were freed.
+    pub reclaim: fn(size: usize) -> usize,
+}
+
+static mut SHRINKERS: [Option<Shrinker>; MAX_SHRINKERS] = [None; MAX_SHRINKERS];
+
+/// Bytes released by each shrinker, on the last call to [`reclaim`] and in total.
+static mut RECLAIMED: [(usize, usize); MAX_SHRINKERS] = [(0, 0); MAX_SHRINKERS];
+
+/// Set while the shrinkers are running, so that an allocation failing inside of a shrinker
+/// does not recurse into them.
+static mut RECLAIMING: bool = false;
+
+/// Registers `shrinker` to be run whenever an allocation fails.
+///
+/// # Errors
+/// This function returns an error if [`MAX_SHRINKERS`] shrinkers are already registered.
+#[allow(static_mut_refs)]
+pub fn register_shrinker(shrinker: Shrinker) -> Result<(), OomError> {
+    // SAFETY:
+    // `SHRINKERS` is only accessed through this module, which is not reentrant.
+    let shrinkers = unsafe { &mut SHRINKERS };
+
+    let slot = shrinkers.iter_mut().find(|s| s.is_none()).ok_or(OomError::TooManyShrinkers)?;
+    *slot = Some(shrinker);
+
+    Ok(())
+}
+
+/// Runs all registered shrinkers, and returns the total number of bytes they released.
+#[allow(static_mut_refs)]
+pub fn reclaim(size: usize) -> usize {
+    // SAFETY:
+    // `RECLAIMING` is only accessed through this function.
+    if unsafe { RECLAIMING } {
+        return 0;
+    }
+
+    // SAFETY:
+    // `RECLAIMING` is only accessed through this function.
+    unsafe { RECLAIMING = true };
+
+    // SAFETY:
+    // `SHRINKERS` is only accessed through this module, and copied out before running any
+    // shrinker, which may register new ones.
+    let shrinkers = unsafe { SHRINKERS };
+    let mut reclaimed = 0;
+    for (index, shrinker) in shrinkers.iter().enumerate() {
+        let Some(shrinker) = shrinker else { continue };
+
+        let bytes = (shrinker.reclaim)(size);
+        // SAFETY:
+        // `RECLAIMED` is only accessed through this module, and no shrinker is running anymore.
+        let (last, total) = unsafe { &mut RECLAIMED[index] };
+        *last = bytes;
+        *total += bytes;
+        reclaimed += bytes;
+    }
+
+    // SAFETY:
+    // `RECLAIMING` is only accessed through this function.
+    unsafe { RECLAIMING = false };
+
+    reclaimed
+}
+
+/// Prints the state of the kernel allocator after failing to allocate `size`
+/// bytes from `origin`, and panics.
+///
+/// # Panics
+/// This function always panics.
+#[allow(static_mut_refs)]
+pub fn out_of_memory(size: usize, origin: Origin) -> ! {
+    // SAFETY:
+    // We are only reading the allocator's bookkeeping, nothing is allocated past this point.
+    let allocator = unsafe { &KERNEL_ALLOCATOR };
+
+    pr_emerg!("Out of memory: failed to allocate {} bytes from the {:?} allocator", size, origin);
+
+    pr_emerg!(
+        "{:>8} {:>6} {:>6} {:>7} {:>6} {:>8} {:>8}",
+        "size",
+        "slabs",
+        "full",
+        "partial",
+        "empty",
+        "objects",
+        "in use"
+    );
+    for SlabCacheStats {
+        object_size,
+        slabs,
+        full,
+        partial,
+        empty,
+        objects,
+        allocated,
+    } in allocator.slab_allocator.stats()
+    {
+        pr_emerg!(
+            "{:>8} {:>6} {:>6} {:>7} {:>6} {:>8} {:>8}",
+            object_size,
+            slabs,
+            full,
+            partial,
+            empty,
+            objects,
+            allocated
+        );
+    }
+
+    let BuddyStats {
+        size: total,
+        free,
+        largest_free,
+    } = allocator.buddy_allocator.stats();
+    pr_emerg!("buddy: {} of {} bytes free, largest free block: {} bytes", free, total, largest_free);
+
+    // SAFETY:
+    // `SHRINKERS` is only accessed through this module, and no shrinker is running.
+    let shrinkers = unsafe { SHRINKERS };
+    // SAFETY:
+    // `RECLAIMED` is only accessed through this module, and no shrinker is running.
+    let reclaimed = unsafe { RECLAIMED };
+    for (shrinker, (last, total)) in shrinkers.iter().zip(reclaimed) {
+        if let Some(shrinker) = shrinker {
+            pr_emerg!("shrinker {}: {} bytes reclaimed on the last pass, {} in total", shrinker.name, last, total);
+        }
+    }
+
+    panic!("out of memory");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kassert_eq;
+
+    static mut REQUESTED: usize = 0;
+
+    fn record_request(size: usize) -> usize {
+        // SAFETY:
+        // Tests run sequentially.
+        unsafe { REQUESTED = size };
+        0
+    }
+
+    #[test_case]
+    fn reclaim_runs_shrinkers() -> Result<(), &'static str> {
+        register_shrinker(Shrinker {
+            name: "record",
+            reclaim: record_request,
+        })
+        .map_err(|_| "Could not register shrinker")?;
+
+        kassert_eq!(reclaim(4096), 0);
+        // SAFETY:
+        // Tests run sequentially.
+        kassert_eq!(unsafe { REQUESTED }, 4096);
+
+        Ok(())
+    }
+}
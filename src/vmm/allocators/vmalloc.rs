@@ -0,0 +1,84 @@
+//! Virtually contiguous allocations.
+//!
+//! [`kmalloc`](super::kmalloc::kmalloc) serves large allocations from the
+//! buddy allocator, rounding them up to a power of two of physically contiguous
+//! memory. [`vmalloc`] instead reserves a virtual range in the kernel half and
+//! backs each of its pages with whichever physical frame is free, so that a
+//! 600KiB buffer only costs 150 frames.
+//!
+//! The memory is not physically contiguous, so it must not be handed to
+//! devices doing DMA. `kmalloc` remains the choice for small allocations and
+//! physically contiguous buffers.
+
+use crate::vmm::paging::{
+    Access, PAGE_SIZE, Permissions,
+    mmap::{Mode, mmap, munmap},
+};
+
+/// Maximum number of live `vmalloc` areas.
+pub const MAX_VMALLOC_AREAS: usize = 64;
+
+#[derive(Debug)]
+pub enum VmallocError {
+    /// There are not enough free frames, or no large enough virtual range.
+    NotEnoughMemory,
+    /// [`MAX_VMALLOC_AREAS`] areas are already live.
+    TooManyAreas,
+    SizeIsZero,
+}
+
+#[derive(Debug)]
+pub enum VfreeError {
+    InvalidPointer,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct VmArea {
+    addr: usize,
+    size: usize,
+}
+
+static mut AREAS: [Option<VmArea>; MAX_VMALLOC_AREAS] = [None; MAX_VMALLOC_AREAS];
+
+#[allow(static_mut_refs)]
+fn areas() -> &'static mut [Option<VmArea>; MAX_VMALLOC_AREAS] {
+    // SAFETY:
+    // `AREAS` is only reachable through this module, which is not reentrant.
+    unsafe { &mut AREAS }
+}
+
+/// Allocates `size` bytes of virtually contiguous memory, rounded up to a
+/// multiple of `PAGE_SIZE`.
+///
+/// # Errors
+/// This function returns an error if `size` is 0, if there is not enough free
+/// memory, or if [`MAX_VMALLOC_AREAS`] areas are already live.
+pub fn vmalloc(size: usize) -> Result<*mut u8, VmallocError> {
+    if size == 0 {
+        return Err(VmallocError::SizeIsZero);
+    }
+
+    let slot = areas().iter_mut().find(|a| a.is_none()).ok_or(VmallocError::TooManyAreas)?;
+
+    let size = size.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    let addr = mmap(None, size, Permissions::ReadWrite, Access::Root, &Mode::Scattered).map_err(|_| VmallocError::NotEnoughMemory)?;
+
+    *slot = Some(VmArea { addr, size });
+
+    Ok(addr as *mut u8)
+}
+
+/// Unmaps the area starting at `addr` and releases its frames.
+///
+/// # Errors
+/// This function returns an error if `addr` was not returned by [`vmalloc`],
+/// or was already freed.
+pub fn vfree(addr: *const u8) -> Result<(), VfreeError> {
+    let slot = areas()
+        .iter_mut()
+        .find(|a| a.is_some_and(|a| a.addr == addr as usize))
+        .ok_or(VfreeError::InvalidPointer)?;
+    let area = slot.take().ok_or(VfreeError::InvalidPointer)?;
+
+    munmap(area.addr, area.size).map_err(|_| VfreeError::InvalidPointer)
+}
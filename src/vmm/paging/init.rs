@@ -14,7 +14,7 @@ unsafe extern "C" {
     pub static KERNEL_END: u8;
 }
 
-fn invalidate(vaddr: usize) {
+pub(crate) fn invalidate(vaddr: usize) {
     unsafe { core::arch::asm!("invlpg [{}]", in(reg) vaddr) };
 }
 
@@ -4,7 +4,8 @@ use crate::{
         MEMORY_MAX,
         paging::{
             Access, PAGE_SIZE, Permissions,
-            page_entries::PageTableEntry,
+            init::invalidate,
+            page_entries::{PageDirectoryEntry, PageTableEntry},
             state::{self, KERNEL_PAGE_TABLES, USED_PAGES},
         },
     },
@@ -65,8 +66,12 @@ fn pages_physical_iter() -> impl Iterator<Item = (usize, &'static mut Option<Acc
     unsafe { USED_PAGES.iter_mut().enumerate() }
 }
 
-fn pages_physical_free_iter(pages_needed: usize, _mode: &Mode) -> Result<impl Iterator<Item = (usize, &'static mut Option<Access>)>, MmapError> {
-    let _lets_see = pages_physical_iter();
+fn pages_physical_free_from(start: usize, pages_needed: usize) -> impl Iterator<Item = (usize, &'static mut Option<Access>)> {
+    pages_physical_iter().skip(start).filter(|(_, p)| (**p).is_none()).take(pages_needed)
+}
+
+/// Returns the index of the first run of `pages_needed` free physical pages.
+fn pages_physical_continous_start(pages_needed: usize) -> Result<usize, MmapError> {
     let mut i = 0;
     loop {
         if i >= pages_physical_iter().count() {
@@ -74,7 +79,7 @@ fn pages_physical_free_iter(pages_needed: usize, _mode: &Mode) -> Result<impl It
         }
         let pages_physical = pages_physical_iter().skip(i).take(pages_needed).filter(|(_, p)| (**p).is_none());
         if pages_physical.count() == pages_needed {
-            return Ok(pages_physical_iter().skip(i).take(pages_needed));
+            return Ok(i);
         } else {
             match pages_physical_iter().skip(i).take(pages_needed).filter(|(_, p)| p.is_some()).last() {
                 Some((x, _)) => i = x + 1,
@@ -88,6 +93,22 @@ fn pages_physical_free_iter(pages_needed: usize, _mode: &Mode) -> Result<impl It
     Err(MmapError::NotEnoughMemory)
 }
 
+/// With [`Mode::Continous`], the returned pages are physically contiguous. With
+/// [`Mode::Scattered`], they are the first `pages_needed` free pages, wherever
+/// they are.
+fn pages_physical_free_iter(pages_needed: usize, mode: &Mode) -> Result<impl Iterator<Item = (usize, &'static mut Option<Access>)>, MmapError> {
+    let start = match mode {
+        Mode::Continous => pages_physical_continous_start(pages_needed)?,
+        Mode::Scattered => 0,
+    };
+
+    if pages_physical_free_from(start, pages_needed).count() != pages_needed {
+        return Err(MmapError::NotEnoughMemory);
+    }
+
+    Ok(pages_physical_free_from(start, pages_needed))
+}
+
 #[derive(Debug)]
 pub enum VirtToPhysError {
     PageNotPresent,
@@ -127,6 +148,27 @@ pub fn virt_to_phys(vaddr: usize) -> Result<usize, VirtToPhysError> {
     }
 }
 
+/// Returns the entries translating `vaddr`: its page directory entry, and its page
+/// table entry unless the directory entry is not present or maps a 4MB page.
+#[must_use]
+#[allow(static_mut_refs)]
+pub fn page_entries(vaddr: usize) -> (&'static PageDirectoryEntry, Option<&'static PageTableEntry>) {
+    let page_directory_index = vaddr >> 22;
+    let page_table_index = (vaddr >> 12) & 0x3FF;
+
+    // SAFETY:
+    // The page tables are only modified by `mmap` and `munmap`, which cannot run while the
+    // returned references are used, the kernel being single threaded.
+    unsafe {
+        let page_directory_entry = &state::KERNEL_PAGE_DIRECTORY_TABLE.0[page_directory_index];
+        if page_directory_entry.present() == 0 || page_directory_entry.ps() == 1 {
+            return (page_directory_entry, None);
+        }
+
+        (page_directory_entry, Some(&KERNEL_PAGE_TABLES[page_directory_index].0[page_table_index]))
+    }
+}
+
 /// # Errors
 /// todo @fbruggem
 #[allow(static_mut_refs)]
@@ -198,6 +240,8 @@ pub fn munmap(vaddr: usize, size: usize) -> Result<(), MunmapError> {
             }
             *page_table_entry = PageTableEntry::empty();
         }
+
+        invalidate(vaddr);
     }
     Ok(())
 }
@@ -1,3 +1,5 @@
+use core::fmt;
+
 pub const PAGE_DIRECTORY_SIZE: usize = 1024;
 #[repr(align(0x1000))]
 pub struct PageDirectory(pub [PageDirectoryEntry; PAGE_DIRECTORY_SIZE]);
@@ -40,6 +42,39 @@ impl PageDirectoryEntry {
     }
 }
 
+/// Writes the names of the set flags, or `-` if there are none.
+fn write_flags(f: &mut fmt::Formatter<'_>, flags: &[(&str, u8)]) -> fmt::Result {
+    let mut first = true;
+    for (name, _) in flags.iter().filter(|(_, set)| *set == 1) {
+        write!(f, "{}{name}", if first { "" } else { " " })?;
+        first = false;
+    }
+    if first {
+        write!(f, "-")?;
+    }
+    Ok(())
+}
+
+/// Shows the raw entry, the frame it points to and its flags, like
+/// `0x00400083 frame 0x00400 P RW PS`.
+impl fmt::Display for PageDirectoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x} frame {:#07x} ", self.0, self.address())?;
+        write_flags(
+            f,
+            &[
+                ("P", self.present()),
+                ("RW", self.read_write()),
+                ("US", self.user_supervisor()),
+                ("PWT", self.write_through()),
+                ("PCD", self.cache_disable()),
+                ("A", self.accessed()),
+                ("PS", self.ps()),
+            ],
+        )
+    }
+}
+
 #[bitstruct::bitstruct]
 pub struct PageTableEntry {
     address: u20,
@@ -73,3 +108,25 @@ impl PageTableEntry {
         Self(0)
     }
 }
+
+/// Shows the raw entry, the frame it points to and its flags, like
+/// `0x00123063 frame 0x00123 P RW A D`.
+impl fmt::Display for PageTableEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x} frame {:#07x} ", self.0, self.address())?;
+        write_flags(
+            f,
+            &[
+                ("P", self.present()),
+                ("RW", self.read_write()),
+                ("US", self.user_supervisor()),
+                ("PWT", self.write_through()),
+                ("PCD", self.cache_disable()),
+                ("A", self.accessed()),
+                ("D", self.dirty()),
+                ("PAT", self.page_attribute_table()),
+                ("G", self.global()),
+            ],
+        )
+    }
+}
@@ -8,38 +8,249 @@
 
 use core::panic::PanicInfo;
 
-use kfs::alloc::vec::Vec;
+use kfs::alloc::{boxed::Box, vec::Vec};
 use kfs::boot::MultibootInfo;
-use kfs::vmm::allocators::kmalloc::{kfree, kmalloc};
+use kfs::vmm::allocators::{
+    collections::{KBox, KVec},
+    kasan::AllocSite,
+    kmalloc::{self, KERNEL_ALLOCATOR, kfree, kmalloc, leak},
+    oom,
+};
 use kfs::{
     alloc::string::String,
     vmm::{self, allocators::backend::buddy::BUDDY_ALLOCATOR_SIZE, paging::PAGE_SIZE},
 };
-use kfs::{kassert, kassert_eq};
+use kfs::{kassert, kassert_eq, serial_println};
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     kfs::tester::panic_handler(info)
 }
 
-#[test_case]
-fn move_to_next_slabs() -> Result<(), &'static str> {
-    let mut ps = [core::ptr::null(); 16];
+/// Runs `f` and fails if any allocation it made is still live once it returns.
+fn assert_no_leaks<F: FnOnce() -> Result<(), &'static str>>(f: F) -> Result<(), &'static str> {
+    let snapshot = leak::snapshot();
+    f()?;
 
-    // 256 bytes objects are stored on order 0 slabs, which means one slab contains a maximum of
-    // 15 allocations. The 16th allocation should successfully move to the next slab instead of
-    // failing.
-    for p in ps.iter_mut() {
-        *p = kmalloc(256).map_err(|_| "Could not allocate")?;
+    let mut leaked = false;
+    for allocation in snapshot.leaks() {
+        serial_println!("leaked {}", allocation);
+        leaked = true;
     }
 
-    for p in ps {
-        let _ = unsafe { kfree(p) };
+    kassert!(!leaked, "Allocations survived the block");
+    kassert_eq!(snapshot.untracked(), 0);
+
+    Ok(())
+}
+
+#[test_case]
+fn no_leaks_after_drop() -> Result<(), &'static str> {
+    assert_no_leaks(|| {
+        let mut v = Vec::new();
+        for i in 0..PAGE_SIZE {
+            v.push(i);
+        }
+        let s = String::from("no leaks here");
+        core::hint::black_box((&v, &s));
+
+        Ok(())
+    })
+}
+
+#[test_case]
+fn leak_is_detected() -> Result<(), &'static str> {
+    let snapshot = leak::snapshot();
+    let p = kmalloc(64).map_err(|_| "Could not allocate")?;
+
+    let leaks = snapshot.leaks().filter(|a| a.ptr == p as usize && a.size == 64).count();
+    let _ = unsafe { kfree(p) };
+
+    kassert_eq!(leaks, 1);
+    kassert_eq!(snapshot.leaks().count(), 0);
+
+    Ok(())
+}
+
+#[test_case]
+fn global_allocations_record_their_call_site() -> Result<(), &'static str> {
+    let snapshot = leak::snapshot();
+    let first = Box::new(1u32);
+    let second = Box::new(2u32);
+    core::hint::black_box((&first, &second));
+
+    let site_of = |b: &u32| snapshot.leaks().find(|a| a.ptr == core::ptr::from_ref(b) as usize).map(|a| a.site);
+    let (first_site, second_site) = (site_of(&first), site_of(&second));
+
+    kassert!(matches!(first_site, Some(AllocSite::Return([address, ..])) if address != 0));
+    kassert!(matches!(second_site, Some(AllocSite::Return([address, ..])) if address != 0));
+    kassert!(first_site != second_site, "Both allocations were recorded at the same site");
+
+    Ok(())
+}
+
+#[test_case]
+fn growing_vecs_record_their_call_site() -> Result<(), &'static str> {
+    let snapshot = leak::snapshot();
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+    first.push(1u32);
+    second.push(2u32);
+    core::hint::black_box((&first, &second));
+
+    let site_of = |v: &Vec<u32>| snapshot.leaks().find(|a| a.ptr == v.as_ptr() as usize).map(|a| a.site);
+    kassert!(matches!(site_of(&first), Some(AllocSite::Return(_))));
+    kassert!(site_of(&first) != site_of(&second), "Both pushes were recorded at the same site");
+
+    // Growing again reallocates, from two other sites.
+    let (first_site, second_site) = (site_of(&first), site_of(&second));
+    first.extend_from_slice(&[0; 64]);
+    second.extend_from_slice(&[0; 64]);
+    core::hint::black_box((&first, &second));
+    kassert!(site_of(&first) != first_site);
+    kassert!(site_of(&first) != site_of(&second), "Both reallocations were recorded at the same site");
+    kassert!(site_of(&second) != second_site);
+
+    Ok(())
+}
+
+#[test_case]
+fn collections_record_their_call_site() -> Result<(), &'static str> {
+    let snapshot = leak::snapshot();
+    let first = KVec::<u32>::try_with_capacity(4).map_err(|_| "Could not allocate")?;
+    let mut second = KVec::new();
+    second.try_push(2u32).map_err(|_| "Could not allocate")?;
+    let third = KBox::try_new(3u32).map_err(|_| "Could not allocate")?;
+
+    let site_of = |ptr: *const u32| snapshot.leaks().find(|a| a.ptr == ptr as usize).map(|a| a.site);
+    let sites = [site_of(first.as_ptr()), site_of(second.as_ptr()), site_of(&raw const *third)];
+
+    let mut lines = [0; 3];
+    for (line, site) in lines.iter_mut().zip(sites) {
+        let Some(AllocSite::Location(location)) = site else {
+            return Err("Allocation not recorded at a source location");
+        };
+        kassert_eq!(location.file(), file!());
+        *line = location.line();
     }
+    kassert!(lines[0] != lines[1] && lines[1] != lines[2], "Allocations were recorded on the same line");
+
+    Ok(())
+}
+
+static mut SHRINKER_CALLS: usize = 0;
+
+fn count_shrinker_calls(_size: usize) -> usize {
+    unsafe { SHRINKER_CALLS += 1 };
+    0
+}
+
+#[test_case]
+fn shrinkers_run_before_failing() -> Result<(), &'static str> {
+    oom::register_shrinker(oom::Shrinker {
+        name: "count",
+        reclaim: count_shrinker_calls,
+    })
+    .map_err(|_| "Could not register shrinker")?;
+
+    let half = kmalloc(BUDDY_ALLOCATOR_SIZE / 2).map_err(|_| "Could not allocate")?;
+    let calls = unsafe { SHRINKER_CALLS };
+
+    let res = kmalloc(BUDDY_ALLOCATOR_SIZE / 2 + 1);
+    let _ = unsafe { kfree(half) };
+
+    kassert!(res.is_err());
+    kassert_eq!(unsafe { SHRINKER_CALLS }, calls + 1);
 
     Ok(())
 }
 
+#[test_case]
+fn failed_global_allocation_can_be_handled() -> Result<(), &'static str> {
+    let mut v = Vec::<u8>::new();
+
+    kassert!(v.try_reserve(BUDDY_ALLOCATOR_SIZE).is_err());
+    kassert!(v.try_reserve(PAGE_SIZE).is_ok());
+
+    Ok(())
+}
+
+#[test_case]
+#[allow(static_mut_refs)]
+fn empty_slabs_move_to_exhausted_cache() -> Result<(), &'static str> {
+    let slabs = || unsafe { KERNEL_ALLOCATOR.slab_allocator.stats() }.map(|s| s.slabs);
+    let before = slabs();
+
+    let reclaimed = (kmalloc::EMPTY_SLABS_SHRINKER.reclaim)(8);
+    let after = slabs();
+
+    kassert!(reclaimed > 0);
+    kassert_eq!(after[0], before[0] + 1);
+    kassert_eq!(after.iter().sum::<usize>(), before.iter().sum::<usize>());
+    kassert_eq!((kmalloc::EMPTY_SLABS_SHRINKER.reclaim)(PAGE_SIZE), 0);
+
+    Ok(())
+}
+
+#[test_case]
+fn try_push_grows() -> Result<(), &'static str> {
+    assert_no_leaks(|| {
+        let mut v = KVec::new();
+        for i in 0..PAGE_SIZE {
+            v.try_push(i).map_err(|_| "Could not push")?;
+        }
+
+        kassert_eq!(v.len(), PAGE_SIZE);
+        kassert!(v.iter().enumerate().all(|(idx, e)| idx == *e));
+
+        Ok(())
+    })
+}
+
+#[test_case]
+fn try_new_boxes_value() -> Result<(), &'static str> {
+    assert_no_leaks(|| {
+        let b = KBox::try_new([7u8; 512]).map_err(|_| "Could not allocate")?;
+
+        kassert!(b.iter().all(|e| *e == 7));
+        kassert_eq!(KBox::into_inner(b)[511], 7);
+
+        Ok(())
+    })
+}
+
+#[test_case]
+fn try_reserve_fails_gracefully() -> Result<(), &'static str> {
+    let mut v = KVec::<u8>::new();
+    v.try_push(1).map_err(|_| "Could not push")?;
+
+    kassert!(v.try_reserve(BUDDY_ALLOCATOR_SIZE * 2).is_err());
+    kassert!(KVec::<u8>::try_with_capacity(BUDDY_ALLOCATOR_SIZE * 2).is_err());
+    kassert_eq!(&v[..], &[1]);
+
+    Ok(())
+}
+
+#[test_case]
+fn move_to_next_slabs() -> Result<(), &'static str> {
+    assert_no_leaks(|| {
+        let mut ps = [core::ptr::null(); 16];
+
+        // 256 bytes objects are stored on order 0 slabs, which means one slab contains a maximum of
+        // 15 allocations. The 16th allocation should successfully move to the next slab instead of
+        // failing.
+        for p in ps.iter_mut() {
+            *p = kmalloc(256).map_err(|_| "Could not allocate")?;
+        }
+
+        for p in ps {
+            let _ = unsafe { kfree(p) };
+        }
+
+        Ok(())
+    })
+}
+
 #[test_case]
 fn alloc_string() -> Result<(), &'static str> {
     let mut s = String::from("a");
@@ -51,6 +262,8 @@ fn alloc_string() -> Result<(), &'static str> {
     Ok(())
 }
 
+// Redzones push each allocation into the next buddy order.
+#[cfg(not(feature = "kasan"))]
 #[test_case]
 fn consecutive_allocations() -> Result<(), &'static str> {
     let p1 = Vec::<u8>::with_capacity(PAGE_SIZE);
@@ -140,6 +353,29 @@ fn stress_test_many_allocations() -> Result<(), &'static str> {
     Ok(())
 }
 
+#[test_case]
+fn alignment_is_respected() -> Result<(), &'static str> {
+    #[repr(align(64))]
+    struct Line([u8; 16]);
+    #[repr(align(4096))]
+    struct Page([u8; 32]);
+
+    let small = Box::new(0u64);
+    let line = Box::new(Line([0; 16]));
+    let page = Box::new(Page([0; 32]));
+    let large = Vec::<u8>::with_capacity(PAGE_SIZE * 2);
+    let fallible = KBox::try_new(Line([0; 16])).map_err(|_| "Could not allocate")?;
+
+    kassert!((&raw const *small).is_aligned());
+    kassert!((&raw const *line).is_aligned());
+    kassert!((&raw const *page).is_aligned());
+    kassert!((large.as_ptr() as usize).is_multiple_of(PAGE_SIZE));
+    kassert!((&raw const *fallible).is_aligned());
+    kassert_eq!(line.0[0] + page.0[0] + fallible.0[0], 0);
+
+    Ok(())
+}
+
 #[test_case]
 fn memory_corruption() -> Result<(), &'static str> {
     for size in [8, 64, 256, 2048] {
@@ -163,7 +399,7 @@ fn memory_corruption() -> Result<(), &'static str> {
 #[cfg(test)]
 #[unsafe(no_mangle)]
 pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
-    use kfs::{arch, qemu, serial_println, vmm::paging::init::init_memory};
+    use kfs::{arch, qemu, vmm::paging::init::init_memory};
 
     serial_println!("");
 
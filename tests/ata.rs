@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![test_runner(kfs::tester::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use kfs::ata;
+use kfs::block::{self, SECTOR_SIZE};
+use kfs::boot::MultibootInfo;
+use kfs::{kassert, kassert_eq, serial_println};
+
+/// Size of the disk image attached by the test runner as the primary master.
+const DISK_SECTORS: u64 = 16 * 1024 * 1024 / SECTOR_SIZE as u64;
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    kfs::tester::panic_handler(info)
+}
+
+#[test_case]
+fn primary_master_is_identified() -> Result<(), &'static str> {
+    let drive = ata::drives().next().ok_or("No drive found")?;
+    kassert_eq!(drive.identity().sectors, DISK_SECTORS);
+    kassert!(!drive.identity().model().is_empty());
+
+    let disk = block::find("hda").ok_or("Drive not registered")?;
+    kassert_eq!(disk.sector_count(), DISK_SECTORS);
+
+    Ok(())
+}
+
+#[test_case]
+fn sectors_round_trip() -> Result<(), &'static str> {
+    let disk = block::find("hda").ok_or("Drive not registered")?;
+    let lba = disk.sector_count() - 2;
+
+    let mut original = [0; 2 * SECTOR_SIZE];
+    disk.read(lba, &mut original).map_err(|_| "Could not read")?;
+
+    let mut pattern = [0; 2 * SECTOR_SIZE];
+    for (i, byte) in pattern.iter_mut().enumerate() {
+        *byte = i as u8 ^ 0x5a;
+    }
+    disk.write(lba, &pattern).map_err(|_| "Could not write")?;
+
+    let mut read = [0; 2 * SECTOR_SIZE];
+    disk.read(lba, &mut read).map_err(|_| "Could not read back")?;
+    kassert_eq!(read, pattern);
+
+    disk.write(lba, &original).map_err(|_| "Could not restore")?;
+    kassert!(disk.read(disk.sector_count(), &mut read[..SECTOR_SIZE]).is_err());
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[unsafe(no_mangle)]
+pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
+    use kfs::{arch, pci, qemu, vmm, vmm::paging::init::init_memory};
+
+    serial_println!("");
+
+    arch::x86::gdt::init();
+    arch::x86::idt::init();
+
+    init_memory(info);
+
+    if vmm::allocators::kmalloc::init().is_err() {
+        panic!("Failed to initialize dynamic memory allocation");
+    }
+    pci::init();
+    block::init();
+    ata::init();
+
+    test_main();
+
+    unsafe { qemu::exit(qemu::ExitCode::Success) };
+}
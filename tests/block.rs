@@ -0,0 +1,209 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![test_runner(kfs::tester::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::{
+    cell::{Cell, RefCell},
+    panic::PanicInfo,
+};
+
+use kfs::alloc::{boxed::Box, vec, vec::Vec};
+use kfs::block::{
+    self, BlockDevice, BlockError, SECTOR_SIZE,
+    cache::{self, MAX_BUFFERS},
+    partition,
+};
+use kfs::boot::MultibootInfo;
+use kfs::vmm::{self, allocators::oom};
+use kfs::{kassert, kassert_eq, serial_println};
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    kfs::tester::panic_handler(info)
+}
+
+/// Disk held in memory, counting the transfers it serves.
+struct RamDisk {
+    name: &'static str,
+    data: RefCell<Vec<u8>>,
+    reads: Cell<usize>,
+    writes: Cell<usize>,
+}
+
+impl RamDisk {
+    fn leak(name: &'static str, sectors: usize) -> &'static Self {
+        Box::leak(Box::new(Self {
+            name,
+            data: RefCell::new(vec![0; sectors * SECTOR_SIZE]),
+            reads: Cell::new(0),
+            writes: Cell::new(0),
+        }))
+    }
+
+    fn sector(&self, lba: usize) -> Vec<u8> {
+        self.data.borrow()[lba * SECTOR_SIZE..(lba + 1) * SECTOR_SIZE].to_vec()
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn sector_count(&self) -> u64 {
+        (self.data.borrow().len() / SECTOR_SIZE) as u64
+    }
+
+    fn read(&self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_range(self.sector_count(), lba, buf)?;
+        let start = lba as usize * SECTOR_SIZE;
+        buf.copy_from_slice(&self.data.borrow()[start..start + buf.len()]);
+        self.reads.set(self.reads.get() + 1);
+        Ok(())
+    }
+
+    fn write(&self, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+        block::check_range(self.sector_count(), lba, buf)?;
+        let start = lba as usize * SECTOR_SIZE;
+        self.data.borrow_mut()[start..start + buf.len()].copy_from_slice(buf);
+        self.writes.set(self.writes.get() + 1);
+        Ok(())
+    }
+}
+
+#[test_case]
+fn cached_sectors_are_read_once() -> Result<(), &'static str> {
+    let disk = RamDisk::leak("ram0", 8);
+    let mut buf = [0; 2 * SECTOR_SIZE];
+
+    cache::read(disk, 2, &mut buf).map_err(|_| "Could not read")?;
+    kassert_eq!(disk.reads.get(), 2);
+    cache::read(disk, 3, &mut buf).map_err(|_| "Could not read")?;
+    kassert_eq!(disk.reads.get(), 3);
+
+    kassert!(cache::read(disk, 7, &mut buf).is_err());
+
+    Ok(())
+}
+
+#[test_case]
+fn writes_reach_the_disk_on_sync() -> Result<(), &'static str> {
+    let disk = RamDisk::leak("ram1", 8);
+
+    cache::write(disk, 1, &[0xaa; SECTOR_SIZE]).map_err(|_| "Could not write")?;
+    kassert_eq!(disk.writes.get(), 0);
+    kassert_eq!(disk.sector(1), [0; SECTOR_SIZE]);
+
+    let mut buf = [0; SECTOR_SIZE];
+    cache::read(disk, 1, &mut buf).map_err(|_| "Could not read")?;
+    kassert_eq!(buf, [0xaa; SECTOR_SIZE]);
+    kassert_eq!(disk.reads.get(), 0);
+
+    cache::sync_device(disk).map_err(|_| "Could not sync")?;
+    kassert_eq!(disk.writes.get(), 1);
+    kassert_eq!(disk.sector(1), [0xaa; SECTOR_SIZE]);
+
+    Ok(())
+}
+
+#[test_case]
+fn least_recently_used_buffer_is_evicted() -> Result<(), &'static str> {
+    let disk = RamDisk::leak("ram2", MAX_BUFFERS + 1);
+    let mut buf = [0; SECTOR_SIZE];
+
+    cache::write(disk, 0, &[1; SECTOR_SIZE]).map_err(|_| "Could not write")?;
+    for lba in 1..=MAX_BUFFERS as u64 {
+        cache::read(disk, lba, &mut buf).map_err(|_| "Could not read")?;
+    }
+
+    // Sector 0 was evicted to make room for the last one, and written back on the way.
+    kassert_eq!(disk.sector(0), [1; SECTOR_SIZE]);
+    let reads = disk.reads.get();
+    cache::read(disk, 0, &mut buf).map_err(|_| "Could not read")?;
+    kassert_eq!(disk.reads.get(), reads + 1);
+
+    Ok(())
+}
+
+#[test_case]
+fn shrinker_releases_buffers() -> Result<(), &'static str> {
+    let disk = RamDisk::leak("ram3", 4);
+    cache::write(disk, 0, &[2; SECTOR_SIZE]).map_err(|_| "Could not write")?;
+    kassert!(cache::usage().0 > 0);
+
+    kassert!(oom::reclaim(0) > 0);
+    kassert_eq!(cache::usage(), (0, 0));
+    kassert_eq!(disk.sector(0), [2; SECTOR_SIZE]);
+
+    Ok(())
+}
+
+#[test_case]
+fn mbr_partitions_are_registered() -> Result<(), &'static str> {
+    let disk = RamDisk::leak("ram4", 64);
+    {
+        let mut data = disk.data.borrow_mut();
+        let entry = &mut data[446..462];
+        entry[4] = 0x83;
+        entry[8..12].copy_from_slice(&16_u32.to_le_bytes());
+        entry[12..16].copy_from_slice(&32_u32.to_le_bytes());
+        data[510..512].copy_from_slice(&[0x55, 0xaa]);
+    }
+
+    block::add_disk(disk).map_err(|_| "Could not add disk")?;
+    let part = block::find("ram41").ok_or("Partition not registered")?;
+    kassert_eq!(part.sector_count(), 32);
+    kassert_eq!(partition::partitions().count(), 1);
+
+    part.write(0, &[3; SECTOR_SIZE]).map_err(|_| "Could not write")?;
+    kassert_eq!(disk.sector(16), [3; SECTOR_SIZE]);
+    kassert!(part.read(32, &mut [0; SECTOR_SIZE]).is_err());
+
+    Ok(())
+}
+
+#[test_case]
+fn partition_sectors_are_cached_with_their_disk() -> Result<(), &'static str> {
+    let disk = block::find("ram4").ok_or("Disk not registered")?;
+    let part = block::find("ram41").ok_or("Partition not registered")?;
+    let mut buf = [0; SECTOR_SIZE];
+
+    cache::read(disk, 17, &mut buf).map_err(|_| "Could not read")?;
+    let (buffers, _) = cache::usage();
+    cache::write(part, 1, &[4; SECTOR_SIZE]).map_err(|_| "Could not write")?;
+    kassert_eq!(cache::usage(), (buffers, 1));
+
+    cache::read(disk, 17, &mut buf).map_err(|_| "Could not read")?;
+    kassert_eq!(buf, [4; SECTOR_SIZE]);
+
+    cache::sync_device(part).map_err(|_| "Could not sync")?;
+    kassert_eq!(cache::usage(), (buffers, 0));
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[unsafe(no_mangle)]
+pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
+    use kfs::{arch, qemu, vmm::paging::init::init_memory};
+
+    serial_println!("");
+
+    arch::x86::gdt::init();
+    arch::x86::idt::init();
+
+    init_memory(info);
+
+    if vmm::allocators::kmalloc::init().is_err() {
+        panic!("Failed to initialize dynamic memory allocation");
+    }
+    block::init();
+
+    test_main();
+
+    unsafe { qemu::exit(qemu::ExitCode::Success) };
+}
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![test_runner(kfs::tester::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use kfs::boot::MultibootInfo;
+use kfs::serial_println;
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    kfs::tester::expected_panic_handler(info, "kasan: DoubleFree")
+}
+
+#[cfg(feature = "kasan")]
+#[test_case]
+fn double_free_is_detected() -> Result<(), &'static str> {
+    use kfs::vmm::allocators::kmalloc::{kfree, kmalloc};
+
+    let p = kmalloc(64).map_err(|_| "Could not allocate")?;
+    let _ = unsafe { kfree(p) };
+    let _ = unsafe { kfree(p) };
+
+    Err("The double free was not detected")
+}
+
+#[cfg(test)]
+#[unsafe(no_mangle)]
+pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
+    use kfs::{arch, qemu, vmm, vmm::paging::init::init_memory};
+
+    serial_println!("");
+
+    arch::x86::gdt::init();
+    arch::x86::idt::init();
+
+    init_memory(info);
+
+    if vmm::allocators::kmalloc::init().is_err() {
+        panic!("Failed to initialize dynamic memory allocation");
+    }
+
+    test_main();
+
+    unsafe { qemu::exit(qemu::ExitCode::Success) };
+}
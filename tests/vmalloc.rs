@@ -0,0 +1,120 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![test_runner(kfs::tester::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use kfs::boot::{KERNEL_BASE, MultibootInfo};
+use kfs::kassert;
+use kfs::vmm::{
+    self,
+    allocators::vmalloc::{MAX_VMALLOC_AREAS, vfree, vmalloc},
+    paging::{PAGE_SIZE, mmap::virt_to_phys},
+};
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    kfs::tester::panic_handler(info)
+}
+
+#[test_case]
+fn large_area_is_usable() -> Result<(), &'static str> {
+    const SIZE: usize = 600 * 1024;
+
+    let p = vmalloc(SIZE).map_err(|_| "Could not vmalloc")?;
+    kassert!(p as usize >= KERNEL_BASE);
+
+    let area = unsafe { core::slice::from_raw_parts_mut(p, SIZE) };
+    for (idx, b) in area.iter_mut().enumerate() {
+        *b = idx as u8;
+    }
+    kassert!(area.iter().enumerate().all(|(idx, b)| *b == idx as u8));
+
+    vfree(p).map_err(|_| "Could not vfree")?;
+
+    Ok(())
+}
+
+#[test_case]
+fn pages_are_unmapped_on_free() -> Result<(), &'static str> {
+    let p = vmalloc(PAGE_SIZE * 4).map_err(|_| "Could not vmalloc")?;
+
+    for i in 0..4 {
+        kassert!(virt_to_phys(p as usize + i * PAGE_SIZE).is_ok());
+    }
+
+    vfree(p).map_err(|_| "Could not vfree")?;
+
+    for i in 0..4 {
+        kassert!(virt_to_phys(p as usize + i * PAGE_SIZE).is_err());
+    }
+
+    Ok(())
+}
+
+#[test_case]
+fn size_is_rounded_up_to_pages() -> Result<(), &'static str> {
+    let p = vmalloc(PAGE_SIZE + 1).map_err(|_| "Could not vmalloc")?;
+
+    kassert!(virt_to_phys(p as usize + PAGE_SIZE).is_ok());
+
+    vfree(p).map_err(|_| "Could not vfree")?;
+
+    Ok(())
+}
+
+#[test_case]
+fn invalid_frees_are_rejected() -> Result<(), &'static str> {
+    let p = vmalloc(PAGE_SIZE).map_err(|_| "Could not vmalloc")?;
+
+    kassert!(vfree(p.wrapping_add(1)).is_err());
+    kassert!(vfree(p).is_ok());
+    kassert!(vfree(p).is_err());
+
+    Ok(())
+}
+
+#[test_case]
+fn areas_are_limited() -> Result<(), &'static str> {
+    let mut ps = [core::ptr::null_mut(); MAX_VMALLOC_AREAS];
+
+    for p in ps.iter_mut() {
+        *p = vmalloc(PAGE_SIZE).map_err(|_| "Could not vmalloc")?;
+    }
+
+    let res = vmalloc(PAGE_SIZE);
+
+    for p in ps {
+        let _ = vfree(p);
+    }
+
+    kassert!(res.is_err());
+    kassert!(vmalloc(0).is_err());
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[unsafe(no_mangle)]
+pub extern "C" fn kmain(_magic: usize, info: &MultibootInfo) {
+    use kfs::{arch, qemu, serial_println, vmm::paging::init::init_memory};
+
+    serial_println!("");
+
+    arch::x86::gdt::init();
+    arch::x86::idt::init();
+
+    init_memory(info);
+
+    if vmm::allocators::kmalloc::init().is_err() {
+        panic!("Failed to initialize dynamic memory allocation");
+    }
+
+    test_main();
+
+    unsafe { qemu::exit(qemu::ExitCode::Success) };
+}
@@ -4,21 +4,27 @@ import argparse
 import json
 import logging
 import os
+import queue
+import re
 import subprocess
 import sys
+import threading
+import time
 import typing
 from pathlib import Path
 
 LOGGER = logging.getLogger("x")
 LOGLEVEL = os.environ.get("LOGLEVEL")
+FEATURES = ",".join(["test-utils", *filter(None, os.environ.get("FEATURES", "").split(","))])
 level = logging.DEBUG if LOGLEVEL == "DEBUG" else logging.INFO
 LOGGER.setLevel(level)
 logging.basicConfig(level=level, format="%(message)s")
 
 
-def run_with_output(commandline: list[str]) -> subprocess.CompletedProcess[bytes]:
+def run_with_output(commandline: list[str], env: dict[str, str] | None = None) -> subprocess.CompletedProcess[bytes]:
     return subprocess.run(
         commandline,
+        env={**os.environ, **(env or {})},
         stdout=sys.stdout if LOGGER.level <= logging.INFO else subprocess.DEVNULL,
         stderr=sys.stderr if LOGGER.level <= logging.DEBUG else subprocess.DEVNULL,
     )
@@ -29,12 +35,13 @@ def parse_args(argv: list[str]) -> argparse.Namespace:
     group = parser.add_argument_group("Test Type").add_mutually_exclusive_group(required=True)
     _ = group.add_argument("--unit-tests", action="store_true")
     _ = group.add_argument("--end-to-end-tests", action="store_true")
+    _ = group.add_argument("--shell-tests", action="store_true")
     return parser.parse_args(argv[1:])
 
 
 def build_tests() -> str:
     proc = subprocess.Popen(
-        ["cargo", "build", "--tests", "--release", "--lib", "--features", "test-utils", "--message-format=json", "-Zjson-target-spec"],
+        ["cargo", "build", "--tests", "--release", "--lib", "--features", FEATURES, "--message-format=json", "-Zjson-target-spec"],
         stdout=subprocess.PIPE,
         stderr=subprocess.PIPE,
         text=True,
@@ -85,6 +92,15 @@ def discover_e2e_tests():
 
 QEMU_ARGS = "-boot d -device isa-debug-exit,iobase=0xf4,iosize=0x04 -serial stdio -display none -m 4G"
 ISO_PATH = "./build/kernel.iso"
+DISK_PATH = Path("./build/test-disk.img")
+DISK_SIZE = 16 * 1024 * 1024
+
+
+def create_disk() -> None:
+    """Creates an empty raw disk image, so each suite starts from the same disk."""
+    DISK_PATH.parent.mkdir(parents=True, exist_ok=True)
+    with DISK_PATH.open("wb") as disk:
+        disk.truncate(DISK_SIZE)
 
 
 def run_tests(type: typing.Literal["E2E", "Unit"]):
@@ -100,14 +116,109 @@ def run_tests(type: typing.Literal["E2E", "Unit"]):
     for path in test_paths:
         LOGGER.info(f"Building ISO for {path}")
         proc = run_with_output(["./scripts/build_iso.sh", str(path)])
+        create_disk()
         LOGGER.info(f"Running tests for {path}")
-        proc = run_with_output(["./scripts/run.sh", ISO_PATH, QEMU_ARGS])
+        proc = run_with_output(["./scripts/run.sh", ISO_PATH, QEMU_ARGS], env={"DISK": str(DISK_PATH)})
         ok += int(proc.returncode == 0)
         ko += int(proc.returncode != 0)
 
     LOGGER.info(f"\n{type} test results: {'ok' if ko == 0 else 'FAILED'}. {ok} suite{'s' if ok > 1 else ''} passed; {ko} failed.")
 
 
+KERNEL_PATH = "./target/i386-unknown-none/release/kfs"
+# Menu entry of grub/grub.cfg passing `console=ttyS0` to the kernel.
+SERIAL_CONSOLE_ENTRY = "1"
+SHELL_TIMEOUT = 30
+# Commands typed on COM1, and a line their output must contain.
+SHELL_TESTS = [
+    ("echo hello from COM1", "hello from COM1"),
+    ("echo foo bar | grep bar", "foo bar"),
+    ("help | grep history", "history"),
+]
+ANSI_ESCAPE = re.compile(rb"\x1b\[[0-9;?]*[A-Za-z]")
+# Ctrl+U, to drop whatever is left on the prompt before typing a command.
+KILL_LINE = b"\x15"
+
+
+class SerialConsole:
+    """Kernel booted with its shell on COM1, which QEMU connects to its stdio."""
+
+    def __init__(self) -> None:
+        self.proc = subprocess.Popen(
+            ["./scripts/run.sh", ISO_PATH, QEMU_ARGS], env={**os.environ, "DISK": str(DISK_PATH)}, stdin=subprocess.PIPE, stdout=subprocess.PIPE
+        )
+        self.lines: queue.Queue[bytes] = queue.Queue()
+        threading.Thread(target=self._read, daemon=True).start()
+
+    def _read(self) -> None:
+        assert self.proc.stdout
+        for line in self.proc.stdout:
+            line = ANSI_ESCAPE.sub(b"", line).replace(b"\x08", b"").strip()
+            LOGGER.debug(line.decode(errors="replace"))
+            self.lines.put(line)
+
+    def send(self, command: str) -> None:
+        assert self.proc.stdin
+        self.proc.stdin.write(KILL_LINE + command.encode() + b"\r")
+        self.proc.stdin.flush()
+
+    def expect(self, line: str, timeout: float) -> bool:
+        """Waits for a line of output starting with `line`, which the echo of the typed command does not."""
+        deadline = time.monotonic() + timeout
+        while (remaining := deadline - time.monotonic()) > 0:
+            try:
+                if self.lines.get(timeout=remaining).startswith(line.encode()):
+                    return True
+            except queue.Empty:
+                break
+        return False
+
+    def wait_ready(self) -> bool:
+        """Bytes sent before the kernel listens on COM1 are lost, so a command is retried until it answers."""
+        deadline = time.monotonic() + SHELL_TIMEOUT
+        while time.monotonic() < deadline:
+            self.send("echo ready")
+            if self.expect("ready", 1):
+                return True
+        return False
+
+
+def run_shell_tests() -> None:
+    LOGGER.info("Building kernel...")
+    if run_with_output(["cargo", "build", "--release", "-Zjson-target-spec"]).returncode != 0:
+        raise RuntimeError("Could not build kernel")
+    run_with_output(["./scripts/build_iso.sh", KERNEL_PATH], env={"GRUB_DEFAULT": SERIAL_CONSOLE_ENTRY})
+    create_disk()
+
+    LOGGER.info("Running shell tests...")
+    console = SerialConsole()
+    ok = 0
+    ko = 0
+    try:
+        if not console.wait_ready():
+            raise RuntimeError("The shell did not answer on COM1")
+
+        for command, expected in SHELL_TESTS:
+            console.send(command)
+            passed = console.expect(expected, SHELL_TIMEOUT)
+            LOGGER.info(f"{command} ... {'ok' if passed else 'FAILED'}")
+            ok += int(passed)
+            ko += int(not passed)
+
+        console.send("exit")
+        if console.proc.wait(timeout=SHELL_TIMEOUT) != 0:
+            raise RuntimeError("The kernel did not exit cleanly")
+    finally:
+        console.proc.kill()
+
+    LOGGER.info(f"\nShell test results: {'ok' if ko == 0 else 'FAILED'}. {ok} passed; {ko} failed.")
+    if ko != 0:
+        sys.exit(1)
+
+
 if __name__ == "__main__":
     args = parse_args(sys.argv)
-    run_tests("Unit" if args.unit_tests else "E2E")
+    if args.shell_tests:
+        run_shell_tests()
+    else:
+        run_tests("Unit" if args.unit_tests else "E2E")